@@ -0,0 +1,671 @@
+//! Solving the whole collection of puzzles with the summary of the results.
+
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+#[cfg(feature = "threaded")]
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use log::info;
+
+use crate::{
+    block::{
+        binary::BinaryBlock,
+        multicolor::{ColoredBlock, WideColoredBlock},
+        Block,
+    },
+    board::Board,
+    parser::{BoardParser, DetectedParser, PuzzleFormat, PuzzleScheme},
+    solver::{
+        backtracking::{self, SearchOptions},
+        line::DynamicColor,
+        line::DynamicSolver,
+        probing::FullProbe1,
+        propagate, SolveError,
+    },
+    utils::{rc::MutRc, time},
+};
+
+/// How the solving of the single puzzle ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PuzzleStatus {
+    /// The solution found. If the timeout occurs after the first solution,
+    /// the puzzle still considered solved, though it can have more solutions.
+    Solved,
+    /// The puzzle has no solutions.
+    Unsolved,
+    /// The puzzle has more than one solution.
+    Multiple,
+    /// The timeout occurs before any solution found.
+    Timeout,
+    /// The puzzle cannot be read, parsed or its clues are invalid.
+    Error(String),
+}
+
+impl PuzzleStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Solved => "solved",
+            Self::Unsolved => "unsolved",
+            Self::Multiple => "multiple",
+            Self::Timeout => "timeout",
+            Self::Error(_) => "error",
+        }
+    }
+
+    fn error(&self) -> Option<&str> {
+        if let Self::Error(message) = self {
+            Some(message)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for PuzzleStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The result of solving the single puzzle file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PuzzleReport {
+    pub path: PathBuf,
+    /// The detected format of the file (`None` if it cannot be parsed).
+    pub format: Option<PuzzleFormat>,
+    pub scheme: Option<PuzzleScheme>,
+    /// The width and the height of the board.
+    pub size: Option<(usize, usize)>,
+    pub status: PuzzleStatus,
+    /// The part of the board solved (from 0 to 1).
+    pub solution_rate: f64,
+    /// The solving time (`None` if the `std_time` feature is disabled).
+    pub time: Option<Duration>,
+    /// The maximum depth of the backtracking search (0 if the search was not needed).
+    pub search_depth: usize,
+}
+
+impl PuzzleReport {
+    fn with_error(path: &Path, error: String) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            format: None,
+            scheme: None,
+            size: None,
+            status: PuzzleStatus::Error(error),
+            solution_rate: 0.0,
+            time: None,
+            search_depth: 0,
+        }
+    }
+}
+
+/// Solve the puzzle stored in the file, detecting its format automatically.
+///
+/// The search stops after the second solution found or when the `timeout` (in seconds) occurs.
+/// Any error gets reported in the `status` instead of returned.
+pub fn solve_file(path: &Path, timeout: Option<u32>, options: SearchOptions) -> PuzzleReport {
+    info!("Solving {:?}", path);
+
+    let parser = match fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|content| DetectedParser::with_content(&content).map_err(|err| err.0))
+    {
+        Ok(parser) => parser,
+        Err(err) => return PuzzleReport::with_error(path, err),
+    };
+
+    let scheme = parser.infer_scheme();
+    let mut report = match scheme {
+        PuzzleScheme::BlackAndWhite => solve_parsed::<BinaryBlock>(path, &parser, timeout, options),
+        PuzzleScheme::MultiColor => solve_parsed::<ColoredBlock>(path, &parser, timeout, options),
+        PuzzleScheme::WideColor => {
+            solve_parsed::<WideColoredBlock>(path, &parser, timeout, options)
+        }
+    };
+    report.format = Some(parser.format());
+    report.scheme = Some(scheme);
+    report
+}
+
+fn solve_parsed<B>(
+    path: &Path,
+    parser: &DetectedParser,
+    timeout: Option<u32>,
+    options: SearchOptions,
+) -> PuzzleReport
+where
+    B: 'static + Block,
+    B::Color: DynamicColor,
+{
+    let board = match parser.parse::<B>() {
+        Ok(mut board) => {
+            board.reduce_colors();
+            MutRc::new(board)
+        }
+        Err(err) => return PuzzleReport::with_error(path, err.0),
+    };
+
+    let start = time::now();
+    let (status, search_depth) = match solve_board(&board, timeout, options) {
+        Ok(result) => result,
+        Err(err) if err.is_contradiction() => (PuzzleStatus::Unsolved, 0),
+        Err(err) => (PuzzleStatus::Error(err.to_string()), 0),
+    };
+
+    let board = board.read();
+    PuzzleReport {
+        path: path.to_path_buf(),
+        format: None,
+        scheme: None,
+        size: Some((board.width(), board.height())),
+        status,
+        solution_rate: board.solution_rate(),
+        time: start.map(|start| start.elapsed()),
+        search_depth,
+    }
+}
+
+fn solve_board<B>(
+    board: &MutRc<Board<B>>,
+    timeout: Option<u32>,
+    options: SearchOptions,
+) -> Result<(PuzzleStatus, usize), SolveError>
+where
+    B: 'static + Block,
+    B::Color: DynamicColor,
+{
+    if propagate::<_, DynamicSolver<_>>(board)? {
+        return Ok((PuzzleStatus::Solved, 0));
+    }
+
+    let mut solver = backtracking::Solver::<_, FullProbe1<_>, DynamicSolver<_>>::with_options(
+        MutRc::clone(board),
+        Some(2),
+        timeout,
+        None,
+    );
+    solver.set_search_options(options);
+    solver.run()?;
+
+    let status = match solver.solutions.len() {
+        0 if solver.exceeded_limit().is_some() => PuzzleStatus::Timeout,
+        0 => PuzzleStatus::Unsolved,
+        1 => {
+            Board::restore_with_callback(board, solver.solutions[0].clone());
+            PuzzleStatus::Solved
+        }
+        _ => PuzzleStatus::Multiple,
+    };
+    Ok((status, solver.depth_reached()))
+}
+
+/// The files in the directory (not recursively) in the order of their names.
+fn puzzle_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Solve every file in the directory (not recursively) in the order of their names.
+pub fn solve_dir(
+    dir: &Path,
+    timeout: Option<u32>,
+    options: SearchOptions,
+) -> io::Result<Vec<PuzzleReport>> {
+    Ok(puzzle_files(dir)?
+        .iter()
+        .map(|path| solve_file(path, timeout, options))
+        .collect())
+}
+
+/// The same as the main thread has: the search is recursive.
+#[cfg(feature = "threaded")]
+const WORKER_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Solve the files with the pool of `workers` threads.
+///
+/// Every report is sent into the returned channel as soon as ready,
+/// along with the index of its file in the `paths`.
+/// The channel gets closed when all the files are processed.
+/// If the solver panics on some puzzle, the panic gets reported as its error
+/// (unless the panics abort the process) and the worker proceeds to the next file.
+#[cfg(feature = "threaded")]
+pub fn solve_files_parallel(
+    paths: Vec<PathBuf>,
+    timeout: Option<u32>,
+    options: SearchOptions,
+    workers: usize,
+) -> mpsc::Receiver<(usize, PuzzleReport)> {
+    assert!(workers > 0, "At least one worker is required");
+
+    let jobs = Arc::new(Mutex::new(paths.into_iter().enumerate()));
+    let (sender, receiver) = mpsc::channel();
+
+    for worker in 0..workers {
+        let (jobs, sender) = (Arc::clone(&jobs), sender.clone());
+        let _handle = thread::Builder::new()
+            .name(format!("batch-worker-{}", worker))
+            .stack_size(WORKER_STACK_SIZE)
+            .spawn(move || loop {
+                // the lock is released before solving
+                let job = jobs.lock().expect("The jobs queue is poisoned").next();
+                let (index, path) = match job {
+                    Some(job) => job,
+                    None => break,
+                };
+
+                if sender
+                    .send((index, solve_file_catching(&path, timeout, options)))
+                    .is_err()
+                {
+                    // nobody waits for the results anymore
+                    break;
+                }
+            })
+            .expect("Cannot spawn the worker thread");
+    }
+
+    receiver
+}
+
+#[cfg(feature = "threaded")]
+fn solve_file_catching(path: &Path, timeout: Option<u32>, options: SearchOptions) -> PuzzleReport {
+    // the board is dropped along with the panic, so nothing broken is observed after it
+    panic::catch_unwind(AssertUnwindSafe(|| solve_file(path, timeout, options)))
+        .unwrap_or_else(|payload| PuzzleReport::with_error(path, panic_message(&*payload)))
+}
+
+#[cfg(feature = "threaded")]
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|message| (*message).to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown error".to_string());
+    format!("The solver panicked: {}", message)
+}
+
+/// Solve every file in the directory with the pool of `workers` threads.
+/// The reports are in the same order as the `solve_dir` gives.
+#[cfg(feature = "threaded")]
+pub fn solve_dir_parallel(
+    dir: &Path,
+    timeout: Option<u32>,
+    options: SearchOptions,
+    workers: usize,
+) -> io::Result<Vec<PuzzleReport>> {
+    let mut reports: Vec<_> = solve_files_parallel(puzzle_files(dir)?, timeout, options, workers)
+        .into_iter()
+        .collect();
+    reports.sort_by_key(|&(index, _)| index);
+    Ok(reports.into_iter().map(|(_index, report)| report).collect())
+}
+
+/// The format of the summary written by the [`SummaryFormat::write`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SummaryFormat {
+    Csv,
+    Json,
+}
+
+impl SummaryFormat {
+    const NAMES: [(Self, &'static str); 2] = [(Self::Csv, "csv"), (Self::Json, "json")];
+
+    const COLUMNS: [&'static str; 10] = [
+        "file",
+        "format",
+        "scheme",
+        "width",
+        "height",
+        "status",
+        "solution_rate",
+        "time",
+        "search_depth",
+        "error",
+    ];
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find_map(|&(format, name)| if format == self { Some(name) } else { None })
+            .expect("Every format should have a name")
+    }
+
+    /// The names of all the formats.
+    pub fn variants() -> impl Iterator<Item = &'static str> {
+        Self::NAMES.iter().map(|&(_format, name)| name)
+    }
+
+    /// Write the summary for all the `reports`:
+    /// the CSV with the header line or the JSON array of objects.
+    pub fn write<W>(self, reports: &[PuzzleReport], mut writer: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        match self {
+            Self::Csv => {
+                writeln!(writer, "{}", Self::COLUMNS.join(","))?;
+                for report in reports {
+                    let values: Vec<_> = Field::all(report).iter().map(Field::to_csv).collect();
+                    writeln!(writer, "{}", values.join(","))?;
+                }
+            }
+            Self::Json => {
+                writeln!(writer, "[")?;
+                for (i, report) in reports.iter().enumerate() {
+                    let fields: Vec<_> = Self::COLUMNS
+                        .iter()
+                        .zip(Field::all(report))
+                        .map(|(name, value)| format!("{}:{}", json_string(name), value.to_json()))
+                        .collect();
+                    let separator = if i + 1 < reports.len() { "," } else { "" };
+                    writeln!(writer, "  {{{}}}{}", fields.join(","), separator)?;
+                }
+                writeln!(writer, "]")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SummaryFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for SummaryFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        Self::NAMES
+            .iter()
+            .find_map(|&(format, name)| if name == s { Some(format) } else { None })
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::variants().collect();
+                format!(
+                    "Unknown summary format {:?}: expected one of {:?}",
+                    s, names
+                )
+            })
+    }
+}
+
+/// The single value of the summary.
+enum Field {
+    Text(String),
+    Number(String),
+    Missing,
+}
+
+impl Field {
+    /// The values of the `SummaryFormat::COLUMNS`.
+    fn all(report: &PuzzleReport) -> Vec<Self> {
+        let text = |value: Option<String>| value.map_or(Self::Missing, Self::Text);
+        let number = |value: Option<String>| value.map_or(Self::Missing, Self::Number);
+
+        vec![
+            Self::Text(report.path.display().to_string()),
+            text(report.format.map(|format| format.name().to_string())),
+            text(report.scheme.map(|scheme| scheme.to_string())),
+            number(report.size.map(|(width, _height)| width.to_string())),
+            number(report.size.map(|(_width, height)| height.to_string())),
+            Self::Text(report.status.name().to_string()),
+            Self::Number(format!("{:.4}", report.solution_rate)),
+            number(report.time.map(|time| format!("{:.3}", time.as_secs_f64()))),
+            Self::Number(report.search_depth.to_string()),
+            text(report.status.error().map(ToString::to_string)),
+        ]
+    }
+
+    fn to_csv(&self) -> String {
+        match self {
+            Self::Text(value) => {
+                if value.contains(&[',', '"', '\n', '\r'][..]) {
+                    format!("\"{}\"", value.replace('"', "\"\""))
+                } else {
+                    value.clone()
+                }
+            }
+            Self::Number(value) => value.clone(),
+            Self::Missing => String::new(),
+        }
+    }
+
+    fn to_json(&self) -> String {
+        match self {
+            Self::Text(value) => json_string(value),
+            Self::Number(value) => value.clone(),
+            Self::Missing => "null".to_string(),
+        }
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process, time::Duration};
+
+    use crate::parser::{PuzzleFormat, PuzzleScheme};
+
+    use super::{solve_dir, PuzzleReport, PuzzleStatus, SearchOptions, SummaryFormat};
+
+    fn puzzles_dir(name: &str, puzzles: &[(&str, &str)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("nonogrid-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file_name, content) in puzzles {
+            fs::write(dir.join(file_name), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn every_status() {
+        let dir = puzzles_dir(
+            "batch",
+            &[
+                ("1.txt", "rows\n1 1\n1 1\n3\n\ncolumns\n3\n1\n3\n"),
+                ("2.txt", "rows\n1\n1\n\ncolumns\n1\n1\n"),
+                ("3.txt", "rows\n3\n\ncolumns\n1\n"),
+                ("4.txt", "garbage"),
+            ],
+        );
+        let reports = solve_dir(&dir, None, SearchOptions::default()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let statuses: Vec<_> = reports.iter().map(|report| report.status.name()).collect();
+        assert_eq!(statuses, vec!["solved", "multiple", "error", "error"]);
+
+        let solved = &reports[0];
+        assert_eq!(solved.path, dir.join("1.txt"));
+        assert_eq!(solved.format, Some(PuzzleFormat::Simple));
+        assert_eq!(solved.scheme, Some(PuzzleScheme::BlackAndWhite));
+        assert_eq!(solved.size, Some((3, 3)));
+        assert!((solved.solution_rate - 1.0).abs() < f64::EPSILON);
+        assert_eq!(solved.search_depth, 0);
+
+        assert!(reports[1].search_depth > 0);
+        // the clues are invalid, but the puzzle itself is parsed
+        assert_eq!(reports[2].size, Some((1, 1)));
+        assert_eq!(reports[3].format, None);
+    }
+
+    #[test]
+    fn malformed_clues() {
+        let dir = puzzles_dir(
+            "malformed",
+            &[
+                ("1.txt", "rows\n1 x\n\ncolumns\n1\n"),
+                ("2.txt", "rows\n1\n\ncolumns\n1\n"),
+            ],
+        );
+        let reports = solve_dir(&dir, None, SearchOptions::default()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(reports.len(), 2);
+        assert_eq!(
+            reports[0].status,
+            PuzzleStatus::Error("Non-integer block size given: \"x\"".to_string())
+        );
+        assert_eq!(reports[0].format, Some(PuzzleFormat::Simple));
+        assert_eq!(reports[0].size, None);
+        assert_eq!(reports[1].status.name(), "solved");
+    }
+
+    #[cfg(feature = "threaded")]
+    #[test]
+    fn parallel_reports_the_panic() {
+        let dir = puzzles_dir(
+            "panic",
+            &[
+                ("1.html", "var d=[[1,x]];"),
+                ("2.txt", "rows\n1\n\ncolumns\n1\n"),
+                ("3.txt", "rows\n1\n\ncolumns\n1\n"),
+            ],
+        );
+        // the single worker has to survive the panic to solve the rest
+        let reports = super::solve_dir_parallel(&dir, None, SearchOptions::default(), 1).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(reports.len(), 3);
+        assert_eq!(
+            reports[0].status,
+            PuzzleStatus::Error(
+                "The solver panicked: The items should be positive integers: ParseIntError { kind: InvalidDigit }"
+                    .to_string()
+            )
+        );
+        assert_eq!(reports[1].status.name(), "solved");
+        assert_eq!(reports[2].status.name(), "solved");
+    }
+
+    fn report(path: &str, status: PuzzleStatus) -> PuzzleReport {
+        PuzzleReport {
+            path: PathBuf::from(path),
+            format: Some(PuzzleFormat::Toml),
+            scheme: Some(PuzzleScheme::MultiColor),
+            size: Some((10, 5)),
+            status,
+            solution_rate: 0.5,
+            time: Some(Duration::from_millis(1500)),
+            search_depth: 2,
+        }
+    }
+
+    #[test]
+    fn csv() {
+        let reports = vec![
+            report("a.toml", PuzzleStatus::Timeout),
+            report("b,c.toml", PuzzleStatus::Error("bad \"clue\"".to_string())),
+        ];
+        let mut summary = vec![];
+        SummaryFormat::Csv.write(&reports, &mut summary).unwrap();
+        assert_eq!(
+            String::from_utf8(summary).unwrap(),
+            "file,format,scheme,width,height,status,solution_rate,time,search_depth,error\n\
+             a.toml,toml,multicolor,10,5,timeout,0.5000,1.500,2,\n\
+             \"b,c.toml\",toml,multicolor,10,5,error,0.5000,1.500,2,\"bad \"\"clue\"\"\"\n"
+        );
+    }
+
+    #[test]
+    fn json() {
+        let mut unparsed = report("b\\c.txt", PuzzleStatus::Error("bad\nfile".to_string()));
+        unparsed.format = None;
+        unparsed.time = None;
+        let reports = vec![report("a.toml", PuzzleStatus::Solved), unparsed];
+
+        let mut summary = vec![];
+        SummaryFormat::Json.write(&reports, &mut summary).unwrap();
+        let summary = String::from_utf8(summary).unwrap();
+        let lines: Vec<_> = summary.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "[");
+        assert_eq!(
+            lines[1],
+            r#"  {"file":"a.toml","format":"toml","scheme":"multicolor","width":10,"height":5,"status":"solved","solution_rate":0.5000,"time":1.500,"search_depth":2,"error":null},"#
+        );
+        assert_eq!(
+            lines[2],
+            r#"  {"file":"b\\c.txt","format":null,"scheme":"multicolor","width":10,"height":5,"status":"error","solution_rate":0.5000,"time":null,"search_depth":2,"error":"bad\nfile"}"#
+        );
+        assert_eq!(lines[3], "]");
+    }
+
+    #[cfg(feature = "threaded")]
+    #[test]
+    fn parallel_in_the_same_order() {
+        let puzzles: Vec<_> = (0..10)
+            .map(|i| {
+                let puzzle = if i % 2 == 0 {
+                    "rows\n1 1\n1 1\n3\n\ncolumns\n3\n1\n3\n"
+                } else {
+                    "rows\n1\n1\n1\n1\n\ncolumns\n1\n1\n1\n1\n"
+                };
+                (format!("{}.txt", i), puzzle)
+            })
+            .collect();
+        let puzzles: Vec<_> = puzzles
+            .iter()
+            .map(|(name, puzzle)| (name.as_str(), *puzzle))
+            .collect();
+        let dir = puzzles_dir("parallel", &puzzles);
+
+        let sequential = solve_dir(&dir, None, SearchOptions::default()).unwrap();
+        let parallel = super::solve_dir_parallel(&dir, None, SearchOptions::default(), 3).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(parallel.len(), 10);
+        for (one, other) in sequential.iter().zip(&parallel) {
+            assert_eq!(one.path, other.path);
+            assert_eq!(one.status, other.status);
+            assert_eq!(one.search_depth, other.search_depth);
+        }
+    }
+
+    #[test]
+    fn summary_format_names() {
+        for name in SummaryFormat::variants() {
+            let format: SummaryFormat = name.parse().unwrap();
+            assert_eq!(format.to_string(), name);
+        }
+        assert!("xml".parse::<SummaryFormat>().is_err());
+    }
+}
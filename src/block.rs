@@ -1,4 +1,4 @@
-pub use base::{Block, Color, Description};
+pub use base::{Block, Clue, Color, Description};
 
 use crate::utils::rc::ReadRc;
 
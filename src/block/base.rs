@@ -1,5 +1,5 @@
 use std::{
-    fmt::Debug,
+    fmt::{self, Debug},
     hash::Hash,
     marker::Sized,
     ops::{Add, Range, Sub},
@@ -20,6 +20,7 @@ where
         + Copy
         + Send
         + Sync
+        + 'static
         + Add<Output = Self>
         + Sub<Output = Result<Self, String>>,
 {
@@ -39,13 +40,15 @@ where
 
 pub trait Block
 where
-    Self: Debug + Eq + Hash + Default + Copy + Send + Sync,
+    Self: Debug + Eq + Hash + Default + Copy + Send + Sync + 'static,
 {
     type Color: Color;
 
-    fn from_str_and_color(s: &str, color: Option<ColorId>) -> Self {
-        let size = s.parse().expect("Non-integer block size given");
-        Self::from_size_and_color(size, color)
+    fn from_str_and_color(s: &str, color: Option<ColorId>) -> Result<Self, String> {
+        let size = s
+            .parse()
+            .map_err(|_| format!("Non-integer block size given: {:?}", s))?;
+        Ok(Self::from_size_and_color(size, color))
     }
 
     fn from_size_and_color(size: usize, color: Option<ColorId>) -> Self;
@@ -57,9 +60,53 @@ where
     fn color(self) -> Self::Color;
 }
 
+/// The single item of the line's clue.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Clue<T: Block> {
+    Block(T),
+    /// The hidden number that stands for zero or more blocks of the given color.
+    Blot(T::Color),
+}
+
+impl<T> Clue<T>
+where
+    T: Block,
+{
+    /// Parse the block size or the `?` blot.
+    pub fn from_str_and_color(s: &str, color: Option<ColorId>) -> Result<Self, String> {
+        if s == "?" {
+            Ok(Self::Blot(T::from_size_and_color(0, color).color()))
+        } else {
+            T::from_str_and_color(s, color).map(Self::Block)
+        }
+    }
+
+    pub fn color(self) -> T::Color {
+        match self {
+            Self::Block(block) => block.color(),
+            Self::Blot(color) => color,
+        }
+    }
+}
+
+impl<T> fmt::Display for Clue<T>
+where
+    T: Block,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Block(block) => write!(f, "{}", block.size()),
+            Self::Blot(_) => write!(f, "?"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct Description<T: Block> {
+    /// The known blocks.
     pub vec: Vec<T>,
+    /// The blots along with the index of the known block they precede.
+    blots: Vec<(usize, T::Color)>,
 }
 
 impl<T> Description<T>
@@ -67,9 +114,76 @@ where
     T: Block,
 {
     pub fn new(mut vec: Vec<T>) -> Self {
-        let zero = T::default();
-        vec.retain(|x| *x != zero);
-        Self { vec }
+        // the colored block of zero size is empty too
+        vec.retain(|x| x.size() > 0);
+        Self { vec, blots: vec![] }
+    }
+
+    /// The description where some of the numbers can be blotted.
+    pub fn with_clues(clues: Vec<Clue<T>>) -> Self {
+        let mut this = Self::new(vec![]);
+        for clue in clues {
+            match clue {
+                Clue::Block(block) => {
+                    if block.size() > 0 {
+                        this.vec.push(block);
+                    }
+                }
+                Clue::Blot(color) => this.blots.push((this.vec.len(), color)),
+            }
+        }
+        this
+    }
+
+    /// Whether the exact number of blocks is unknown.
+    pub fn is_blotted(&self) -> bool {
+        !self.blots.is_empty()
+    }
+
+    /// All the blocks and blots in their order.
+    pub fn clues(&self) -> Vec<Clue<T>> {
+        let mut blots = self.blots.iter().peekable();
+        let mut clues = Vec::with_capacity(self.vec.len() + self.blots.len());
+        for (index, &block) in self.vec.iter().enumerate() {
+            while let Some(&&(before, color)) = blots.peek() {
+                if before > index {
+                    break;
+                }
+                clues.push(Clue::Blot(color));
+                let _ = blots.next();
+            }
+            clues.push(Clue::Block(block));
+        }
+        clues.extend(blots.map(|&(_, color)| Clue::Blot(color)));
+        clues
+    }
+
+    /// Whether the blocks of the actual line fit the clues:
+    /// every blot stands for any number of the blocks of its color.
+    pub fn matches(&self, actual: &[T]) -> bool {
+        if !self.is_blotted() {
+            return self.vec == actual;
+        }
+
+        // the prefixes of the actual blocks fitting the already seen clues
+        let mut matched = vec![false; actual.len() + 1];
+        matched[0] = true;
+        for clue in self.clues() {
+            let mut next = vec![false; actual.len() + 1];
+            for index in 0..=actual.len() {
+                next[index] = match clue {
+                    Clue::Block(block) => {
+                        index > 0 && matched[index - 1] && actual[index - 1] == block
+                    }
+                    Clue::Blot(color) => {
+                        matched[index]
+                            || (index > 0 && next[index - 1] && actual[index - 1].color() == color)
+                    }
+                };
+            }
+            matched = next;
+        }
+        matched[actual.len()]
     }
 
     /// Generate clues for the given line of color codes
@@ -100,7 +214,9 @@ where
     pub fn colors(&self) -> impl Iterator<Item = ColorId> + '_ {
         self.vec
             .iter()
-            .filter_map(|block| block.color().as_color_id())
+            .map(|block| block.color())
+            .chain(self.blots.iter().map(|&(_, color)| color))
+            .filter_map(Color::as_color_id)
     }
 }
 
@@ -150,7 +266,7 @@ where
     }
 
     /// How long should be the minimal line to contain given description?
-    fn min_space(&self) -> usize {
+    pub fn min_space(&self) -> usize {
         if self.vec.is_empty() {
             return 0;
         }
@@ -171,6 +287,11 @@ where
 
     /// For every color in the given description produce a valid position range
     pub fn color_ranges(&self, line_length: usize) -> HashMap<ColorId, Range<usize>> {
+        if self.is_blotted() {
+            // the blots can hide the blocks of any color anywhere
+            return self.colors().map(|color| (color, 0..line_length)).collect();
+        }
+
         let start_indexes = self.block_starts();
         let sums = B::partial_sums(&self.vec);
         let slack_space = self.positions_number(line_length) - 1;
@@ -183,7 +304,7 @@ where
                 let mut last_index = None;
                 for (block_index, block) in self.vec.iter().enumerate() {
                     if block.color().as_color_id() == Some(color) {
-                        if first_index == None {
+                        if first_index.is_none() {
                             first_index = Some(block_index);
                         }
                         last_index = Some(block_index);
@@ -205,6 +326,8 @@ where
 }
 
 pub mod color {
+    use std::fmt;
+
     use super::HashMap;
 
     #[derive(Debug, PartialEq, Clone)]
@@ -222,10 +345,9 @@ pub mod color {
 
     impl ColorValue {
         pub fn parse(value: &str) -> Self {
-            #[allow(unused_imports)]
             use crate::utils::Stripper; // for Rust<1.45
 
-            let value = value.strip_prefix('#').unwrap_or(value);
+            let value = Stripper::strip_prefix(value, '#').unwrap_or(value);
 
             if value.len() == 3 {
                 let hex3 = u16::from_str_radix(value, 16);
@@ -295,9 +417,29 @@ pub mod color {
                 },
             }
         }
+
+        /// The color in the form of `#RRGGBB`.
+        pub fn to_hex(&self) -> String {
+            let (r, g, b) = self.to_rgb();
+            format!("#{:02X}{:02X}{:02X}", r, g, b)
+        }
+    }
+
+    /// The form that `ColorValue::parse` understands.
+    impl fmt::Display for ColorValue {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Self::CommonName(name) => write!(f, "{}", name),
+                Self::RgbTriplet(r, g, b) => write!(f, "{}, {}, {}", r, g, b),
+                Self::HexValue3(hex3) => write!(f, "#{:03X}", hex3),
+                Self::HexValue6(hex6) => write!(f, "#{:06X}", hex6),
+            }
+        }
     }
 
-    pub type ColorId = u32;
+    /// Every color of the palette is a separate bit,
+    /// so the puzzle can have up to 127 colors besides the white one.
+    pub type ColorId = u128;
 
     #[derive(Debug, Clone)]
     pub struct ColorDesc {
@@ -324,6 +466,19 @@ pub mod color {
         pub fn rgb_value(&self) -> (u8, u8, u8) {
             self.value.to_rgb()
         }
+
+        pub fn id(&self) -> ColorId {
+            self.id
+        }
+
+        /// The name of the color as it was defined in the puzzle.
+        pub fn defined_name(&self) -> &str {
+            &self.name
+        }
+
+        pub fn value(&self) -> &ColorValue {
+            &self.value
+        }
     }
 
     #[derive(Debug, Clone)]
@@ -335,10 +490,13 @@ pub mod color {
 
     impl ColorPalette {
         pub const WHITE_ID: ColorId = 1;
+        /// How many colors fit into the `ColorId` besides the white one.
+        pub const MAX_COLORS: usize = 127;
 
         pub fn with_white_and_black(white_name: &str, black_name: &str) -> Self {
             let mut this = Self::with_white(white_name);
-            this.color_with_name_value_and_symbol(black_name, ColorValue::HexValue3(0x000), 'X');
+            this.color_with_name_value_and_symbol(black_name, ColorValue::HexValue3(0x000), 'X')
+                .expect("The second color always fits");
             this.set_default(black_name).unwrap();
             this
         }
@@ -360,14 +518,11 @@ pub mod color {
         }
 
         fn with_colors(colors: HashMap<String, ColorDesc>) -> Self {
-            let symbols = (0_u8..0xFF)
-                .filter_map(|ch| {
-                    if ch.is_ascii_punctuation() {
-                        Some(ch.into())
-                    } else {
-                        None
-                    }
-                })
+            // the letters and digits only come when the punctuation is exhausted
+            let chars = || (0_u8..0xFF).map(char::from);
+            let symbols = chars()
+                .filter(char::is_ascii_punctuation)
+                .chain(chars().filter(|ch| ch.is_alphanumeric()))
                 .collect();
 
             Self {
@@ -399,6 +554,13 @@ pub mod color {
             self.vec.get(name).map(|desc| desc.id)
         }
 
+        /// All the colors ordered by their ids.
+        pub fn colors(&self) -> Vec<&ColorDesc> {
+            let mut colors: Vec<_> = self.vec.values().collect();
+            colors.sort_unstable_by_key(|color_desc| color_desc.id);
+            colors
+        }
+
         pub fn desc_by_id(&self, id: ColorId) -> Option<ColorDesc> {
             self.vec
                 .values()
@@ -428,22 +590,36 @@ pub mod color {
             name: &str,
             value: ColorValue,
             symbol: char,
-        ) {
+        ) -> Result<(), String> {
             let current_max = self.vec.values().map(|color| color.id).max();
-            let id = current_max.map_or(1, |val| val * 2);
+            let id = current_max.map_or(Some(1), |val| val.checked_mul(2));
+            let id = id.ok_or_else(|| {
+                format!(
+                    "Cannot create color {}: no more than {} colors are supported",
+                    name,
+                    Self::MAX_COLORS
+                )
+            })?;
             self.color_with_name_value_symbol_and_id(name, value, symbol, id);
+            Ok(())
         }
 
-        pub fn color_with_name_and_value(&mut self, name: &str, value: ColorValue) {
+        pub fn color_with_name_and_value(
+            &mut self,
+            name: &str,
+            value: ColorValue,
+        ) -> Result<(), String> {
             let occupied_symbols: Vec<_> = self.vec.values().map(|color| color.symbol).collect();
 
             let &next_symbol = self
                 .symbols
                 .iter()
                 .find(|available_symbol| !occupied_symbols.contains(available_symbol))
-                .expect("Cannot create color: No more symbols available.");
+                .ok_or_else(|| {
+                    format!("Cannot create color {}: no more symbols available", name)
+                })?;
 
-            self.color_with_name_value_and_symbol(name, value, next_symbol);
+            self.color_with_name_value_and_symbol(name, value, next_symbol)
         }
     }
 }
@@ -454,6 +630,19 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn blotted_matches() {
+        let blot = || Clue::Blot(BinaryBlock(0).color());
+        let desc = Description::with_clues(vec![blot(), Clue::Block(BinaryBlock(2)), blot()]);
+
+        let blocks = |sizes: &[usize]| sizes.iter().map(|&n| BinaryBlock(n)).collect::<Vec<_>>();
+        assert!(desc.matches(&blocks(&[2])));
+        assert!(desc.matches(&blocks(&[1, 3, 2])));
+        assert!(desc.matches(&blocks(&[2, 1, 1])));
+        assert!(!desc.matches(&blocks(&[])));
+        assert!(!desc.matches(&blocks(&[1, 3])));
+    }
+
     #[test]
     fn block_starts_empty_binary() {
         let d = Description::new(Vec::<BinaryBlock>::new());
@@ -526,7 +715,7 @@ mod tests {
 
 #[cfg(test)]
 mod color_value_tests {
-    use super::color::ColorValue;
+    use super::color::{ColorPalette, ColorValue};
 
     #[test]
     fn construct() {
@@ -564,4 +753,37 @@ mod color_value_tests {
             ColorValue::parse("5b8").to_rgb()
         );
     }
+
+    #[test]
+    fn palette_limit() {
+        let mut palette = ColorPalette::with_white("W");
+        for i in 0..ColorPalette::MAX_COLORS {
+            let name = format!("color-{}", i);
+            palette
+                .color_with_name_and_value(&name, ColorValue::parse("f00"))
+                .unwrap();
+        }
+        assert_eq!(palette.colors().len(), ColorPalette::MAX_COLORS + 1);
+
+        let err = palette
+            .color_with_name_value_and_symbol("extra", ColorValue::parse("f00"), '*')
+            .unwrap_err();
+        assert!(err.contains("no more than 127 colors"));
+        assert!(palette.id_by_name("extra").is_none());
+    }
+
+    #[test]
+    fn display_and_parse_back() {
+        for value in &["#0F0", "#0000FF", "white", "200, 16, 0"] {
+            let color = ColorValue::parse(value);
+            assert_eq!(&color.to_string(), value);
+            assert_eq!(ColorValue::parse(&color.to_string()), color);
+        }
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(ColorValue::parse("0F0").to_hex(), "#00FF00");
+        assert_eq!(ColorValue::parse("navy").to_hex(), "#000080");
+    }
 }
@@ -12,14 +12,20 @@ use crate::{
         },
         Block, Color, Description, Line,
     },
+    solver::SolveError,
     utils::{
         dedup,
         rc::{mutate_ref, InteriorMutableRef, MutRc, ReadRc},
     },
 };
 
+pub use self::reduce::BinaryDowngrade;
+pub(crate) use self::reduce::Reduction;
+
 use self::callbacks::{ChangeColorCallback, RestoreCallback, SetLineCallback};
 
+mod reduce;
+
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, PartialOrd, Ord)]
 pub struct Point {
     pub x: usize,
@@ -140,6 +146,7 @@ where
     on_set_line: Option<Box<dyn SetLineCallback>>,
     on_restore: Option<Box<dyn RestoreCallback>>,
     on_change_color: Option<Box<dyn ChangeColorCallback>>,
+    on_unset_color: Option<Box<dyn ChangeColorCallback>>,
 }
 
 impl<B> fmt::Debug for Board<B>
@@ -176,13 +183,13 @@ where
         let height = rows.len();
         let width = columns.len();
 
-        let all_colors = Self::all_colors(&rows);
+        let all_colors = Self::all_colors(&rows, &columns);
         let init = B::Color::from_color_ids(&all_colors);
         warn!("Initializing board: height={}, width={}", height, width);
         let cells = vec![init; width * height];
 
         let uniq_indexes = |lines: &[Description<B>]| {
-            let uniq_lines: Vec<&Vec<B>> = dedup(lines.iter().map(|desc| &desc.vec));
+            let uniq_lines: Vec<&Description<B>> = dedup(lines.iter());
             if uniq_lines.len() < lines.len() {
                 warn!(
                     "Reducing number of clues: {} --> {}",
@@ -195,7 +202,7 @@ where
                 .map(|desc| {
                     uniq_lines
                         .iter()
-                        .position(|&uniq_line| uniq_line == &desc.vec)
+                        .position(|&uniq_line| uniq_line == desc)
                         .expect("Every line should be present in unique lines")
                 })
                 .collect()
@@ -218,20 +225,27 @@ where
             on_set_line: None,
             on_restore: None,
             on_change_color: None,
+            on_unset_color: None,
         }
     }
 
     /// Clue colors describing the board more precisely than the palette
     /// (as the latter can contain excess colors like 'white').
-    fn all_colors(descriptions: &[Description<B>]) -> Vec<ColorId> {
-        let colors = descriptions
+    fn all_colors(rows: &[Description<B>], columns: &[Description<B>]) -> Vec<ColorId> {
+        // the blotted rows can miss some colors
+        let colors = rows
             .iter()
+            .chain(columns)
             .flat_map(Description::colors)
             .chain(once(ColorPalette::WHITE_ID));
 
         dedup(colors)
     }
 
+    pub fn palette(&self) -> Option<&ColorPalette> {
+        self.palette.as_ref()
+    }
+
     pub fn desc_by_id(&self, id: ColorId) -> Option<ColorDesc> {
         self.palette
             .as_ref()
@@ -401,6 +415,80 @@ where
             LinePosition::Column(index) => self.cols_cache_indexes[index],
         }
     }
+
+    /// Whether some clues have the blotted numbers.
+    pub fn is_blotted(&self) -> bool {
+        self.desc_rows
+            .iter()
+            .chain(&self.desc_cols)
+            .any(|desc| desc.is_blotted())
+    }
+
+    /// Check that the clues can describe some board at all:
+    /// - every line's blocks fit into the line;
+    /// - the rows and the columns have the same number of cells of every color
+    ///   (unless the clues are blotted);
+    /// - every color of the clues is defined in the palette.
+    pub fn validate(&self) -> Result<(), SolveError> {
+        self.validate_descriptions_size()?;
+        self.validate_colors()
+    }
+
+    fn validate_descriptions_size(&self) -> Result<(), SolveError> {
+        for (direction, line_length) in &[
+            (LineDirection::Row, self.width()),
+            (LineDirection::Column, self.height()),
+        ] {
+            for (index, desc) in self.descriptions(*direction).iter().enumerate() {
+                let min_space = desc.min_space();
+                if min_space > *line_length {
+                    let position = LinePosition::with_direction_and_index(*direction, index);
+                    return Err(SolveError::InvalidClues(format!(
+                        "{:?} requires at least {} cells, but the line has only {}",
+                        position, min_space, line_length
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_colors(&self) -> Result<(), SolveError> {
+        let cells_by_color = |direction| {
+            let mut sizes = HashMap::new();
+            for desc in self.descriptions(direction) {
+                for block in &desc.vec {
+                    *sizes.entry(block.color()).or_insert(0) += block.size();
+                }
+            }
+            sizes
+        };
+
+        let rows_cells = cells_by_color(LineDirection::Row);
+        let columns_cells = cells_by_color(LineDirection::Column);
+        if rows_cells != columns_cells && !self.is_blotted() {
+            return Err(SolveError::InvalidClues(format!(
+                "The rows define {:?} colored cells, but the columns define {:?}",
+                rows_cells, columns_cells
+            )));
+        }
+
+        if self.palette.is_some() {
+            if let Some(&unknown) = self
+                .all_colors
+                .iter()
+                .find(|&&color_id| self.desc_by_id(color_id).is_none())
+            {
+                return Err(SolveError::InconsistentPalette(format!(
+                    "The color with id {} is not defined",
+                    unknown
+                )));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl<B> Board<B>
@@ -438,11 +526,15 @@ where
                 })
                 .collect();
 
-            let (columns, rows) = clues_from_solution(&solution_matrix, white);
-            let columns: Vec<_> = columns.into_iter().map(ReadRc::new).collect();
-            let rows: Vec<_> = rows.into_iter().map(ReadRc::new).collect();
-            assert_eq!(self.desc_cols, columns);
-            assert_eq!(self.desc_rows, rows);
+            let (columns, rows) = clues_from_solution::<B>(&solution_matrix, white);
+            for (desc, actual) in self
+                .desc_cols
+                .iter()
+                .zip(&columns)
+                .chain(self.desc_rows.iter().zip(&rows))
+            {
+                assert!(desc.matches(&actual.vec), "{:?} != {:?}", desc, actual);
+            }
         }
     }
 
@@ -480,12 +572,15 @@ where
         }
     }
 
-    fn unset_color(&mut self, point: &Point, color: &B::Color) -> Result<(), String> {
+    fn unset_color(&mut self, point: &Point, color: &B::Color) -> Result<(), SolveError> {
         let old_value = self.cell(point);
         let Point { x, y } = *point;
         let index = self.linear_index(y, x);
         if let Some(cell) = self.cells.get_mut(index) {
-            *cell = (old_value - *color)?;
+            *cell = (old_value - *color).map_err(|err| {
+                debug!("{}", err);
+                SolveError::CellContradiction(*point)
+            })?;
         }
 
         Ok(())
@@ -597,6 +692,20 @@ where
     pub fn set_callback_on_change_color<CB: ChangeColorCallback + 'static>(&mut self, f: CB) {
         self.on_change_color = Some(Box::new(f));
     }
+
+    /// Called when the color gets excluded from the cell (e.g. after a contradiction).
+    /// If not set, the `on_change_color` callback is used instead.
+    pub fn set_callback_on_unset_color<CB: ChangeColorCallback + 'static>(&mut self, f: CB) {
+        self.on_unset_color = Some(Box::new(f));
+    }
+
+    /// Whether anybody watches the changes of the board.
+    pub fn has_callbacks(&self) -> bool {
+        self.on_set_line.is_some()
+            || self.on_restore.is_some()
+            || self.on_change_color.is_some()
+            || self.on_unset_color.is_some()
+    }
 }
 
 impl<B> Board<B>
@@ -635,9 +744,14 @@ where
         self_: &MutRc<Self>,
         point: &Point,
         color: &B::Color,
-    ) -> Result<(), String> {
+    ) -> Result<(), SolveError> {
         self_.write().unset_color(point, color)?;
-        if let Some(f) = &self_.read().on_change_color {
+        let board = self_.read();
+        if let Some(f) = board
+            .on_unset_color
+            .as_ref()
+            .or(board.on_change_color.as_ref())
+        {
             f(*point);
         }
         Ok(())
@@ -662,15 +776,19 @@ where
             on_set_line: None,
             on_restore: None,
             on_change_color: None,
+            on_unset_color: None,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::block::{
-        binary::{BinaryBlock, BinaryColor::Undefined},
-        Description,
+    use crate::{
+        block::{
+            binary::{BinaryBlock, BinaryColor::Undefined},
+            Description,
+        },
+        solver::SolveError,
     };
 
     use super::Board;
@@ -719,4 +837,58 @@ mod tests {
         assert_eq!(board.desc_rows[1].vec, vec![]);
         assert_eq!(board.desc_rows[2].vec, vec![BinaryBlock(1)]);
     }
+
+    #[test]
+    fn validate_u_letter() {
+        let rows = vec![
+            Description::new(vec![BinaryBlock(1), BinaryBlock(1)]),
+            Description::new(vec![BinaryBlock(1), BinaryBlock(1)]),
+            Description::new(vec![BinaryBlock(3)]),
+        ];
+        let columns = vec![
+            Description::new(vec![BinaryBlock(3)]),
+            Description::new(vec![BinaryBlock(1)]),
+            Description::new(vec![BinaryBlock(3)]),
+        ];
+
+        let board = Board::with_descriptions(rows, columns);
+        assert_eq!(board.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_too_long_clue() {
+        let rows = vec![
+            Description::new(vec![BinaryBlock(1), BinaryBlock(2)]),
+            Description::new(vec![]),
+        ];
+        let columns = vec![
+            Description::new(vec![BinaryBlock(1)]),
+            Description::new(vec![BinaryBlock(1)]),
+            Description::new(vec![BinaryBlock(1)]),
+        ];
+
+        let board = Board::with_descriptions(rows, columns);
+        match board.validate() {
+            Err(SolveError::InvalidClues(_)) => {}
+            other => panic!("Expected invalid clues, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn validate_cells_mismatch() {
+        let rows = vec![
+            Description::new(vec![BinaryBlock(2)]),
+            Description::new(vec![BinaryBlock(1)]),
+        ];
+        let columns = vec![
+            Description::new(vec![BinaryBlock(2)]),
+            Description::new(vec![BinaryBlock(2)]),
+        ];
+
+        let board = Board::with_descriptions(rows, columns);
+        match board.validate() {
+            Err(SolveError::InvalidClues(_)) => {}
+            other => panic!("Expected invalid clues, got {:?}", other),
+        }
+    }
 }
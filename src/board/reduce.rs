@@ -0,0 +1,527 @@
+//! Shrinking the board by removing the solved parts of it.
+
+use log::{debug, info};
+
+use crate::{
+    block::{
+        base::color::{ColorId, ColorPalette},
+        binary::{BinaryBlock, BinaryColor},
+        Block, Color, Description,
+    },
+    board::{Board, Point},
+    utils::rc::ReadRc,
+};
+
+/// Where the rows and the columns of the reduced board are on the original one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Reduction {
+    rows: Vec<usize>,
+    columns: Vec<usize>,
+    /// The width of the original board.
+    width: usize,
+}
+
+impl Reduction {
+    /// The point of the original board corresponding to the point of the reduced one.
+    pub(crate) fn original_point(&self, point: &Point) -> Point {
+        Point::new(self.columns[point.x], self.rows[point.y])
+    }
+
+    /// Put the cells of the reduced board to their places on the original one.
+    ///
+    /// The cells removed (or whitened) by the reduction were solved before,
+    /// so they are taken from the original cells as is.
+    pub(crate) fn expand<C>(&self, original: &[C], reduced: &[C]) -> Vec<C>
+    where
+        C: Color,
+    {
+        let mut cells = original.to_vec();
+        let reduced_width = self.columns.len();
+        for (index, &cell) in reduced.iter().enumerate() {
+            let point =
+                self.original_point(&Point::new(index % reduced_width, index / reduced_width));
+            let original = &mut cells[point.y * self.width + point.x];
+            if !original.is_solved() {
+                *original = cell;
+            }
+        }
+        cells
+    }
+}
+
+/// The single color of the board replaced with the black one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinaryDowngrade {
+    color: ColorId,
+}
+
+impl BinaryDowngrade {
+    /// Convert the cells of the black-and-white board back to the original colors.
+    pub fn upgrade<C>(self, cells: &[BinaryColor]) -> Vec<C>
+    where
+        C: Color,
+    {
+        let black = C::from_color_ids(&[self.color]);
+        let unknown = C::from_color_ids(&[ColorPalette::WHITE_ID, self.color]);
+
+        cells
+            .iter()
+            .map(|&cell| match cell {
+                BinaryColor::White => C::blank(),
+                BinaryColor::Black => black,
+                BinaryColor::Undefined | BinaryColor::BlackOrWhite => unknown,
+            })
+            .collect()
+    }
+
+    fn downgrade<C>(cell: C) -> BinaryColor
+    where
+        C: Color,
+    {
+        let variants = cell.variants();
+        let blank = variants.contains(&C::blank());
+        let colored = variants.iter().any(|&color| color != C::blank());
+
+        match (blank, colored) {
+            (true, true) => BinaryColor::Undefined,
+            (false, true) => BinaryColor::Black,
+            _ => BinaryColor::White,
+        }
+    }
+}
+
+/// The board being reduced: the cells are stored by rows.
+///
+/// Only the rows are reduced, the columns get their turn after the transposition.
+struct Grid<B>
+where
+    B: Block,
+{
+    cells: Vec<Vec<B::Color>>,
+    rows: Vec<Vec<B>>,
+    columns: Vec<Vec<B>>,
+    row_indexes: Vec<usize>,
+    column_indexes: Vec<usize>,
+}
+
+impl<B> Grid<B>
+where
+    B: Block,
+{
+    fn with_board(board: &Board<B>) -> Self {
+        let clues = |descriptions: &[ReadRc<Description<B>>]| {
+            descriptions.iter().map(|desc| desc.vec.clone()).collect()
+        };
+
+        Self {
+            cells: board.iter_rows().map(<[_]>::to_vec).collect(),
+            rows: clues(&board.desc_rows),
+            columns: clues(&board.desc_cols),
+            row_indexes: (0..board.height()).collect(),
+            column_indexes: (0..board.width()).collect(),
+        }
+    }
+
+    fn transpose(self) -> Self {
+        let width = self.columns.len();
+        let cells = (0..width)
+            .map(|x| self.cells.iter().map(|row| row[x]).collect())
+            .collect();
+
+        Self {
+            cells,
+            rows: self.columns,
+            columns: self.rows,
+            row_indexes: self.column_indexes,
+            column_indexes: self.row_indexes,
+        }
+    }
+
+    /// Whether the row has the single possible color in every cell.
+    fn is_row_solved(&self, y: usize) -> bool {
+        self.cells[y].iter().all(|cell| cell.is_solved())
+    }
+
+    /// Replace the blocks of the solved rows with the blank cells
+    /// removing the corresponding cells from the column blocks.
+    fn whiten_rows(&mut self) -> bool {
+        let mut changed = false;
+        for y in 0..self.cells.len() {
+            if self.rows[y].is_empty() || !self.is_row_solved(y) {
+                continue;
+            }
+
+            let blocks = blocks(&self.cells[y]);
+            if blocks.len() != self.rows[y].len() {
+                debug!("The solved row {} does not correspond to its clue", y);
+                continue;
+            }
+
+            // the later blocks go first to keep the indexes of the earlier ones
+            for (index, (start, end)) in blocks.into_iter().enumerate().rev() {
+                let column_blocks: Option<Vec<_>> = (start..end)
+                    .map(|x| self.column_block(x, y).map(|block| (x, block)))
+                    .collect();
+
+                if let Some(column_blocks) = column_blocks {
+                    for (x, block) in column_blocks {
+                        shrink(&mut self.columns[x], block);
+                        self.cells[y][x] = B::Color::blank();
+                    }
+                    let _ = self.rows[y].remove(index);
+                    changed = true;
+                }
+            }
+        }
+
+        changed
+    }
+
+    /// The index of the column block the cell belongs to,
+    /// if the cell can be removed from the block without splitting it.
+    fn column_block(&self, x: usize, y: usize) -> Option<usize> {
+        let column: Vec<_> = self.cells.iter().map(|row| row[x]).collect();
+        let color = column[y];
+        let blocks_number = self.columns[x].len();
+
+        let index = if column[..y].iter().all(|cell| cell.is_solved()) {
+            blocks(&column[..=y]).len().checked_sub(1)?
+        } else if column[y..].iter().all(|cell| cell.is_solved()) {
+            blocks_number.checked_sub(blocks(&column[y..]).len())?
+        } else {
+            return None;
+        };
+
+        let block = self.columns[x].get(index)?;
+        if block.color() != color {
+            return None;
+        }
+
+        // the edge of the board or the cell surely of another color
+        let other_color = |cell: &B::Color| cell.is_solved() && *cell != color;
+        let at_start = y == 0 || other_color(&column[y - 1]);
+        let at_end = y + 1 == column.len() || other_color(&column[y + 1]);
+        if block.size() == 1 || at_start || at_end {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    /// Remove the blank rows which neighbours cannot be merged into a single block.
+    fn remove_rows(&mut self) -> bool {
+        let mut changed = false;
+        while let Some(y) = self.removable_row() {
+            let _ = self.cells.remove(y);
+            let _ = self.rows.remove(y);
+            let original = self.row_indexes.remove(y);
+            debug!("Removing the blank line {}", original);
+            changed = true;
+        }
+
+        changed
+    }
+
+    fn removable_row(&self) -> Option<usize> {
+        // keep at least one row
+        if self.cells.len() <= 1 {
+            return None;
+        }
+
+        (0..self.cells.len()).find(|&y| {
+            self.rows[y].is_empty()
+                && self.cells[y].iter().all(|&cell| cell == B::Color::blank())
+                && self.can_join_neighbours(y)
+        })
+    }
+
+    /// The 'comparing neighbours' check: the rows around the removed one
+    /// should not have any common colors except the blank in every column.
+    fn can_join_neighbours(&self, y: usize) -> bool {
+        if y == 0 || y + 1 == self.cells.len() {
+            return true;
+        }
+
+        let (previous, next) = (&self.cells[y - 1], &self.cells[y + 1]);
+        previous.iter().zip(next).all(|(&previous, &next)| {
+            let next = next.variants();
+            previous
+                .variants()
+                .into_iter()
+                .all(|color| color == B::Color::blank() || !next.contains(&color))
+        })
+    }
+
+    fn into_board(self, original: &Board<B>) -> (Board<B>, Reduction) {
+        let descriptions = |lines: Vec<Vec<B>>| lines.into_iter().map(Description::new).collect();
+        let mut board = Board::with_descriptions_and_palette(
+            descriptions(self.rows),
+            descriptions(self.columns),
+            original.palette.clone(),
+        );
+        board.cells = self.cells.into_iter().flatten().collect();
+
+        let reduction = Reduction {
+            rows: self.row_indexes,
+            columns: self.column_indexes,
+            width: original.width(),
+        };
+        (board, reduction)
+    }
+}
+
+/// The start and end (exclusive) of every block of the solved line.
+fn blocks<C>(line: &[C]) -> Vec<(usize, usize)>
+where
+    C: Color,
+{
+    let mut blocks = vec![];
+    let mut index = 0;
+    while index < line.len() {
+        let color = line[index];
+        let size = line[index..]
+            .iter()
+            .take_while(|&&cell| cell == color)
+            .count();
+        if color != C::blank() {
+            blocks.push((index, index + size));
+        }
+        index += size;
+    }
+    blocks
+}
+
+/// Decrease the size of the block removing it completely if nothing left.
+fn shrink<B>(clue: &mut Vec<B>, index: usize)
+where
+    B: Block,
+{
+    let block = clue[index];
+    if block.size() > 1 {
+        clue[index] = B::from_size_and_color(block.size() - 1, block.color().as_color_id());
+    } else {
+        let _ = clue.remove(index);
+    }
+}
+
+impl<B> Board<B>
+where
+    B: Block,
+{
+    /// Make the smaller board with the same solutions (on the unsolved cells):
+    /// - the blocks of the solved lines are replaced with the blank cells,
+    ///   if every cell can be removed from its perpendicular block without splitting it;
+    /// - the blank lines are removed, if their neighbours cannot merge into a single block.
+    ///
+    /// The board should be propagated by the line solver until no line changes
+    /// (as the `solve` does before the reduction): the solved cells at the edges of every line
+    /// are matched with the clue blocks by their order, which only holds when every line
+    /// agrees with its clue. Otherwise the reduced board can lose or gain the solutions.
+    ///
+    /// Return `None` if nothing can be reduced.
+    pub(crate) fn reduce(&self) -> Option<(Self, Reduction)> {
+        // the blots do not tell which blocks the solved cells belong to
+        if self.is_solved_full() || self.is_blotted() {
+            return None;
+        }
+
+        let mut grid = Grid::with_board(self);
+        let mut changed = false;
+        loop {
+            let (whitened, removed) = (grid.whiten_rows(), grid.remove_rows());
+            grid = grid.transpose();
+            let (columns_whitened, columns_removed) = (grid.whiten_rows(), grid.remove_rows());
+            grid = grid.transpose();
+
+            if !(whitened || removed || columns_whitened || columns_removed) {
+                break;
+            }
+            changed = true;
+        }
+
+        if !changed {
+            return None;
+        }
+
+        let (board, reduction) = grid.into_board(self);
+        info!(
+            "Reduced the board {}x{} --> {}x{}",
+            self.height(),
+            self.width(),
+            board.height(),
+            board.width()
+        );
+        Some((board, reduction))
+    }
+
+    /// Make the black-and-white board with the same solutions,
+    /// if the clues have the single color besides the blank one.
+    ///
+    /// The black-and-white boards are never downgraded.
+    pub fn downgrade(&self) -> Option<(Board<BinaryBlock>, BinaryDowngrade)> {
+        // the black-and-white clues have no colors except the blank one
+        if self.all_colors.len() != 2 || self.is_blotted() {
+            return None;
+        }
+
+        let color = self
+            .all_colors
+            .iter()
+            .copied()
+            .find(|&color| color != ColorPalette::WHITE_ID)?;
+        let descriptions = |descriptions: &[ReadRc<Description<B>>]| {
+            descriptions
+                .iter()
+                .map(|desc| {
+                    Description::new(
+                        desc.vec
+                            .iter()
+                            .map(|block| BinaryBlock(block.size()))
+                            .collect(),
+                    )
+                })
+                .collect()
+        };
+
+        let mut board = Board::with_descriptions_and_palette(
+            descriptions(&self.desc_rows),
+            descriptions(&self.desc_cols),
+            None,
+        );
+        board.cells = self
+            .cells
+            .iter()
+            .map(|&cell| BinaryDowngrade::downgrade(cell))
+            .collect();
+
+        info!("Downgraded the board with the single color {}", color);
+        Some((board, BinaryDowngrade { color }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block::{
+            binary::{BinaryBlock, BinaryColor},
+            multicolor::{ColoredBlock, MultiColor},
+            Description,
+        },
+        board::{Board, Point},
+        test_utils::propagated,
+    };
+
+    #[test]
+    fn solved_lines_removed() {
+        let board = propagated(
+            &[&[1], &[1], &[1], &[], &[4]],
+            &[&[1, 1], &[1, 1], &[1, 1], &[1]],
+        )
+        .read()
+        .clone();
+        let (reduced, reduction) = board.reduce().unwrap();
+        assert_eq!((reduced.height(), reduced.width()), (3, 3));
+        for desc in reduced.desc_rows.iter().chain(&reduced.desc_cols) {
+            assert_eq!(desc.vec, vec![BinaryBlock(1)]);
+        }
+        assert_eq!(
+            reduction.original_point(&Point::new(2, 1)),
+            Point::new(2, 1)
+        );
+
+        let mut solution = vec![BinaryColor::White; 9];
+        for &index in &[0, 4, 8] {
+            solution[index] = BinaryColor::Black;
+        }
+        let expanded = reduction.expand(&board.make_snapshot(), &solution);
+        assert_eq!(expanded.len(), 20);
+        let black: Vec<_> = expanded
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &cell)| {
+                if cell == BinaryColor::Black {
+                    Some(index)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        // the diagonal of the top left corner and the last row
+        assert_eq!(black, vec![0, 5, 10, 16, 17, 18, 19]);
+    }
+
+    #[test]
+    fn neighbours_cannot_join() {
+        // the blank row separates the cells that can be black in both neighbours
+        let board = propagated(&[&[1], &[], &[1]], &[&[1], &[1]]).read().clone();
+        assert!(!board.is_solved_full());
+        assert!(board.reduce().is_none());
+    }
+
+    #[test]
+    fn nothing_to_reduce() {
+        let board = propagated(&[&[1], &[1]], &[&[1], &[1]]).read().clone();
+        assert!(board.reduce().is_none());
+    }
+
+    #[test]
+    fn downgrade_single_color() {
+        let desc = |sizes: &[usize]| {
+            Description::new(
+                sizes
+                    .iter()
+                    .map(|&size| ColoredBlock::from_size_and_color(size, 4))
+                    .collect(),
+            )
+        };
+        let mut board = Board::with_descriptions_and_palette(
+            vec![desc(&[2]), desc(&[])],
+            vec![desc(&[1]), desc(&[1])],
+            None,
+        );
+        board.cells[2] = MultiColor(1);
+
+        let (binary, downgrade) = board.downgrade().unwrap();
+        assert_eq!(binary.desc_rows[0].vec, vec![BinaryBlock(2)]);
+        assert_eq!(binary.desc_rows[1].vec, vec![]);
+        assert_eq!(
+            binary.make_snapshot(),
+            vec![
+                BinaryColor::Undefined,
+                BinaryColor::Undefined,
+                BinaryColor::White,
+                BinaryColor::Undefined
+            ]
+        );
+
+        let upgraded: Vec<MultiColor> = downgrade.upgrade(&[
+            BinaryColor::Black,
+            BinaryColor::Black,
+            BinaryColor::White,
+            BinaryColor::Undefined,
+        ]);
+        assert_eq!(
+            upgraded,
+            vec![MultiColor(4), MultiColor(4), MultiColor(1), MultiColor(5)]
+        );
+    }
+
+    #[test]
+    fn no_downgrade() {
+        let two_colors = Board::with_descriptions_and_palette(
+            vec![Description::new(vec![
+                ColoredBlock::from_size_and_color(1, 4),
+                ColoredBlock::from_size_and_color(1, 8),
+            ])],
+            vec![
+                Description::new(vec![ColoredBlock::from_size_and_color(1, 4)]),
+                Description::new(vec![ColoredBlock::from_size_and_color(1, 8)]),
+            ],
+            None,
+        );
+        assert!(two_colors.downgrade().is_none());
+
+        let binary = propagated(&[&[1], &[1]], &[&[1], &[1]]).read().clone();
+        assert!(binary.downgrade().is_none());
+    }
+}
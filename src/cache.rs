@@ -7,7 +7,9 @@ use lru::LruCache;
 pub trait Cached<K, V> {
     fn cache_get(&mut self, key: &K) -> Option<&V>;
     fn cache_set(&mut self, key: K, val: V);
+    #[allow(dead_code)]
     fn cache_remove(&mut self, k: &K) -> Option<V>;
+    #[allow(dead_code)]
     fn cache_clear(&mut self);
     fn cache_size(&self) -> usize;
     fn cache_hits(&self) -> Option<u32>;
@@ -0,0 +1,333 @@
+//! Random puzzles with the unique solution.
+
+use log::{info, warn};
+
+use crate::{
+    block::{
+        base::{
+            clues_from_solution,
+            color::{ColorId, ColorPalette, ColorValue},
+        },
+        Block,
+    },
+    board::{Board, Point},
+    parser::PuzzleScheme,
+    solver::{
+        backtracking::SearchOptions, check_unique, line::DynamicColor, line::DynamicSolver,
+        probing::FullProbe1, Backend, Uniqueness,
+    },
+    utils::{random::XorShift, rc::MutRc},
+};
+
+/// The code of the blank cell in the solution matrix.
+const BLANK: ColorId = 0;
+
+/// Generates the random grids and tweaks them until the solution becomes unique.
+#[derive(Debug, Clone)]
+pub struct Generator {
+    width: usize,
+    height: usize,
+    density: f64,
+    colors: Vec<String>,
+    max_tweaks: usize,
+    search_options: SearchOptions,
+    rng: XorShift,
+}
+
+impl Generator {
+    pub const DEFAULT_DENSITY: f64 = 0.5;
+    pub const DEFAULT_MAX_TWEAKS: usize = 1000;
+    pub const MAX_COLORS: usize = ColorPalette::MAX_COLORS;
+
+    /// The black-and-white puzzles of the given size.
+    /// The same `seed` always produces the same puzzles.
+    pub fn new(width: usize, height: usize, seed: u64) -> Result<Self, String> {
+        if width == 0 || height == 0 {
+            return Err("The puzzle should not be empty".to_string());
+        }
+
+        Ok(Self {
+            width,
+            height,
+            density: Self::DEFAULT_DENSITY,
+            colors: vec![],
+            max_tweaks: Self::DEFAULT_MAX_TWEAKS,
+            search_options: SearchOptions::default(),
+            rng: XorShift::with_seed(seed),
+        })
+    }
+
+    /// The probability of every cell to be filled, from 0 to 1.
+    pub fn with_density(mut self, density: f64) -> Result<Self, String> {
+        if !(0.0..=1.0).contains(&density) {
+            return Err("The density should be between 0 and 1".to_string());
+        }
+
+        self.density = density;
+        Ok(self)
+    }
+
+    /// Fill the cells with the given colors (in any form that `ColorValue::parse` understands)
+    /// instead of the black one. Use the `ColoredBlock` to generate such a puzzle.
+    pub fn with_colors(mut self, colors: &[&str]) -> Result<Self, String> {
+        if colors.len() > Self::MAX_COLORS {
+            return Err(format!(
+                "No more than {} colors are supported",
+                Self::MAX_COLORS
+            ));
+        }
+
+        self.colors = colors.iter().map(|&color| color.to_string()).collect();
+        Ok(self)
+    }
+
+    /// How many times to change the grid before giving up.
+    pub fn with_max_tweaks(mut self, max_tweaks: usize) -> Self {
+        self.max_tweaks = max_tweaks;
+        self
+    }
+
+    /// How to search for the second solution when checking the uniqueness.
+    pub fn with_search_options(mut self, options: SearchOptions) -> Self {
+        self.search_options = options;
+        self
+    }
+
+    /// Whether to generate the puzzle with `BinaryBlock`, `ColoredBlock` or `WideColoredBlock`.
+    pub fn scheme(&self) -> PuzzleScheme {
+        self.palette()
+            .map_or(PuzzleScheme::BlackAndWhite, |palette| {
+                PuzzleScheme::with_palette(&palette)
+            })
+    }
+
+    fn palette(&self) -> Option<ColorPalette> {
+        if self.colors.is_empty() {
+            return None;
+        }
+
+        let mut palette = ColorPalette::with_white("white");
+        for name in &self.colors {
+            palette
+                .color_with_name_and_value(name, ColorValue::parse(name))
+                .expect("The number of colors is checked in with_colors");
+        }
+        Some(palette)
+    }
+
+    /// The codes of the colors to fill the cells with.
+    fn color_ids(palette: Option<&ColorPalette>) -> Vec<ColorId> {
+        palette.map_or_else(
+            || vec![1],
+            |palette| {
+                palette
+                    .colors()
+                    .into_iter()
+                    .map(|color_desc| color_desc.id())
+                    .filter(|&id| id != ColorPalette::WHITE_ID)
+                    .collect()
+            },
+        )
+    }
+
+    fn random_color(&mut self, color_ids: &[ColorId]) -> ColorId {
+        color_ids[self.rng.below(color_ids.len())]
+    }
+
+    fn random_grid(&mut self, color_ids: &[ColorId]) -> Vec<Vec<ColorId>> {
+        (0..self.height)
+            .map(|_| {
+                (0..self.width)
+                    .map(|_| {
+                        if self.rng.next_f64() < self.density {
+                            self.random_color(color_ids)
+                        } else {
+                            BLANK
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Change the cell to make the puzzle different.
+    fn tweak(&mut self, grid: &mut [Vec<ColorId>], point: Point, color_ids: &[ColorId]) {
+        let cell = &mut grid[point.y][point.x];
+        *cell = if *cell == BLANK {
+            self.random_color(color_ids)
+        } else {
+            BLANK
+        };
+    }
+
+    /// Generate the next puzzle.
+    ///
+    /// Return `None` if the puzzle cannot be made unique in the maximum number of tweaks.
+    pub fn generate<B>(&mut self) -> Option<Board<B>>
+    where
+        B: 'static + Block,
+        B::Color: DynamicColor,
+    {
+        let palette = self.palette();
+        let color_ids = Self::color_ids(palette.as_ref());
+        let mut grid = self.random_grid(&color_ids);
+
+        for tweak in 0..=self.max_tweaks {
+            let (columns, rows) = clues_from_solution::<B>(&grid, BLANK);
+            let checked = MutRc::new(Board::with_descriptions_and_palette(
+                rows.clone(),
+                columns.clone(),
+                palette.clone(),
+            ));
+
+            let uniqueness = check_unique::<_, DynamicSolver<_>, FullProbe1<_>>(
+                checked,
+                Backend::Backtracking,
+                None,
+                None,
+                self.search_options,
+            );
+            let point = match uniqueness {
+                Ok(Uniqueness::Unique(_)) => {
+                    info!("The unique puzzle generated after {} tweaks", tweak);
+                    return Some(Board::with_descriptions_and_palette(rows, columns, palette));
+                }
+                Ok(Uniqueness::Multiple { diff, .. }) => diff[self.rng.below(diff.len())],
+                Ok(Uniqueness::NoSolutions) | Err(_) => {
+                    warn!("The generated puzzle is not solvable: {:?}", uniqueness);
+                    Point::new(self.rng.below(self.width), self.rng.below(self.height))
+                }
+            };
+            self.tweak(&mut grid, point, &color_ids);
+        }
+
+        warn!(
+            "Cannot generate the unique puzzle in {} tweaks",
+            self.max_tweaks
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block::{
+            binary::BinaryBlock,
+            multicolor::{ColoredBlock, WideColoredBlock},
+        },
+        board::LineDirection,
+        parser::PuzzleScheme,
+        solver::{check_unique, line::DynamicSolver, probing::FullProbe1, Backend, Uniqueness},
+        utils::rc::MutRc,
+    };
+
+    use super::{Generator, SearchOptions};
+
+    #[test]
+    fn black_and_white() {
+        let mut generator = Generator::new(8, 6, 42).unwrap();
+        assert_eq!(generator.scheme(), PuzzleScheme::BlackAndWhite);
+        let board = generator.generate::<BinaryBlock>().unwrap();
+        assert_eq!(board.width(), 8);
+        assert_eq!(board.height(), 6);
+        assert!(board.palette().is_none());
+
+        let uniqueness = check_unique::<_, DynamicSolver<_>, FullProbe1<_>>(
+            MutRc::new(board),
+            Backend::Backtracking,
+            None,
+            None,
+            SearchOptions::default(),
+        );
+        match uniqueness {
+            Ok(Uniqueness::Unique(_)) => {}
+            other => panic!("Expected unique solution, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn same_seed_same_puzzle() {
+        let first = Generator::new(5, 5, 7)
+            .unwrap()
+            .generate::<BinaryBlock>()
+            .unwrap();
+        let second = Generator::new(5, 5, 7)
+            .unwrap()
+            .generate::<BinaryBlock>()
+            .unwrap();
+        assert_eq!(
+            first.descriptions(LineDirection::Row),
+            second.descriptions(LineDirection::Row)
+        );
+        assert_eq!(
+            first.descriptions(LineDirection::Column),
+            second.descriptions(LineDirection::Column)
+        );
+    }
+
+    #[test]
+    fn colored() {
+        let mut generator = Generator::new(6, 6, 1)
+            .and_then(|generator| generator.with_colors(&["red", "blue"]))
+            .and_then(|generator| generator.with_density(0.7))
+            .unwrap();
+        assert_eq!(generator.scheme(), PuzzleScheme::MultiColor);
+        let board = generator.generate::<ColoredBlock>().unwrap();
+        let palette = board.palette().unwrap();
+        assert_eq!(palette.colors().len(), 3);
+    }
+
+    #[test]
+    fn too_many_colors_for_multicolor() {
+        let names: Vec<_> = (1..=40).map(|i| format!("#{:06X}", i * 0x0402)).collect();
+        let names: Vec<_> = names.iter().map(String::as_str).collect();
+        let mut generator = Generator::new(6, 6, 3)
+            .and_then(|generator| generator.with_colors(&names))
+            .unwrap();
+        assert_eq!(generator.scheme(), PuzzleScheme::WideColor);
+
+        let board = generator.generate::<WideColoredBlock>().unwrap();
+        assert_eq!(board.palette().unwrap().colors().len(), 41);
+
+        let uniqueness = check_unique::<_, DynamicSolver<_>, FullProbe1<_>>(
+            MutRc::new(board),
+            Backend::Backtracking,
+            None,
+            None,
+            SearchOptions::default(),
+        );
+        match uniqueness {
+            Ok(Uniqueness::Unique(_)) => {}
+            other => panic!("Expected unique solution, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn empty_puzzle_is_unique() {
+        let mut generator = Generator::new(3, 3, 1)
+            .and_then(|generator| generator.with_density(0.0))
+            .unwrap();
+        let board = generator.generate::<BinaryBlock>().unwrap();
+        assert!(board
+            .descriptions(LineDirection::Row)
+            .iter()
+            .all(|d| d.vec.is_empty()));
+    }
+
+    #[test]
+    fn invalid_options() {
+        assert!(Generator::new(0, 3, 1).is_err());
+        assert!(Generator::new(3, 0, 1).is_err());
+
+        let generator = || Generator::new(3, 3, 1).unwrap();
+        assert!(generator().with_density(-0.1).is_err());
+        assert!(generator().with_density(1.5).is_err());
+        assert!(generator().with_density(f64::NAN).is_err());
+
+        let names: Vec<_> = (0..=Generator::MAX_COLORS).map(|i| i.to_string()).collect();
+        let names: Vec<_> = names.iter().map(String::as_str).collect();
+        assert!(generator().with_colors(&names).is_err());
+        assert!(generator().with_colors(&names[1..]).is_ok());
+    }
+}
@@ -8,12 +8,9 @@
 //
 #![warn(absolute_paths_not_starting_with_crate)]
 #![warn(anonymous_parameters)]
-// use `Box` without fear
-#![allow(box_pointers)]
 #![warn(deprecated_in_future)]
 #![warn(elided_lifetimes_in_paths)]
 #![warn(explicit_outlives_requirements)]
-#![warn(indirect_structural_match)]
 #![warn(keyword_idents)]
 #![warn(macro_use_extern_crate)]
 #![warn(meta_variable_misuse)]
@@ -23,7 +20,6 @@
 #![warn(missing_docs)]
 #![warn(non_ascii_idents)]
 #![warn(noop_method_call)]
-#![warn(pointer_structural_match)]
 // produces an ICE when enabled as a warning
 #![allow(rust_2021_incompatible_closure_captures)]
 #![warn(rust_2021_incompatible_or_patterns)]
@@ -62,26 +58,39 @@
 
 pub use self::{
     block::{
-        base::{color::ColorId, Block, Color, Description},
+        base::{color::ColorId, Block, Clue, Color, Description},
         binary::{BinaryBlock, BinaryColor},
-        multicolor::ColoredBlock,
+        multicolor::{ColoredBlock, WideColoredBlock},
     },
-    board::Board,
+    board::{BinaryDowngrade, Board, LineDirection, LinePosition, Point},
     parser::{BoardParser, DetectedParser},
     solver::{
+        backtracking::{
+            ChoosePixel, SearchOptions, SolutionsIter as BacktrackingSolutions,
+            Solver as BacktrackingSolver,
+        },
+        check_unique, count_solutions,
+        difficulty::{rate_difficulty, Difficulty, DifficultyLevel},
+        hint::{next_hint, Hint},
         line::{DynamicColor, DynamicSolver as LineSolver},
-        probing::{FullProbe1 as FullProbe, ProbeSolver},
+        probing::{FullProbe1 as FullProbe, FullProbe2, ProbeSolver, ProbeStats},
+        progress::{CancelToken, Progress, ProgressCallback},
         propagation::Solver as PropagationSolver,
-        run as solve,
+        run as solve, Backend, SolveError, Uniqueness,
     },
 };
 
+pub mod batch;
 mod block;
 mod board;
 mod cache;
+pub mod generator;
 pub mod parser;
 pub mod render;
 mod solver;
+#[cfg(test)]
+mod test_utils;
+pub mod trace;
 mod utils;
 
 pub type RcBoard<B> = utils::rc::MutRc<Board<B>>;
@@ -1,39 +1,50 @@
 use std::{
     fmt::Display,
     fs,
-    io::{self, stdin, Read},
+    io::{self, stdin, BufWriter, Read},
+    path::Path,
+    thread,
+    time::Duration,
 };
 
-use self::{
-    block::{binary::BinaryBlock, multicolor::ColoredBlock, Block},
-    board::Board,
-    cli::Params,
-    parser::{BoardParser, NetworkReader, ParseError, PuzzleScheme},
-    render::{Renderer, ShellRenderer},
-    solver::{
-        line::{DynamicColor, DynamicSolver},
-        probing::FullProbe1,
+use nonogrid::{
+    batch::{self, PuzzleReport, SummaryFormat},
+    generator::Generator,
+    parser::{
+        self, BoardParser, DetectedParser, NetworkReader, ParseError, PuzzleFormat, PuzzleScheme,
+        WriteError,
     },
-    utils::rc::MutRc,
+    render::{RasterFormat, RasterRenderer, Renderer, ShellRenderer},
+    trace::SolveTrace,
+    Backend, BinaryBlock, Block, Board, ColoredBlock, DynamicColor, FullProbe, LinePosition,
+    LineSolver, RcBoard, SearchOptions, Uniqueness, WideColoredBlock,
 };
 
-mod block;
-mod board;
-mod cache;
-mod parser;
-mod render;
-mod solver;
-mod utils;
+use self::cli::Params;
 
 #[cfg(feature = "clap")]
 mod cli {
-    use std::str::FromStr;
+    use std::{
+        iter::once,
+        str::FromStr,
+        time::{SystemTime, UNIX_EPOCH},
+    };
 
     use clap::{
         crate_authors, crate_description, crate_name, crate_version, value_t, App, Arg, ArgMatches,
+        Error, ErrorKind, SubCommand,
+    };
+    use nonogrid::ChoosePixel;
+
+    use super::{
+        fs, read_stdin, Backend, BatchOptions, ConvertOptions, Duration, GenerateOptions,
+        Generator, ImageOptions, Mode, ParseError, PuzzleFormat, RasterFormat, SearchOptions,
+        SearchParams, Source, SummaryFormat,
     };
 
-    use super::{fs, read_stdin, ParseError, SearchOptions, Source};
+    const AUTO_FORMAT: &str = "auto";
+    /// The default pause between the frames of the `--animate` mode, in milliseconds.
+    const DEFAULT_FRAME_DELAY: u64 = 50;
 
     pub(super) struct Params<'a> {
         matches: ArgMatches<'a>,
@@ -41,6 +52,12 @@ mod cli {
 
     impl Params<'_> {
         pub(super) fn new() -> Self {
+            let choose_pixel_help = format!(
+                "How the backtracking search chooses the cell to guess: {}. \
+                 The `random` strategy takes the optional seed as `random:SEED`",
+                ChoosePixel::variants().collect::<Vec<_>>().join(", ")
+            );
+
             let matches = App::new(crate_name!())
                 .version(crate_version!())
                 .about(crate_description!())
@@ -58,18 +75,146 @@ mod cli {
                     Arg::with_name("nonograms-org").help("Solve puzzle from http://www.nonograms.org/ with specified ID")
                         .short("o").long("nonograms-org").requires("INPUT").conflicts_with("webpbn")
                 )
+                .arg(
+                    Arg::with_name("backend").help("The algorithm to search the solutions of the puzzles that cannot be solved logically")
+                        .short("b").long("backend").takes_value(true)
+                        .possible_values(&Backend::variants().collect::<Vec<_>>())
+                        .default_value(Backend::default().name())
+                )
+                .arg(
+                    Arg::with_name("choose-pixel").help(&choose_pixel_help)
+                        .long("choose-pixel").takes_value(true).value_name("STRATEGY")
+                )
+                .arg(
+                    Arg::with_name("threads").help("The number of threads to split the probing and the backtracking search between (requires the threaded feature)")
+                        .long("threads").takes_value(true).value_name("N").default_value("1")
+                )
                 .args_from_usage(
                     "-m, --max-solutions=[THRESHOLD] 'Stop searching after finding enough solutions'
              -t, --timeout=[SECONDS] 'Stop searching after specified timeout in seconds'
              -d, --max-depth=[DEPTH] 'Stop searching after reaching specified search depth'",
                 )
+                .arg(
+                    Arg::with_name("check-unique").help("Only check whether the puzzle has exactly one solution. Exit with 0 if it does, 2 if no solutions, 3 if multiple solutions")
+                        .short("u").long("check-unique").conflicts_with_all(&["max-solutions", "image"])
+                )
+                .arg(
+                    Arg::with_name("difficulty").help("Only rate how hard the puzzle is: which stage of the solver is enough to solve it and how much work it takes")
+                        .long("difficulty").conflicts_with_all(&["check-unique", "max-solutions", "image"])
+                )
+                .arg(
+                    Arg::with_name("count").help("Only count the solutions (up to --max-solutions) without keeping them in memory. Exit with 0 if there is exactly one, 2 if no solutions, 3 if multiple solutions")
+                        .long("count").conflicts_with_all(&["check-unique", "difficulty", "image"])
+                )
+                .arg(
+                    Arg::with_name("image").help("Save the board as an image after solving (PNG, PPM or PBM, based on the extension)")
+                        .short("i").long("image").takes_value(true).value_name("FILE")
+                )
+                .arg(
+                    Arg::with_name("cell-size").help("The size of every cell in the saved image, in pixels")
+                        .long("cell-size").takes_value(true).value_name("PIXELS").requires("image")
+                )
+                .arg(
+                    Arg::with_name("trace").help("Record every step of the solver into the file as JSON Lines")
+                        .long("trace").takes_value(true).value_name("FILE")
+                        .conflicts_with_all(&["check-unique", "difficulty", "count"])
+                )
+                .arg(
+                    Arg::with_name("animate").help("Redraw the board in place on every step of the solver, highlighting the clues of the line being solved")
+                        .long("animate").conflicts_with("trace")
+                )
+                .arg(
+                    Arg::with_name("frame-delay").help("The pause after every frame of the animation, in milliseconds")
+                        .long("frame-delay").takes_value(true).value_name("MS").requires("animate")
+                )
+                .subcommand(
+                    SubCommand::with_name("convert")
+                        .about("Print the puzzle in another format")
+                        .arg(
+                            Arg::with_name("INPUT")
+                                .help("The nonogram file to convert. When no input is present, read from the stdin.")
+                                .index(1)
+                        )
+                        .arg(
+                            Arg::with_name("from").help("The format of the input")
+                                .long("from").takes_value(true)
+                                .possible_values(&once(AUTO_FORMAT).chain(PuzzleFormat::variants()).collect::<Vec<_>>())
+                                .default_value(AUTO_FORMAT)
+                        )
+                        .arg(
+                            Arg::with_name("to").help("The format of the output")
+                                .long("to").takes_value(true).required(true)
+                                .possible_values(&PuzzleFormat::writable_variants().collect::<Vec<_>>())
+                        )
+                )
+                .subcommand(
+                    SubCommand::with_name("generate")
+                        .about("Print the random puzzle with the unique solution")
+                        .arg(
+                            Arg::with_name("width").help("The number of columns")
+                                .long("width").takes_value(true).required(true)
+                        )
+                        .arg(
+                            Arg::with_name("height").help("The number of rows")
+                                .long("height").takes_value(true).required(true)
+                        )
+                        .arg(
+                            Arg::with_name("density").help("The probability of every cell to be filled, from 0 to 1")
+                                .long("density").takes_value(true)
+                        )
+                        .arg(
+                            Arg::with_name("colors").help("Generate the colored puzzle with the given colors instead of the black-and-white one")
+                                .long("colors").takes_value(true).use_delimiter(true).value_name("COLOR")
+                        )
+                        .arg(
+                            Arg::with_name("seed").help("The seed for the random generator to reproduce the puzzle")
+                                .long("seed").takes_value(true)
+                        )
+                        .arg(
+                            Arg::with_name("to").help("The format of the output")
+                                .long("to").takes_value(true)
+                                .possible_values(&PuzzleFormat::writable_variants().collect::<Vec<_>>())
+                                .default_value(PuzzleFormat::Toml.name())
+                        )
+                )
+                .subcommand(
+                    SubCommand::with_name("batch")
+                        .about("Solve every puzzle in the directory and print the summary of the results")
+                        .arg(
+                            Arg::with_name("DIR")
+                                .help("The directory with the nonogram files")
+                                .index(1).required(true)
+                        )
+                        .arg(
+                            Arg::with_name("timeout").help("Stop searching every puzzle after specified timeout in seconds")
+                                .short("t").long("timeout").takes_value(true).value_name("SECONDS")
+                        )
+                        .arg(
+                            Arg::with_name("summary").help("The format of the summary")
+                                .long("summary").takes_value(true)
+                                .possible_values(&SummaryFormat::variants().collect::<Vec<_>>())
+                                .default_value(SummaryFormat::Csv.name())
+                        )
+                        .arg(
+                            Arg::with_name("jobs").help("The number of puzzles to solve in parallel (requires the threaded feature)")
+                                .short("j").long("jobs").takes_value(true).value_name("N")
+                                .default_value("1")
+                        )
+                )
                 .get_matches();
 
             Self { matches }
         }
 
+        /// The arguments of the subcommand (if any) or the main ones.
+        fn input_matches(&self) -> &ArgMatches<'_> {
+            self.matches
+                .subcommand_matches("convert")
+                .unwrap_or(&self.matches)
+        }
+
         pub(super) fn get_content(&self) -> Result<(Source, String), ParseError> {
-            let input_id = self.matches.value_of("INPUT");
+            let input_id = self.input_matches().value_of("INPUT");
 
             if self.matches.is_present("webpbn") {
                 return Ok((
@@ -100,19 +245,170 @@ mod cli {
             Ok((Source::LocalFile, content))
         }
 
-        pub(super) fn get_search_options(&self) -> SearchOptions {
+        pub(super) fn get_search_params(&self) -> SearchParams {
             (
                 self.parse_arg("max-solutions"),
                 self.parse_arg("timeout"),
                 self.parse_arg("max-depth"),
+                self.get_search_options(),
             )
         }
 
+        pub(super) fn get_search_options(&self) -> SearchOptions {
+            let threads: usize = Self::required_arg_of(&self.matches, "threads");
+            if threads == 0 {
+                Error::with_description("At least one thread is required", ErrorKind::InvalidValue)
+                    .exit();
+            }
+            if threads > 1 && !cfg!(feature = "threaded") {
+                Error::with_description(
+                    "The parallel search is not available (hint: add --features=threaded)",
+                    ErrorKind::InvalidValue,
+                )
+                .exit();
+            }
+
+            SearchOptions {
+                choose_pixel: self.parse_arg("choose-pixel").unwrap_or_default(),
+                threads,
+            }
+        }
+
+        pub(super) fn get_backend(&self) -> Backend {
+            self.parse_arg("backend").unwrap_or_default()
+        }
+
+        pub(super) fn get_mode(&self) -> Mode {
+            if self.matches.is_present("check-unique") {
+                Mode::CheckUnique
+            } else if self.matches.is_present("difficulty") {
+                Mode::RateDifficulty
+            } else if self.matches.is_present("count") {
+                Mode::CountSolutions
+            } else {
+                Mode::Solve
+            }
+        }
+
+        pub(super) fn get_image_options(&self) -> Option<ImageOptions> {
+            let path = self.matches.value_of("image")?;
+            let format = RasterFormat::from_path(path)
+                .unwrap_or_else(|e| Error::with_description(&e, ErrorKind::InvalidValue).exit());
+            let cell_size = self.parse_arg("cell-size");
+            if cell_size == Some(0) {
+                Error::with_description(
+                    "The cell should be at least one pixel",
+                    ErrorKind::InvalidValue,
+                )
+                .exit();
+            }
+            Some((path.to_string(), format, cell_size))
+        }
+
+        pub(super) fn get_trace_path(&self) -> Option<String> {
+            self.matches.value_of("trace").map(ToString::to_string)
+        }
+
+        pub(super) fn get_frame_delay(&self) -> Option<Duration> {
+            if !self.matches.is_present("animate") {
+                return None;
+            }
+
+            let millis = self.parse_arg("frame-delay").unwrap_or(DEFAULT_FRAME_DELAY);
+            Some(Duration::from_millis(millis))
+        }
+
+        pub(super) fn get_convert_options(&self) -> Option<ConvertOptions> {
+            let matches = self.matches.subcommand_matches("convert")?;
+            let from = if matches.value_of("from") == Some(AUTO_FORMAT) {
+                None
+            } else {
+                Self::parse_arg_of(matches, "from")
+            };
+            let to = Self::parse_arg_of(matches, "to").expect("The output format is required");
+            Some((from, to))
+        }
+
+        pub(super) fn get_generate_options(&self) -> Option<GenerateOptions> {
+            let matches = self.matches.subcommand_matches("generate")?;
+            let seed = Self::parse_arg_of(matches, "seed").unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|since_epoch| since_epoch.as_secs())
+                    .unwrap_or(0)
+            });
+            let width = Self::required_arg_of(matches, "width");
+            let height = Self::required_arg_of(matches, "height");
+            let invalid = |err: String| -> Generator {
+                Error::with_description(&err, ErrorKind::InvalidValue).exit()
+            };
+
+            let mut generator = Generator::new(width, height, seed).unwrap_or_else(invalid);
+            if matches.is_present("density") {
+                let density = Self::required_arg_of(matches, "density");
+                generator = generator.with_density(density).unwrap_or_else(invalid);
+            }
+            if let Some(colors) = matches.values_of("colors") {
+                let colors: Vec<_> = colors.collect();
+                generator = generator.with_colors(&colors).unwrap_or_else(invalid);
+            }
+
+            let to = Self::parse_arg_of(matches, "to").expect("The output format has a default");
+            Some((generator, to))
+        }
+
+        pub(super) fn get_batch_options(&self) -> Option<BatchOptions> {
+            let matches = self.matches.subcommand_matches("batch")?;
+            let dir = matches.value_of("DIR").expect("The directory is required");
+            let summary =
+                Self::parse_arg_of(matches, "summary").expect("The summary format has a default");
+
+            let jobs: usize = Self::required_arg_of(matches, "jobs");
+            if jobs == 0 {
+                Error::with_description("At least one job is required", ErrorKind::InvalidValue)
+                    .exit();
+            }
+            if jobs > 1 && !cfg!(feature = "threaded") {
+                Error::with_description(
+                    "The parallel solving is not available (hint: add --features=threaded)",
+                    ErrorKind::InvalidValue,
+                )
+                .exit();
+            }
+
+            Some((
+                dir.to_string(),
+                Self::parse_arg_of(matches, "timeout"),
+                summary,
+                jobs,
+            ))
+        }
+
+        /// Exit with the error if the argument is missing or cannot be parsed.
+        fn required_arg_of<T>(matches: &ArgMatches<'_>, name: &str) -> T
+        where
+            T: FromStr,
+        {
+            Self::parse_arg_of(matches, name).unwrap_or_else(|| {
+                Error::with_description(
+                    &format!("The argument --{} is required", name),
+                    ErrorKind::MissingRequiredArgument,
+                )
+                .exit()
+            })
+        }
+
         fn parse_arg<T>(&self, name: &str) -> Option<T>
         where
             T: FromStr,
         {
-            let matches = &self.matches;
+            Self::parse_arg_of(&self.matches, name)
+        }
+
+        fn parse_arg_of<T>(matches: &ArgMatches<'_>, name: &str) -> Option<T>
+        where
+            T: FromStr,
+        {
             if matches.is_present(name) {
                 let value = value_t!(matches, name, T).unwrap_or_else(|e| e.exit());
                 return Some(value);
@@ -127,7 +423,10 @@ mod cli {
 mod cli {
     use std::env;
 
-    use super::{fs, read_stdin, ParseError, SearchOptions, Source};
+    use super::{
+        fs, read_stdin, Backend, BatchOptions, ConvertOptions, Duration, GenerateOptions,
+        ImageOptions, Mode, ParseError, SearchOptions, SearchParams, Source,
+    };
 
     pub(super) struct Params {
         file_name: Option<String>,
@@ -149,8 +448,53 @@ mod cli {
         }
 
         #[allow(clippy::unused_self)]
-        pub(super) const fn get_search_options(&self) -> SearchOptions {
-            (None, None, None)
+        pub(super) fn get_search_params(&self) -> SearchParams {
+            (None, None, None, self.get_search_options())
+        }
+
+        #[allow(clippy::unused_self)]
+        pub(super) fn get_search_options(&self) -> SearchOptions {
+            SearchOptions::default()
+        }
+
+        #[allow(clippy::unused_self)]
+        pub(super) fn get_backend(&self) -> Backend {
+            Backend::default()
+        }
+
+        #[allow(clippy::unused_self)]
+        pub(super) const fn get_mode(&self) -> Mode {
+            Mode::Solve
+        }
+
+        #[allow(clippy::unused_self)]
+        pub(super) const fn get_image_options(&self) -> Option<ImageOptions> {
+            None
+        }
+
+        #[allow(clippy::unused_self)]
+        pub(super) const fn get_trace_path(&self) -> Option<String> {
+            None
+        }
+
+        #[allow(clippy::unused_self)]
+        pub(super) const fn get_frame_delay(&self) -> Option<Duration> {
+            None
+        }
+
+        #[allow(clippy::unused_self)]
+        pub(super) const fn get_convert_options(&self) -> Option<ConvertOptions> {
+            None
+        }
+
+        #[allow(clippy::unused_self)]
+        pub(super) const fn get_generate_options(&self) -> Option<GenerateOptions> {
+            None
+        }
+
+        #[allow(clippy::unused_self)]
+        pub(super) const fn get_batch_options(&self) -> Option<BatchOptions> {
+            None
         }
     }
 }
@@ -162,9 +506,11 @@ fn read_stdin() -> Result<String, io::Error> {
     Ok(buffer)
 }
 
+#[derive(Debug, PartialEq)]
 enum SolverResult {
     Satisfied,
     Unsatisfied,
+    Ambiguous,
     Error(String),
 }
 
@@ -174,17 +520,105 @@ fn main() -> Result<(), ParseError> {
 
     let params = Params::new();
     let search_options = params.get_search_options();
+
+    if let Some((from, to)) = params.get_convert_options() {
+        let (_source, content) = params.get_content()?;
+        let parser = if let Some(from) = from {
+            DetectedParser::with_content_and_format(&content, from)?
+        } else {
+            DetectedParser::with_content(&content)?
+        };
+
+        match convert(&parser, to) {
+            Ok(converted) => print!("{}", converted),
+            Err(WriteError(err_msg)) => {
+                eprintln!("Error: {}", err_msg);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some((generator, to)) = params.get_generate_options() {
+        let mut generator = generator.with_search_options(search_options);
+        let generated = match generator.scheme() {
+            PuzzleScheme::BlackAndWhite => generator
+                .generate::<BinaryBlock>()
+                .map(|board| to.write(&board)),
+            PuzzleScheme::MultiColor => generator
+                .generate::<ColoredBlock>()
+                .map(|board| to.write(&board)),
+            PuzzleScheme::WideColor => generator
+                .generate::<WideColoredBlock>()
+                .map(|board| to.write(&board)),
+        };
+
+        match generated {
+            Some(Ok(puzzle)) => print!("{}", puzzle),
+            Some(Err(WriteError(err_msg))) => {
+                eprintln!("Error: {}", err_msg);
+                std::process::exit(1);
+            }
+            None => {
+                eprintln!("Error: Cannot generate the puzzle with the unique solution");
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some((dir, timeout, summary, jobs)) = params.get_batch_options() {
+        let reports = match solve_dir(Path::new(&dir), timeout, search_options, jobs) {
+            Ok(reports) => reports,
+            Err(err) => {
+                eprintln!("Error: Cannot read the directory {:?}: {}", dir, err);
+                std::process::exit(1);
+            }
+        };
+
+        let stdout = io::stdout();
+        if let Err(err) = summary.write(&reports, BufWriter::new(stdout.lock())) {
+            eprintln!("Error: Cannot write the summary: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let search_params = params.get_search_params();
+    let backend = params.get_backend();
+    let mode = params.get_mode();
+    let image_options = params.get_image_options();
+    let trace_path = params.get_trace_path();
+    let frame_delay = params.get_frame_delay();
     let (source, content) = params.get_content()?;
 
     let result = match source {
         Source::LocalFile => run(
-            &parser::DetectedParser::with_content(&content)?,
-            search_options,
+            &DetectedParser::with_content(&content)?,
+            backend,
+            search_params,
+            mode,
+            image_options,
+            trace_path,
+            frame_delay,
+        ),
+        Source::WebPbn => run(
+            &parser::WebPbn::read_remote(&content)?,
+            backend,
+            search_params,
+            mode,
+            image_options,
+            trace_path,
+            frame_delay,
         ),
-        Source::WebPbn => run(&parser::WebPbn::read_remote(&content)?, search_options),
         Source::NonogramsOrg => run(
             &parser::NonogramsOrg::read_remote(&content)?,
-            search_options,
+            backend,
+            search_params,
+            mode,
+            image_options,
+            trace_path,
+            frame_delay,
         ),
     };
 
@@ -194,6 +628,9 @@ fn main() -> Result<(), ParseError> {
         SolverResult::Unsatisfied => {
             std::process::exit(2);
         }
+        SolverResult::Ambiguous => {
+            std::process::exit(3);
+        }
         SolverResult::Error(err_msg) => {
             eprintln!("Error: {}", err_msg);
             std::process::exit(1);
@@ -201,115 +638,371 @@ fn main() -> Result<(), ParseError> {
     }
 }
 
-fn run<P>(board_parser: &P, search_options: SearchOptions) -> SolverResult
+#[cfg(feature = "threaded")]
+fn solve_dir(
+    dir: &Path,
+    timeout: Option<u32>,
+    options: SearchOptions,
+    jobs: usize,
+) -> io::Result<Vec<PuzzleReport>> {
+    if jobs > 1 {
+        batch::solve_dir_parallel(dir, timeout, options, jobs)
+    } else {
+        batch::solve_dir(dir, timeout, options)
+    }
+}
+
+#[cfg(not(feature = "threaded"))]
+fn solve_dir(
+    dir: &Path,
+    timeout: Option<u32>,
+    options: SearchOptions,
+    _jobs: usize,
+) -> io::Result<Vec<PuzzleReport>> {
+    batch::solve_dir(dir, timeout, options)
+}
+
+fn convert<P>(board_parser: &P, to: PuzzleFormat) -> Result<String, WriteError>
 where
     P: BoardParser,
 {
-    let result = match board_parser.infer_scheme() {
+    let parse_error = |err: ParseError| WriteError(err.0);
+    match board_parser.infer_scheme() {
         PuzzleScheme::BlackAndWhite => {
-            run_with_block::<BinaryBlock, _>(board_parser, search_options)
+            to.write(&board_parser.parse::<BinaryBlock>().map_err(parse_error)?)
         }
-        PuzzleScheme::MultiColor => run_with_block::<ColoredBlock, _>(board_parser, search_options),
-    };
-    return result;
+        PuzzleScheme::MultiColor => {
+            to.write(&board_parser.parse::<ColoredBlock>().map_err(parse_error)?)
+        }
+        PuzzleScheme::WideColor => to.write(
+            &board_parser
+                .parse::<WideColoredBlock>()
+                .map_err(parse_error)?,
+        ),
+    }
 }
 
-fn run_with_block<B, P>(board_parser: &P, search_options: SearchOptions) -> SolverResult
+fn run<P>(
+    board_parser: &P,
+    backend: Backend,
+    search_params: SearchParams,
+    mode: Mode,
+    image_options: Option<ImageOptions>,
+    trace_path: Option<String>,
+    frame_delay: Option<Duration>,
+) -> SolverResult
+where
+    P: BoardParser,
+{
+    match board_parser.infer_scheme() {
+        PuzzleScheme::BlackAndWhite => run_with_block::<BinaryBlock, _>(
+            board_parser,
+            backend,
+            search_params,
+            mode,
+            image_options,
+            trace_path,
+            frame_delay,
+        ),
+        PuzzleScheme::MultiColor => run_with_block::<ColoredBlock, _>(
+            board_parser,
+            backend,
+            search_params,
+            mode,
+            image_options,
+            trace_path,
+            frame_delay,
+        ),
+        PuzzleScheme::WideColor => run_with_block::<WideColoredBlock, _>(
+            board_parser,
+            backend,
+            search_params,
+            mode,
+            image_options,
+            trace_path,
+            frame_delay,
+        ),
+    }
+}
+
+fn run_with_block<B, P>(
+    board_parser: &P,
+    backend: Backend,
+    search_params: SearchParams,
+    mode: Mode,
+    image_options: Option<ImageOptions>,
+    trace_path: Option<String>,
+    frame_delay: Option<Duration>,
+) -> SolverResult
 where
     B: 'static + Block + Display,
     B::Color: DynamicColor + Display,
     P: BoardParser,
 {
-    let board = {
-        let mut board = board_parser.parse::<B>();
-        board.reduce_colors();
-        board
+    let board = match board_parser.parse::<B>() {
+        Ok(mut board) => {
+            board.reduce_colors();
+            board
+        }
+        Err(ParseError(err_msg)) => return SolverResult::Error(err_msg),
     };
-    let board = MutRc::new(board);
-    let r = ShellRenderer::with_board(MutRc::clone(&board));
-
-    #[cfg(not(feature = "sat"))]
-    {
-        let backtracking = match solver::run::<_, DynamicSolver<_>, FullProbe1<_>>(
-            MutRc::clone(&board),
-            search_options.0,
-            search_options.1,
-            search_options.2,
-        ) {
-            Ok(res) => res,
-            Err(err) => return SolverResult::Error(format!("Solver failed: {}", err)),
-        };
+    let board = RcBoard::new(board);
+    let r = ShellRenderer::with_board(RcBoard::clone(&board));
+    let trace = trace_path.as_ref().map(|_| SolveTrace::attach(&board));
+    if let Some(delay) = frame_delay {
+        animate(&board, delay);
+    }
 
-        println!("{}", r.render());
-
-        if let Some(backtracking) = backtracking {
-            let solutions = backtracking.solutions;
-
-            // If there are multiple solutions or the board isn't fully solved
-            if !solutions.is_empty() && (!board.read().is_solved_full() || solutions.len() > 1) {
-                println!("Backtracking found {} solutions:", solutions.len());
-                for (i, solution) in solutions.into_iter().enumerate() {
-                    if i > 0 {
-                        let diff = board.read().diff(&solution);
-                        assert!(!diff.is_empty());
-                        println!("Diff with previous solution: {:?}", diff);
-                    }
-                    Board::restore_with_callback(&board, solution);
-                    println!("{}-th solution:", i + 1);
-                    println!("{}", r.render_simple());
-                }
+    let (max_solutions, timeout, max_depth, options) = search_params;
+    match mode {
+        Mode::Solve => {}
+        Mode::CheckUnique => return check_uniqueness(&board, &r, backend, search_params),
+        Mode::RateDifficulty => return rate_difficulty(&board, search_params),
+        Mode::CountSolutions => {
+            return count_solutions(&board, backend, search_params);
+        }
+    }
 
-                // Return unsatisfied right after printing solutions
-                return SolverResult::Unsatisfied;
-            }
+    let solutions = match nonogrid::solve::<_, LineSolver<_>, FullProbe<_>>(
+        RcBoard::clone(&board),
+        backend,
+        max_solutions,
+        timeout,
+        max_depth,
+        options,
+    ) {
+        Ok(res) => res,
+        Err(err) => return SolverResult::Error(format!("Solver failed: {}", err)),
+    };
+
+    println!("{}", r.render());
 
-            if log::log_enabled!(log::Level::Warn) {
-                let search_tree = backtracking.search_tree.read();
-                if !search_tree.is_empty() {
-                    println!("Searching progress: {:?}", search_tree);
+    let mut found = 0;
+    let mut stopped = None;
+    if let Some(solutions) = solutions {
+        for (i, solution) in solutions.enumerate() {
+            let solution = match solution {
+                Ok(solution) => solution,
+                Err(err) => {
+                    log::warn!("The search stopped: {}", err);
+                    stopped = Some(err);
+                    break;
                 }
+            };
+            if i > 0 {
+                let diff = board.read().diff(&solution);
+                assert!(!diff.is_empty());
+                println!("Diff with previous solution: {:?}", diff);
             }
+
+            Board::restore_with_callback(&board, solution);
+            log::warn!("{}-th solution found!", i + 1);
+            println!("{}-th solution:", i + 1);
+            println!("{}", r.render_simple());
+            found += 1;
         }
     }
 
-    // If we've made it to this point and the board is fully solved with only one solution
-    if board.read().is_solved_full() {
-        return SolverResult::Satisfied;
-    } else {
-        // This could be an unsatisfied state or a special error state, depending on your requirements
+    // the search runs lazily while iterating over the solutions
+    if let (Some(path), Some(trace)) = (trace_path, trace) {
+        if let Err(err) =
+            fs::File::create(&path).and_then(|file| trace.write_jsonl(BufWriter::new(file)))
+        {
+            return SolverResult::Error(format!("Cannot save the trace to {:?}: {}", path, err));
+        }
+    }
+
+    if let Some((path, format, cell_size)) = image_options {
+        let cell_size = cell_size.unwrap_or(RasterRenderer::<B>::DEFAULT_CELL_SIZE);
+        let image = RasterRenderer::with_board_and_cell_size(RcBoard::clone(&board), cell_size);
+        if let Err(err) =
+            fs::File::create(&path).and_then(|file| image.write(format, BufWriter::new(file)))
+        {
+            return SolverResult::Error(format!("Cannot save the image to {:?}: {}", path, err));
+        }
+    }
+
+    if let (0, Some(err)) = (found, stopped) {
+        return SolverResult::Error(format!("Solver failed: {}", err));
+    }
+
+    // several solutions do not satisfy the puzzle either
+    if found > 1 || (found == 0 && !board.read().is_solved_full()) {
         return SolverResult::Unsatisfied;
     }
 
-    #[cfg(feature = "sat")]
-    {
-        let sat_solutions = solver::run::<_, DynamicSolver<_>, FullProbe1<_>>(
-            MutRc::clone(&board),
-            search_options.0,
-        )
-        .unwrap();
-        println!("{}", r.render());
-
-        if let Some(solutions) = sat_solutions {
-            let mut found = false;
-            for (i, solution) in solutions.enumerate() {
-                if i > 0 {
-                    let diff = board.read().diff(&solution);
-                    assert!(!diff.is_empty());
-                    println!("Diff with previous solution: {:?}", diff);
-                }
+    SolverResult::Satisfied
+}
+
+/// Draw the board on every change made by the solver, replacing the previous frame.
+fn animate<B>(board: &RcBoard<B>, delay: Duration)
+where
+    B: 'static + Block + Display,
+    B::Color: Display,
+{
+    let frame = ShellRenderer::with_board(RcBoard::clone(board)).render();
+    println!("{}", frame);
+    // the size of the board and its clues never changes
+    let frame_height = frame.lines().count();
+
+    let redraw = move |board: RcBoard<B>, position: Option<LinePosition>| {
+        let r = ShellRenderer::with_board(board);
+        let frame = position.map_or_else(|| r.render(), |position| r.render_highlighted(position));
+        // move the cursor back to the first line of the previous frame
+        println!("\x1b[{}A\r{}", frame_height, frame);
+        thread::sleep(delay);
+    };
+
+    let mut board_ref = board.write();
+
+    let weak_board = RcBoard::downgrade(board);
+    board_ref.set_callback_on_set_line(move |is_column, index| {
+        if let Some(board) = weak_board.upgrade() {
+            let position = if is_column {
+                LinePosition::Column(index)
+            } else {
+                LinePosition::Row(index)
+            };
+            redraw(board, Some(position));
+        }
+    });
 
-                Board::restore_with_callback(&board, solution);
-                log::warn!("{}-th solution found!", i + 1);
+    let weak_board = RcBoard::downgrade(board);
+    board_ref.set_callback_on_change_color(move |_point| {
+        if let Some(board) = weak_board.upgrade() {
+            redraw(board, None);
+        }
+    });
+}
+
+fn check_uniqueness<B>(
+    board: &RcBoard<B>,
+    r: &ShellRenderer<B>,
+    backend: Backend,
+    search_params: SearchParams,
+) -> SolverResult
+where
+    B: 'static + Block + Display,
+    B::Color: DynamicColor + Display,
+{
+    let (_max_solutions, timeout, max_depth, options) = search_params;
+    let uniqueness = match nonogrid::check_unique::<_, LineSolver<_>, FullProbe<_>>(
+        RcBoard::clone(board),
+        backend,
+        timeout,
+        max_depth,
+        options,
+    ) {
+        Ok(res) => res,
+        Err(err) => return SolverResult::Error(format!("Uniqueness check failed: {}", err)),
+    };
+
+    match uniqueness {
+        Uniqueness::Unique(solution) => {
+            Board::restore_with_callback(board, solution);
+            println!("The puzzle has a unique solution:");
+            println!("{}", r.render_simple());
+            SolverResult::Satisfied
+        }
+        Uniqueness::Multiple {
+            first,
+            second,
+            diff,
+        } => {
+            println!("The puzzle has multiple solutions:");
+            for (i, solution) in vec![first, second].into_iter().enumerate() {
+                Board::restore_with_callback(board, solution);
                 println!("{}-th solution:", i + 1);
                 println!("{}", r.render_simple());
-                found = true;
             }
-            assert!(found, "Puzzle is unsatisfied");
+            println!("The solutions differ in {} cells: {:?}", diff.len(), diff);
+            SolverResult::Ambiguous
+        }
+        Uniqueness::NoSolutions => {
+            println!("The puzzle has no solutions");
+            SolverResult::Unsatisfied
+        }
+    }
+}
+
+fn count_solutions<B>(
+    board: &RcBoard<B>,
+    backend: Backend,
+    search_params: SearchParams,
+) -> SolverResult
+where
+    B: 'static + Block + Display,
+    B::Color: DynamicColor + Display,
+{
+    let (max_solutions, timeout, max_depth, options) = search_params;
+    let count = match nonogrid::count_solutions::<_, LineSolver<_>, FullProbe<_>>(
+        RcBoard::clone(board),
+        backend,
+        max_solutions,
+        timeout,
+        max_depth,
+        options,
+    ) {
+        Ok(count) => count,
+        Err(err) => return SolverResult::Error(format!("Counting failed: {}", err)),
+    };
+
+    if max_solutions == Some(count) {
+        println!("Solutions: at least {}", count);
+    } else {
+        println!("Solutions: {}", count);
+    }
+
+    match count {
+        0 => SolverResult::Unsatisfied,
+        1 => SolverResult::Satisfied,
+        _ => SolverResult::Ambiguous,
+    }
+}
+
+fn rate_difficulty<B>(board: &RcBoard<B>, search_params: SearchParams) -> SolverResult
+where
+    B: 'static + Block + Display,
+    B::Color: DynamicColor + Display,
+{
+    let (_max_solutions, timeout, max_depth, options) = search_params;
+    match nonogrid::rate_difficulty::<_, LineSolver<_>, FullProbe<_>>(
+        RcBoard::clone(board),
+        timeout,
+        max_depth,
+        options,
+    ) {
+        Ok(difficulty) => {
+            println!("Difficulty: {}", difficulty);
+            SolverResult::Satisfied
         }
+        Err(err) => SolverResult::Error(format!("Difficulty rating failed: {}", err)),
     }
 }
 
-type SearchOptions = (Option<usize>, Option<u32>, Option<usize>);
+/// The maximum number of solutions, the timeout, the maximum depth and the rest of the search options.
+type SearchParams = (Option<usize>, Option<u32>, Option<usize>, SearchOptions);
+/// The path, the format and the cell size of the image to save the board to.
+type ImageOptions = (String, RasterFormat, Option<usize>);
+/// The input (`None` to detect it automatically) and the output formats.
+type ConvertOptions = (Option<PuzzleFormat>, PuzzleFormat);
+/// The configured generator and the format of the generated puzzle.
+type GenerateOptions = (Generator, PuzzleFormat);
+/// The directory with the puzzles, the timeout for every puzzle, the format of the summary
+/// and the number of the puzzles to solve in parallel.
+type BatchOptions = (String, Option<u32>, SummaryFormat, usize);
+
+/// What to do with the puzzle.
+#[derive(Debug, Copy, Clone)]
+enum Mode {
+    Solve,
+    #[allow(dead_code)]
+    CheckUnique,
+    #[allow(dead_code)]
+    RateDifficulty,
+    #[allow(dead_code)]
+    CountSolutions,
+}
 
 enum Source {
     LocalFile,
@@ -318,3 +1011,40 @@ enum Source {
     #[allow(dead_code)]
     NonogramsOrg,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use nonogrid::{
+        parser::{BoardParser, DetectedParser},
+        Backend, BinaryBlock, SearchOptions,
+    };
+
+    use super::{run_with_block, Mode, SolverResult};
+
+    #[test]
+    fn trace_records_search() {
+        // every row and column has a single cell: the search is required
+        let content = "rows\n1\n1\n1\ncolumns\n1\n1\n1\n";
+        let board_parser = DetectedParser::with_content(content).unwrap();
+        let path = env::temp_dir().join(format!("nonogrid-trace-{}.jsonl", process::id()));
+
+        let result = run_with_block::<BinaryBlock, _>(
+            &board_parser,
+            Backend::Backtracking,
+            (None, None, None, SearchOptions::default()),
+            Mode::Solve,
+            None,
+            Some(path.to_string_lossy().into_owned()),
+            None,
+        );
+        assert_eq!(result, SolverResult::Unsatisfied);
+
+        let trace = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(trace
+            .lines()
+            .any(|line| line.contains(r#""event":"guess""#)));
+    }
+}
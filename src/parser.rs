@@ -1,4 +1,4 @@
-use std::{any::Any, fmt, fs, io, num::ParseIntError};
+use std::{any::Any, fmt, fs, io, num::ParseIntError, str::FromStr};
 
 use hashbrown::{HashMap, HashSet};
 use log::info;
@@ -10,13 +10,19 @@ use crate::{
             color::{ColorId, ColorPalette, ColorValue},
         },
         binary::BinaryBlock,
-        Block, Description,
+        Block, Clue, Description,
     },
     board::Board,
     utils::{iter::FindOk, product, rc::MutRc, split_sections},
 };
 
-pub use self::{ini::MyFormat, xml::WebPbn};
+pub use self::{
+    ini::MyFormat,
+    writer::{BoardWriter, WriteError},
+    xml::WebPbn,
+};
+
+mod writer;
 
 #[derive(Debug)]
 pub struct ParseError(pub String);
@@ -26,15 +32,15 @@ pub trait BoardParser: fmt::Debug {
     where
         Self: Sized;
 
-    fn parse<B>(&self) -> Board<B>
+    fn parse<B>(&self) -> Result<Board<B>, ParseError>
     where
         B: Block;
 
-    fn parse_rc<B>(&self) -> MutRc<Board<B>>
+    fn parse_rc<B>(&self) -> Result<MutRc<Board<B>>, ParseError>
     where
         B: Block,
     {
-        MutRc::new(self.parse())
+        self.parse().map(MutRc::new)
     }
 
     fn infer_scheme(&self) -> PuzzleScheme;
@@ -100,23 +106,60 @@ pub trait Paletted {
         colors
     }
 
-    fn default_palette(&self, white_name: &str, black_name: &str) -> ColorPalette {
+    fn default_palette(
+        &self,
+        white_name: &str,
+        black_name: &str,
+    ) -> Result<ColorPalette, ParseError> {
         let mut palette = ColorPalette::with_white_and_black(white_name, black_name);
 
         for (name, symbol, value) in &self.get_colors_sorted() {
             let val = ColorValue::parse(value);
-            palette.color_with_name_value_and_symbol(name, val, *symbol);
+            palette.color_with_name_value_and_symbol(name, val, *symbol)?;
         }
 
-        palette
+        Ok(palette)
     }
-    fn get_palette(&self) -> ColorPalette;
+    fn get_palette(&self) -> Result<ColorPalette, ParseError>;
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum PuzzleScheme {
     BlackAndWhite,
     MultiColor,
+    /// Too many colors for the `ColoredBlock`, so the `WideColoredBlock` is used.
+    WideColor,
+}
+
+impl PuzzleScheme {
+    /// The colors besides the white one that fit into the `MultiColor`.
+    const MULTICOLOR_LIMIT: usize = 31;
+
+    /// The colored scheme that fits all the colors of the palette.
+    pub fn with_palette(palette: &ColorPalette) -> Self {
+        let colors = palette
+            .colors()
+            .into_iter()
+            .filter(|color| color.id() != ColorPalette::WHITE_ID)
+            .count();
+
+        if colors > Self::MULTICOLOR_LIMIT {
+            Self::WideColor
+        } else {
+            Self::MultiColor
+        }
+    }
+}
+
+impl fmt::Display for PuzzleScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::BlackAndWhite => "black-and-white",
+            Self::MultiColor => "multicolor",
+            Self::WideColor => "wide-color",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 #[cfg(feature = "ini")]
@@ -124,8 +167,8 @@ mod ini {
     use serde::Deserialize;
 
     use super::{
-        Block, Board, BoardParser, ColorPalette, Description, LocalReader, Paletted, ParseError,
-        PuzzleScheme,
+        Block, Board, BoardParser, Clue, ColorPalette, Description, LocalReader, Paletted,
+        ParseError, PuzzleScheme,
     };
 
     #[derive(Debug, Deserialize)]
@@ -155,27 +198,32 @@ mod ini {
 
     impl BoardParser for MyFormat {
         fn with_content(content: &str) -> Result<Self, ParseError> {
-            Ok(toml::from_str(content)?)
+            let parser: Self = toml::from_str(content)?;
+            let _palette = parser.get_palette()?;
+            Ok(parser)
         }
 
-        fn parse<B>(&self) -> Board<B>
+        fn parse<B>(&self) -> Result<Board<B>, ParseError>
         where
             B: Block,
         {
             let clues = &self.clues;
-            let palette = self.get_palette();
-            Board::with_descriptions_and_palette(
-                Self::parse_clues(&clues.rows, &palette),
-                Self::parse_clues(&clues.columns, &palette),
+            let palette = self.get_palette()?;
+            Ok(Board::with_descriptions_and_palette(
+                Self::parse_clues(&clues.rows, &palette)?,
+                Self::parse_clues(&clues.columns, &palette)?,
                 Some(palette),
-            )
+            ))
         }
 
         fn infer_scheme(&self) -> PuzzleScheme {
             if let Some(colors) = &self.colors {
                 if let Some(defs) = &colors.defs {
                     if !defs.is_empty() {
-                        return PuzzleScheme::MultiColor;
+                        let palette = self
+                            .get_palette()
+                            .expect("The palette is checked when reading the content");
+                        return PuzzleScheme::with_palette(&palette);
                     }
                 }
             }
@@ -185,12 +233,12 @@ mod ini {
     }
 
     impl MyFormat {
-        fn parse_block<B>(block: &str, palette: &ColorPalette) -> B
+        fn parse_block<B>(block: &str, palette: &ColorPalette) -> Result<Clue<B>, ParseError>
         where
             B: Block,
         {
             let mut as_chars = block.chars();
-            let value_color_pos = as_chars.position(|c| !c.is_digit(10));
+            let value_color_pos = as_chars.position(|c| !c.is_ascii_digit() && c != '?');
             #[allow(clippy::option_if_let_else)]
             let (value, block_color) = if let Some(pos) = value_color_pos {
                 let (value, color) = block.split_at(pos);
@@ -200,10 +248,13 @@ mod ini {
             };
 
             let color_id = block_color.and_then(|name| palette.id_by_name(name));
-            B::from_str_and_color(value, color_id)
+            Ok(Clue::from_str_and_color(value, color_id)?)
         }
 
-        fn parse_line<B>(descriptions: &str, palette: &ColorPalette) -> Option<Vec<Description<B>>>
+        fn parse_line<B>(
+            descriptions: &str,
+            palette: &ColorPalette,
+        ) -> Result<Option<Vec<Description<B>>>, ParseError>
         where
             B: Block,
         {
@@ -214,39 +265,42 @@ mod ini {
                 .expect("Split returned empty");
 
             if non_comment.is_empty() {
-                return None;
+                return Ok(None);
             }
 
-            Some(
-                non_comment
-                    .split(',')
-                    .filter_map(|row| {
-                        let row = row.trim().trim_matches(&['\'', '"'][..]);
-                        if row.is_empty() {
-                            None
-                        } else {
-                            Some(Description::new(
-                                row.split_whitespace()
-                                    .map(|block| Self::parse_block(block, palette))
-                                    .collect(),
-                            ))
-                        }
-                    })
-                    .collect(),
-            )
+            non_comment
+                .split(',')
+                .filter_map(|row| {
+                    let row = row.trim().trim_matches(&['\'', '"'][..]);
+                    if row.is_empty() {
+                        None
+                    } else {
+                        Some(
+                            row.split_whitespace()
+                                .map(|block| Self::parse_block(block, palette))
+                                .collect::<Result<_, _>>()
+                                .map(Description::with_clues),
+                        )
+                    }
+                })
+                .collect::<Result<_, _>>()
+                .map(Some)
         }
 
         pub(super) fn parse_clues<B>(
             descriptions: &str,
             palette: &ColorPalette,
-        ) -> Vec<Description<B>>
+        ) -> Result<Vec<Description<B>>, ParseError>
         where
             B: Block,
         {
-            descriptions
-                .lines()
-                .flat_map(|line| Self::parse_line(line, palette).unwrap_or_default())
-                .collect()
+            let mut clues = vec![];
+            for line in descriptions.lines() {
+                if let Some(line_clues) = Self::parse_line(line, palette)? {
+                    clues.extend(line_clues);
+                }
+            }
+            Ok(clues)
         }
 
         ///```
@@ -278,7 +332,7 @@ mod ini {
             vec![]
         }
 
-        fn get_palette(&self) -> ColorPalette {
+        fn get_palette(&self) -> Result<ColorPalette, ParseError> {
             self.default_palette("W", "B")
         }
     }
@@ -305,7 +359,7 @@ mod ini {
             Err(ParseError(Self::NO_FEATURE_ENABLED_MSG.to_string()))
         }
 
-        fn parse<B>(&self) -> Board<B>
+        fn parse<B>(&self) -> Result<Board<B>, ParseError>
         where
             B: Block,
         {
@@ -330,8 +384,8 @@ mod xml {
     use crate::utils::rc::{mutate_ref, read_ref, InteriorMutableRef};
 
     use super::{
-        Block, Board, BoardParser, ColorPalette, Description, LocalReader, NetworkReader, Paletted,
-        ParseError, PuzzleScheme,
+        Block, Board, BoardParser, Clue, ColorPalette, Description, LocalReader, NetworkReader,
+        Paletted, ParseError, PuzzleScheme,
     };
 
     #[derive(Debug)]
@@ -362,22 +416,24 @@ mod xml {
         fn with_content(content: &str) -> Result<Self, ParseError> {
             let package = xml::parser::parse(content)?;
 
-            Ok(Self {
+            let parser = Self {
                 package,
                 cached_colors: InteriorMutableRef::new(None),
                 cached_palette: InteriorMutableRef::new(None),
-            })
+            };
+            let _palette = parser.get_palette()?;
+            Ok(parser)
         }
 
-        fn parse<B>(&self) -> Board<B>
+        fn parse<B>(&self) -> Result<Board<B>, ParseError>
         where
             B: Block,
         {
-            Board::with_descriptions_and_palette(
-                self.parse_clues("rows"),
-                self.parse_clues("columns"),
-                Some(self.get_palette()),
-            )
+            Ok(Board::with_descriptions_and_palette(
+                self.parse_clues("rows")?,
+                self.parse_clues("columns")?,
+                Some(self.get_palette()?),
+            ))
         }
 
         fn infer_scheme(&self) -> PuzzleScheme {
@@ -387,14 +443,18 @@ mod xml {
                 return PuzzleScheme::BlackAndWhite;
             }
 
-            PuzzleScheme::MultiColor
+            PuzzleScheme::with_palette(
+                &self
+                    .get_palette()
+                    .expect("The palette is checked when reading the content"),
+            )
         }
     }
 
     impl WebPbn {
         const BASE_URL: &'static str = "http://webpbn.com";
 
-        fn parse_block<B>(block: &Node<'_>, palette: &ColorPalette) -> B
+        fn parse_block<B>(block: &Node<'_>, palette: &ColorPalette) -> Result<Clue<B>, ParseError>
         where
             B: Block,
         {
@@ -409,30 +469,35 @@ mod xml {
             };
 
             let color_id = block_color.and_then(|name| palette.id_by_name(name));
-            B::from_str_and_color(&value, color_id)
+            Ok(Clue::from_str_and_color(value.trim(), color_id)?)
         }
 
-        fn parse_line<B>(description: &Node<'_>, palette: &ColorPalette) -> Description<B>
+        fn parse_line<B>(
+            description: &Node<'_>,
+            palette: &ColorPalette,
+        ) -> Result<Description<B>, ParseError>
         where
             B: Block,
         {
-            Description::new(
-                description
-                    .children()
-                    .iter()
-                    .filter_map(|child| {
-                        if let Node::Text(_text) = child {
-                            // ignore newlines and whitespaces
-                            None
-                        } else {
-                            Some(Self::parse_block(child, palette))
-                        }
-                    })
-                    .collect(),
-            )
+            description
+                .children()
+                .iter()
+                .filter_map(|child| {
+                    if let Node::Text(_text) = child {
+                        // ignore newlines and whitespaces
+                        None
+                    } else {
+                        Some(Self::parse_block(child, palette))
+                    }
+                })
+                .collect::<Result<_, _>>()
+                .map(Description::with_clues)
         }
 
-        fn get_clues<B>(descriptions: &Nodeset<'_>, palette: &ColorPalette) -> Vec<Description<B>>
+        fn get_clues<B>(
+            descriptions: &Nodeset<'_>,
+            palette: &ColorPalette,
+        ) -> Result<Vec<Description<B>>, ParseError>
         where
             B: Block,
         {
@@ -443,7 +508,7 @@ mod xml {
                 .collect()
         }
 
-        fn parse_clues<B>(&self, type_: &str) -> Vec<Description<B>>
+        fn parse_clues<B>(&self, type_: &str) -> Result<Vec<Description<B>>, ParseError>
         where
             B: Block,
         {
@@ -452,9 +517,9 @@ mod xml {
                 .expect("XPath evaluation failed");
 
             if let Value::Nodeset(ns) = value {
-                Self::get_clues(&ns, &self.get_palette())
+                Self::get_clues(&ns, &self.get_palette()?)
             } else {
-                vec![]
+                Ok(vec![])
             }
         }
     }
@@ -504,13 +569,13 @@ mod xml {
             None
         }
 
-        fn _get_palette(&self) -> ColorPalette {
-            let mut palette = self.default_palette("white", "black");
+        fn _get_palette(&self) -> Result<ColorPalette, ParseError> {
+            let mut palette = self.default_palette("white", "black")?;
 
             if let Some(default_color) = self.get_default_color() {
-                palette.set_default(&default_color).unwrap();
+                palette.set_default(&default_color)?;
             }
-            palette
+            Ok(palette)
         }
     }
 
@@ -526,15 +591,15 @@ mod xml {
             result
         }
 
-        fn get_palette(&self) -> ColorPalette {
+        fn get_palette(&self) -> Result<ColorPalette, ParseError> {
             if let Some(palette) = read_ref(&self.cached_palette).as_ref() {
-                return palette.clone();
+                return Ok(palette.clone());
             }
 
-            let result = self._get_palette();
+            let result = self._get_palette()?;
             let mut cache = mutate_ref(&self.cached_palette);
             *cache = Some(result.clone());
-            result
+            Ok(result)
         }
     }
 }
@@ -560,7 +625,7 @@ mod xml {
             Err(ParseError(Self::NO_FEATURE_ENABLED_MSG.to_string()))
         }
 
-        fn parse<B>(&self) -> Board<B>
+        fn parse<B>(&self) -> Result<Board<B>, ParseError>
         where
             B: Block,
         {
@@ -592,11 +657,10 @@ impl NonogramsOrg {
     const CYPHER_SUFFIX: char = ';';
 
     fn extract_encoded_json(html: &str) -> Option<&str> {
-        #[allow(unused_imports)]
         use crate::utils::Stripper; // for Rust<1.45
 
         html.lines().find_map(|line| {
-            line.strip_prefix(Self::CYPHER_PREFIX)
+            Stripper::strip_prefix(line, Self::CYPHER_PREFIX)
                 .map(|line| line.trim_end_matches(Self::CYPHER_SUFFIX))
         })
     }
@@ -675,7 +739,9 @@ impl NonogramsOrg {
 
     fn get_solution_matrix(&self) -> Vec<Vec<ColorId>> {
         let (_colors, solution_matrix) = self.decipher();
-        let palette = self.get_palette();
+        let palette = self
+            .get_palette()
+            .expect("The palette is checked when reading the content");
 
         let mut mapping_cache = HashMap::new();
         solution_matrix
@@ -724,19 +790,26 @@ impl BoardParser for NonogramsOrg {
         let json = Self::extract_encoded_json(content)
             .ok_or_else(|| ParseError("Not found cypher in HTML content".to_string()))?;
 
-        Ok(Self {
+        let parser = Self {
             encoded: Self::parse_json(json),
-        })
+        };
+        let _palette = parser.get_palette()?;
+        Ok(parser)
     }
 
-    fn parse<B>(&self) -> Board<B>
+    fn parse<B>(&self) -> Result<Board<B>, ParseError>
     where
         B: Block,
     {
         let solution_matrix = self.get_solution_matrix();
         let (columns, rows) = clues_from_solution(&solution_matrix, 0);
 
-        Board::with_descriptions_and_palette(rows, columns, Some(self.get_palette()))
+        let palette = self.get_palette()?;
+        Ok(Board::with_descriptions_and_palette(
+            rows,
+            columns,
+            Some(palette),
+        ))
     }
 
     fn infer_scheme(&self) -> PuzzleScheme {
@@ -746,7 +819,11 @@ impl BoardParser for NonogramsOrg {
             return PuzzleScheme::BlackAndWhite;
         }
 
-        PuzzleScheme::MultiColor
+        PuzzleScheme::with_palette(
+            &self
+                .get_palette()
+                .expect("The palette is checked when reading the content"),
+        )
     }
 }
 
@@ -762,20 +839,21 @@ impl Paletted for NonogramsOrg {
             .collect()
     }
 
-    fn get_palette(&self) -> ColorPalette {
+    fn get_palette(&self) -> Result<ColorPalette, ParseError> {
         let mut palette = ColorPalette::with_white("W");
 
         for (name, _dumb_symbol, value) in &self.get_colors() {
             let val = ColorValue::parse(value);
-            palette.color_with_name_and_value(name, val);
+            palette.color_with_name_and_value(name, val)?;
         }
 
-        palette
+        Ok(palette)
     }
 }
 
-#[derive(Debug)]
-enum ParserKind {
+/// The puzzle formats that the `DetectedParser` understands.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PuzzleFormat {
     Toml,
     WebPbn,
     NonogramsOrg,
@@ -783,8 +861,105 @@ enum ParserKind {
     Simple,
 }
 
+impl PuzzleFormat {
+    const NAMES: [(Self, &'static str); 5] = [
+        (Self::Toml, "toml"),
+        (Self::WebPbn, "webpbn"),
+        (Self::NonogramsOrg, "nonograms-org"),
+        (Self::Olsak, "olsak"),
+        (Self::Simple, "simple"),
+    ];
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find_map(|&(format, name)| if format == self { Some(name) } else { None })
+            .expect("Every format should have a name")
+    }
+
+    /// The names of all the formats.
+    pub fn variants() -> impl Iterator<Item = &'static str> {
+        Self::NAMES.iter().map(|&(_format, name)| name)
+    }
+
+    /// The names of the formats that the board can be written into.
+    pub fn writable_variants() -> impl Iterator<Item = &'static str> {
+        Self::NAMES.iter().filter_map(|&(format, name)| {
+            if format.is_writable() {
+                Some(name)
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn is_writable(self) -> bool {
+        self != Self::NonogramsOrg
+    }
+
+    /// Guess the format of the puzzle by its content.
+    pub fn detect(content: &str) -> Self {
+        let trim_content = content.trim();
+        if trim_content.starts_with("<?xml") {
+            Self::WebPbn
+        } else if ["<!DOCTYPE HTML", "<html", NonogramsOrg::CYPHER_PREFIX]
+            .iter()
+            .any(|&prefix| trim_content.starts_with(prefix))
+        {
+            Self::NonogramsOrg
+        } else {
+            let lines: Vec<_> = trim_content.lines().map(str::trim).collect();
+            if lines.contains(&"[clues]") {
+                Self::Toml
+            } else if lines.contains(&": rows") {
+                Self::Olsak
+            } else {
+                Self::Simple
+            }
+        }
+    }
+
+    /// Serialize the board with the `BoardWriter` of the format.
+    pub fn write<B>(self, board: &Board<B>) -> Result<String, WriteError>
+    where
+        B: Block,
+    {
+        match self {
+            Self::Toml => MyFormat::write(board),
+            Self::WebPbn => WebPbn::write(board),
+            Self::NonogramsOrg => Err(WriteError(format!(
+                "Cannot write the puzzle in {} format",
+                self
+            ))),
+            Self::Olsak => OlsakParser::write(board),
+            Self::Simple => SimpleParser::write(board),
+        }
+    }
+}
+
+impl fmt::Display for PuzzleFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for PuzzleFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        Self::NAMES
+            .iter()
+            .find_map(|&(format, name)| if name == s { Some(format) } else { None })
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::variants().collect();
+                format!("Unknown puzzle format {:?}: expected one of {:?}", s, names)
+            })
+    }
+}
+
 pub struct DetectedParser {
-    parser_kind: ParserKind,
+    format: PuzzleFormat,
     inner: Box<dyn Any>,
 }
 
@@ -793,85 +968,73 @@ impl DetectedParser {
     where
         T: BoardParser + 'static,
     {
-        let expect_msg = format!("Parser should be created with {:?}", self.parser_kind);
+        let expect_msg = format!("Parser should be created with {:?}", self.format);
         self.inner.downcast_ref::<T>().expect(&expect_msg)
     }
+
+    /// Parse the content in the given format instead of detecting it.
+    pub fn with_content_and_format(
+        content: &str,
+        format: PuzzleFormat,
+    ) -> Result<Self, ParseError> {
+        let inner: Box<dyn Any> = match format {
+            PuzzleFormat::Toml => Box::new(MyFormat::with_content(content)?),
+            PuzzleFormat::WebPbn => Box::new(WebPbn::with_content(content)?),
+            PuzzleFormat::NonogramsOrg => Box::new(NonogramsOrg::with_content(content)?),
+            PuzzleFormat::Olsak => Box::new(OlsakParser::with_content(content)?),
+            PuzzleFormat::Simple => Box::new(SimpleParser::with_content(content)?),
+        };
+
+        Ok(Self { format, inner })
+    }
+
+    pub fn format(&self) -> PuzzleFormat {
+        self.format
+    }
 }
 
 impl BoardParser for DetectedParser {
     fn with_content(content: &str) -> Result<Self, ParseError> {
-        let trim_content = content.trim();
-        Ok(if trim_content.starts_with("<?xml") {
-            Self {
-                parser_kind: ParserKind::WebPbn,
-                inner: Box::new(WebPbn::with_content(content)?),
-            }
-        } else if ["<!DOCTYPE HTML", "<html", NonogramsOrg::CYPHER_PREFIX]
-            .iter()
-            .any(|&prefix| trim_content.starts_with(prefix))
-        {
-            Self {
-                parser_kind: ParserKind::NonogramsOrg,
-                inner: Box::new(NonogramsOrg::with_content(content)?),
-            }
-        } else {
-            let lines: Vec<_> = trim_content.lines().map(str::trim).collect();
-            if lines.contains(&"[clues]") {
-                Self {
-                    parser_kind: ParserKind::Toml,
-                    inner: Box::new(MyFormat::with_content(content)?),
-                }
-            } else if lines.contains(&": rows") {
-                Self {
-                    parser_kind: ParserKind::Olsak,
-                    inner: Box::new(OlsakParser::with_content(content)?),
-                }
-            } else {
-                Self {
-                    parser_kind: ParserKind::Simple,
-                    inner: Box::new(SimpleParser::with_content(content)?),
-                }
-            }
-        })
+        Self::with_content_and_format(content, PuzzleFormat::detect(content))
     }
 
     //noinspection RsTypeCheck
-    fn parse<B>(&self) -> Board<B>
+    fn parse<B>(&self) -> Result<Board<B>, ParseError>
     where
         B: Block,
     {
-        match self.parser_kind {
-            ParserKind::Toml => self.cast::<MyFormat>().parse::<B>(),
-            ParserKind::WebPbn => self.cast::<WebPbn>().parse::<B>(),
-            ParserKind::NonogramsOrg => self.cast::<NonogramsOrg>().parse::<B>(),
-            ParserKind::Olsak => self.cast::<OlsakParser>().parse::<B>(),
-            ParserKind::Simple => self.cast::<SimpleParser>().parse::<B>(),
+        match self.format {
+            PuzzleFormat::Toml => self.cast::<MyFormat>().parse::<B>(),
+            PuzzleFormat::WebPbn => self.cast::<WebPbn>().parse::<B>(),
+            PuzzleFormat::NonogramsOrg => self.cast::<NonogramsOrg>().parse::<B>(),
+            PuzzleFormat::Olsak => self.cast::<OlsakParser>().parse::<B>(),
+            PuzzleFormat::Simple => self.cast::<SimpleParser>().parse::<B>(),
         }
     }
 
     fn infer_scheme(&self) -> PuzzleScheme {
-        match self.parser_kind {
-            ParserKind::Toml => self.cast::<MyFormat>().infer_scheme(),
-            ParserKind::WebPbn => self.cast::<WebPbn>().infer_scheme(),
-            ParserKind::NonogramsOrg => self.cast::<NonogramsOrg>().infer_scheme(),
-            ParserKind::Olsak => self.cast::<OlsakParser>().infer_scheme(),
-            ParserKind::Simple => self.cast::<SimpleParser>().infer_scheme(),
+        match self.format {
+            PuzzleFormat::Toml => self.cast::<MyFormat>().infer_scheme(),
+            PuzzleFormat::WebPbn => self.cast::<WebPbn>().infer_scheme(),
+            PuzzleFormat::NonogramsOrg => self.cast::<NonogramsOrg>().infer_scheme(),
+            PuzzleFormat::Olsak => self.cast::<OlsakParser>().infer_scheme(),
+            PuzzleFormat::Simple => self.cast::<SimpleParser>().infer_scheme(),
         }
     }
 }
 
 impl fmt::Debug for DetectedParser {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        let inner = match self.parser_kind {
-            ParserKind::Toml => format!("{:?}", self.cast::<MyFormat>()),
-            ParserKind::WebPbn => format!("{:?}", self.cast::<WebPbn>()),
-            ParserKind::NonogramsOrg => format!("{:?}", self.cast::<NonogramsOrg>()),
-            ParserKind::Olsak => format!("{:?}", self.cast::<OlsakParser>()),
-            ParserKind::Simple => format!("{:?}", self.cast::<SimpleParser>()),
+        let inner = match self.format {
+            PuzzleFormat::Toml => format!("{:?}", self.cast::<MyFormat>()),
+            PuzzleFormat::WebPbn => format!("{:?}", self.cast::<WebPbn>()),
+            PuzzleFormat::NonogramsOrg => format!("{:?}", self.cast::<NonogramsOrg>()),
+            PuzzleFormat::Olsak => format!("{:?}", self.cast::<OlsakParser>()),
+            PuzzleFormat::Simple => format!("{:?}", self.cast::<SimpleParser>()),
         };
 
         f.debug_struct("DetectedParser")
-            .field("parser_kind", &self.parser_kind)
+            .field("format", &self.format)
             .field("inner", &inner)
             .finish()
     }
@@ -954,7 +1117,7 @@ impl BoardParser for OlsakParser {
 
         let colors = sections.remove(names[2]).unwrap_or_default();
 
-        Ok(Self {
+        let parser = Self {
             rows: splitted.remove(names[0]).expect("Rows section not found"),
             columns: splitted
                 .remove(names[1])
@@ -966,19 +1129,21 @@ impl BoardParser for OlsakParser {
                     (color.block_name.clone(), color)
                 })
                 .collect(),
-        })
+        };
+        let _palette = parser.get_palette()?;
+        Ok(parser)
     }
 
-    fn parse<B>(&self) -> Board<B>
+    fn parse<B>(&self) -> Result<Board<B>, ParseError>
     where
         B: Block,
     {
-        let palette = self.get_palette();
-        Board::with_descriptions_and_palette(
-            self.parse_clues(&self.rows, &palette),
-            self.parse_clues(&self.columns, &palette),
+        let palette = self.get_palette()?;
+        Ok(Board::with_descriptions_and_palette(
+            self.parse_clues(&self.rows, &palette)?,
+            self.parse_clues(&self.columns, &palette)?,
             Some(palette),
-        )
+        ))
     }
 
     fn infer_scheme(&self) -> PuzzleScheme {
@@ -987,7 +1152,10 @@ impl BoardParser for OlsakParser {
             names.sort_unstable();
 
             if names != ["black", "white"] {
-                return PuzzleScheme::MultiColor;
+                let palette = self
+                    .get_palette()
+                    .expect("The palette is checked when reading the content");
+                return PuzzleScheme::with_palette(&palette);
             }
         }
 
@@ -996,12 +1164,12 @@ impl BoardParser for OlsakParser {
 }
 
 impl OlsakParser {
-    fn parse_block<B>(&self, block: &str, palette: &ColorPalette) -> B
+    fn parse_block<B>(&self, block: &str, palette: &ColorPalette) -> Result<Clue<B>, ParseError>
     where
         B: Block,
     {
         let mut as_chars = block.chars();
-        let value_color_pos = as_chars.position(|c| !c.is_digit(10));
+        let value_color_pos = as_chars.position(|c| !c.is_ascii_digit() && c != '?');
 
         #[allow(clippy::option_if_let_else)]
         let (value, block_color) = if let Some(pos) = value_color_pos {
@@ -1016,26 +1184,29 @@ impl OlsakParser {
             .map(|color| &color.name);
 
         let color_id = color_name.and_then(|name| palette.id_by_name(name));
-        B::from_str_and_color(value, color_id)
+        Ok(Clue::from_str_and_color(value, color_id)?)
     }
 
-    fn parse_line<B>(&self, descriptions: &[String], palette: &ColorPalette) -> Description<B>
+    fn parse_line<B>(
+        &self,
+        descriptions: &[String],
+        palette: &ColorPalette,
+    ) -> Result<Description<B>, ParseError>
     where
         B: Block,
     {
-        Description::new(
-            descriptions
-                .iter()
-                .map(|block| self.parse_block(block, palette))
-                .collect(),
-        )
+        descriptions
+            .iter()
+            .map(|block| self.parse_block(block, palette))
+            .collect::<Result<_, _>>()
+            .map(Description::with_clues)
     }
 
     fn parse_clues<B>(
         &self,
         descriptions: &[Vec<String>],
         palette: &ColorPalette,
-    ) -> Vec<Description<B>>
+    ) -> Result<Vec<Description<B>>, ParseError>
     where
         B: Block,
     {
@@ -1054,7 +1225,7 @@ impl Paletted for OlsakParser {
             .collect()
     }
 
-    fn get_palette(&self) -> ColorPalette {
+    fn get_palette(&self) -> Result<ColorPalette, ParseError> {
         self.default_palette("white", "black")
     }
 }
@@ -1074,25 +1245,24 @@ impl From<ParseIntError> for ParseError {
 }
 
 impl SimpleParser {
-    fn parse_clues<B>(descriptions: &[Vec<String>]) -> Vec<Description<B>>
+    fn parse_clues<B>(descriptions: &[Vec<String>]) -> Result<Vec<Description<B>>, ParseError>
     where
         B: Block,
     {
         descriptions
             .iter()
             .map(|line| {
-                Description::new(
-                    line.iter()
-                        .filter_map(|block| {
-                            let block = block.trim();
-                            if block.is_empty() {
-                                None
-                            } else {
-                                Some(B::from_str_and_color(block, None))
-                            }
-                        })
-                        .collect(),
-                )
+                line.iter()
+                    .filter_map(|block| {
+                        let block = block.trim();
+                        if block.is_empty() {
+                            None
+                        } else {
+                            Some(Clue::from_str_and_color(block, None).map_err(ParseError))
+                        }
+                    })
+                    .collect::<Result<_, _>>()
+                    .map(Description::with_clues)
             })
             .collect()
     }
@@ -1150,7 +1320,7 @@ impl BoardParser for SimpleParser {
                 .lines()
                 .map(|line| {
                     line.chars()
-                        .map(|ch| ch.to_digit(10).expect("not a decimal digit"))
+                        .map(|ch| ColorId::from(ch.to_digit(10).expect("not a decimal digit")))
                         .collect()
                 })
                 .collect();
@@ -1223,16 +1393,15 @@ impl BoardParser for SimpleParser {
         })
     }
 
-    fn parse<B>(&self) -> Board<B>
+    fn parse<B>(&self) -> Result<Board<B>, ParseError>
     where
         B: Block,
     {
-        let palette = self.get_palette();
-        Board::with_descriptions_and_palette(
-            Self::parse_clues(&self.rows),
-            Self::parse_clues(&self.columns),
-            Some(palette),
-        )
+        Ok(Board::with_descriptions_and_palette(
+            Self::parse_clues(&self.rows)?,
+            Self::parse_clues(&self.columns)?,
+            Some(self.get_palette()?),
+        ))
     }
 
     fn infer_scheme(&self) -> PuzzleScheme {
@@ -1245,7 +1414,7 @@ impl Paletted for SimpleParser {
         vec![]
     }
 
-    fn get_palette(&self) -> ColorPalette {
+    fn get_palette(&self) -> Result<ColorPalette, ParseError> {
         self.default_palette("white", "black")
     }
 }
@@ -1253,7 +1422,11 @@ impl Paletted for SimpleParser {
 #[cfg(test)]
 #[cfg(feature = "ini")]
 mod tests {
-    use crate::block::{base::color::ColorPalette, binary::BinaryBlock, Description};
+    use crate::block::{
+        base::color::ColorPalette,
+        binary::{BinaryBlock, BinaryColor},
+        Clue, Description,
+    };
 
     use super::{BoardParser, MyFormat, Paletted, PuzzleScheme};
 
@@ -1268,7 +1441,7 @@ mod tests {
     #[test]
     fn parse_single() {
         assert_eq!(
-            MyFormat::parse_clues(&String::from("1"), &palette()),
+            MyFormat::parse_clues(&String::from("1"), &palette()).unwrap(),
             vec![Description::new(vec![block(1)])]
         )
     }
@@ -1276,7 +1449,7 @@ mod tests {
     #[test]
     fn parse_two_lines() {
         assert_eq!(
-            MyFormat::parse_clues(&String::from("1\n2"), &palette()),
+            MyFormat::parse_clues(&String::from("1\n2"), &palette()).unwrap(),
             vec![
                 Description::new(vec![block(1)]),
                 Description::new(vec![block(2)])
@@ -1287,7 +1460,7 @@ mod tests {
     #[test]
     fn parse_two_rows_same_line() {
         assert_eq!(
-            MyFormat::parse_clues(&String::from("1, 2"), &palette()),
+            MyFormat::parse_clues(&String::from("1, 2"), &palette()).unwrap(),
             vec![
                 Description::new(vec![block(1)]),
                 Description::new(vec![block(2)])
@@ -1298,7 +1471,7 @@ mod tests {
     #[test]
     fn parse_two_rows_with_commas() {
         assert_eq!(
-            MyFormat::parse_clues(&String::from("1, 2,\n3"), &palette()),
+            MyFormat::parse_clues(&String::from("1, 2,\n3"), &palette()).unwrap(),
             vec![
                 Description::new(vec![block(1)]),
                 Description::new(vec![block(2)]),
@@ -1310,15 +1483,31 @@ mod tests {
     #[test]
     fn parse_two_blocks() {
         assert_eq!(
-            MyFormat::parse_clues(&String::from("1 2"), &palette()),
+            MyFormat::parse_clues(&String::from("1 2"), &palette()).unwrap(),
             vec![Description::new(vec![block(1), block(2)]),]
         )
     }
 
+    #[test]
+    fn parse_blots() {
+        let blotted = Description::with_clues(vec![
+            Clue::Blot(BinaryColor::Black),
+            Clue::Block(block(2)),
+            Clue::Blot(BinaryColor::Black),
+        ]);
+        assert!(blotted.is_blotted());
+        assert_eq!(blotted.vec, vec![block(2)]);
+
+        assert_eq!(
+            MyFormat::parse_clues(&String::from("? 2 ?, 1"), &palette()).unwrap(),
+            vec![blotted, Description::new(vec![block(1)])]
+        )
+    }
+
     #[test]
     fn parse_quotes() {
         assert_eq!(
-            MyFormat::parse_clues(&String::from("'1 2'"), &palette()),
+            MyFormat::parse_clues(&String::from("'1 2'"), &palette()).unwrap(),
             vec![Description::new(vec![block(1), block(2)]),]
         )
     }
@@ -1326,7 +1515,7 @@ mod tests {
     #[test]
     fn parse_double_quotes() {
         assert_eq!(
-            MyFormat::parse_clues(&String::from("1 2\n\"3 4\"\n"), &palette()),
+            MyFormat::parse_clues(&String::from("1 2\n\"3 4\"\n"), &palette()).unwrap(),
             vec![
                 Description::new(vec![block(1), block(2)]),
                 Description::new(vec![block(3), block(4)]),
@@ -1337,7 +1526,7 @@ mod tests {
     #[test]
     fn parse_comment_end_of_line() {
         assert_eq!(
-            MyFormat::parse_clues(&String::from("1 2  # the comment"), &palette()),
+            MyFormat::parse_clues(&String::from("1 2  # the comment"), &palette()).unwrap(),
             vec![Description::new(vec![block(1), block(2)]),]
         )
     }
@@ -1345,7 +1534,7 @@ mod tests {
     #[test]
     fn parse_comment_semicolon() {
         assert_eq!(
-            MyFormat::parse_clues(&String::from("1 2  ; another comment"), &palette()),
+            MyFormat::parse_clues(&String::from("1 2  ; another comment"), &palette()).unwrap(),
             vec![Description::new(vec![block(1), block(2)]),]
         )
     }
@@ -1356,7 +1545,8 @@ mod tests {
             MyFormat::parse_clues(
                 &String::from("1 2 \n # the multi-line \n # comment \n 3, 4"),
                 &palette(),
-            ),
+            )
+            .unwrap(),
             vec![
                 Description::new(vec![block(1), block(2)]),
                 Description::new(vec![block(3)]),
@@ -1444,4 +1634,23 @@ mod tests {
         let colors = vec![("g".to_string(), '%', "0, 204, 0".to_string())];
         assert_eq!(f.get_colors(), colors)
     }
+
+    #[test]
+    fn too_many_colors() {
+        let content = |colors: usize| {
+            let defs: Vec<_> = (0..colors)
+                .map(|i| format!("'c{}=(0, 0, {}) %'", i, i))
+                .collect();
+            format!(
+                "[clues]\nrows = '1'\ncolumns = '1'\n[colors]\ndefs = [{}]\n",
+                defs.join(", ")
+            )
+        };
+
+        // the black color takes one of the ids
+        let max_colors = ColorPalette::MAX_COLORS - 1;
+        assert!(MyFormat::with_content(&content(max_colors)).is_ok());
+        let err = MyFormat::with_content(&content(max_colors + 1)).unwrap_err();
+        assert!(err.0.contains("no more than 127 colors"));
+    }
 }
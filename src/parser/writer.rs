@@ -0,0 +1,495 @@
+use crate::{
+    block::{
+        base::color::{ColorDesc, ColorPalette},
+        Block, Clue, Color,
+    },
+    board::{Board, LineDirection},
+};
+
+use super::{MyFormat, OlsakParser, SimpleParser, WebPbn};
+
+#[derive(Debug)]
+pub struct WriteError(pub String);
+
+/// The counterpart of the `BoardParser`:
+/// serializes the clues and the colors of the board into the parser's format.
+pub trait BoardWriter {
+    fn write<B>(board: &Board<B>) -> Result<String, WriteError>
+    where
+        B: Block;
+}
+
+/// The palette of the colored board or `None` for the black-and-white one.
+fn colored_palette<B>(board: &Board<B>) -> Result<Option<&ColorPalette>, WriteError>
+where
+    B: Block,
+{
+    let is_colored = B::Color::blank().as_color_id().is_some();
+    if !is_colored {
+        return Ok(None);
+    }
+
+    board
+        .palette()
+        .map(Some)
+        .ok_or_else(|| WriteError("Cannot write colored puzzle without palette".to_string()))
+}
+
+/// All the colors of the palette except the white one.
+fn non_white_colors(palette: &ColorPalette) -> Vec<&ColorDesc> {
+    palette
+        .colors()
+        .into_iter()
+        .filter(|color_desc| color_desc.id() != ColorPalette::WHITE_ID)
+        .collect()
+}
+
+fn block_color<B>(block: Clue<B>, palette: &ColorPalette) -> Result<ColorDesc, WriteError>
+where
+    B: Block,
+{
+    block
+        .color()
+        .as_color_id()
+        .and_then(|color_id| palette.desc_by_id(color_id))
+        .ok_or_else(|| WriteError(format!("The color of the block {:?} is not defined", block)))
+}
+
+/// Every line of the clues as the list of the formatted blocks (or blots).
+fn write_clues<B, F>(
+    board: &Board<B>,
+    direction: LineDirection,
+    write_block: F,
+) -> Result<Vec<Vec<String>>, WriteError>
+where
+    B: Block,
+    F: Fn(Clue<B>) -> Result<String, WriteError>,
+{
+    board
+        .descriptions(direction)
+        .iter()
+        .map(|desc| desc.clues().into_iter().map(&write_block).collect())
+        .collect()
+}
+
+impl BoardWriter for MyFormat {
+    fn write<B>(board: &Board<B>) -> Result<String, WriteError>
+    where
+        B: Block,
+    {
+        let palette = colored_palette(board)?;
+        let write_block = |block: Clue<B>| {
+            let size = block.to_string();
+            if let Some(palette) = palette {
+                Ok(size + block_color(block, palette)?.defined_name())
+            } else {
+                Ok(size)
+            }
+        };
+
+        let write_lines = |direction| -> Result<String, WriteError> {
+            let lines = write_clues(board, direction, write_block)?;
+            Ok(lines
+                .into_iter()
+                .map(|line| {
+                    // the empty lines are ignored by the parser
+                    if line.is_empty() {
+                        "0\n".to_string()
+                    } else {
+                        line.join(" ") + "\n"
+                    }
+                })
+                .collect())
+        };
+
+        let mut content = format!(
+            "[clues]\nrows = \"\"\"\n{}\"\"\"\n\ncolumns = \"\"\"\n{}\"\"\"\n",
+            write_lines(LineDirection::Row)?,
+            write_lines(LineDirection::Column)?,
+        );
+
+        if let Some(palette) = palette {
+            let defs: Vec<_> = non_white_colors(palette)
+                .into_iter()
+                .map(|color_desc| {
+                    let def = format!(
+                        "{}=({}) {}",
+                        color_desc.defined_name(),
+                        color_desc.value(),
+                        color_desc.symbol()
+                    );
+                    format!("    {:?},\n", def)
+                })
+                .collect();
+            content += &format!("\n[colors]\ndefs = [\n{}]\n", defs.concat());
+        }
+
+        Ok(content)
+    }
+}
+
+impl WebPbn {
+    fn escape(value: &str) -> String {
+        value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    fn write_color(name: &str, symbol: &str, hex: &str) -> String {
+        format!(
+            "<color name=\"{}\" char=\"{}\">{}</color>\n",
+            Self::escape(name),
+            Self::escape(symbol),
+            hex.trim_start_matches('#'),
+        )
+    }
+}
+
+impl BoardWriter for WebPbn {
+    fn write<B>(board: &Board<B>) -> Result<String, WriteError>
+    where
+        B: Block,
+    {
+        let palette = colored_palette(board)?;
+        let write_block = |block: Clue<B>| {
+            if let Some(palette) = palette {
+                let color = block_color(block, palette)?;
+                Ok(format!(
+                    "<count color=\"{}\">{}</count>",
+                    Self::escape(color.defined_name()),
+                    block
+                ))
+            } else {
+                Ok(format!("<count>{}</count>", block))
+            }
+        };
+
+        let write_lines = |direction, type_| -> Result<String, WriteError> {
+            let lines: Vec<_> = write_clues(board, direction, write_block)?
+                .into_iter()
+                .map(|line| format!("<line>{}</line>\n", line.concat()))
+                .collect();
+            Ok(format!(
+                "<clues type=\"{}\">\n{}</clues>\n",
+                type_,
+                lines.concat()
+            ))
+        };
+
+        let colors = if let Some(palette) = palette {
+            let white = palette
+                .desc_by_id(ColorPalette::WHITE_ID)
+                .ok_or_else(|| WriteError("The white color is not defined".to_string()))?;
+            let mut colors = vec![Self::write_color(
+                "white",
+                &white.symbol(),
+                &white.value().to_hex(),
+            )];
+            colors.extend(non_white_colors(palette).into_iter().map(|color_desc| {
+                Self::write_color(
+                    color_desc.defined_name(),
+                    &color_desc.symbol(),
+                    &color_desc.value().to_hex(),
+                )
+            }));
+            colors.concat()
+        } else {
+            Self::write_color("white", ".", "FFFFFF") + &Self::write_color("black", "X", "000000")
+        };
+
+        Ok(format!(
+            "<?xml version=\"1.0\"?>\n\
+             <!DOCTYPE pbn SYSTEM \"https://webpbn.com/pbn-0.3.dtd\">\n\
+             <puzzleset>\n\
+             <puzzle type=\"grid\" defaultcolor=\"black\">\n\
+             {}{}{}</puzzle>\n\
+             </puzzleset>\n",
+            colors,
+            write_lines(LineDirection::Column, "columns")?,
+            write_lines(LineDirection::Row, "rows")?,
+        ))
+    }
+}
+
+impl OlsakParser {
+    /// The non-numeric name of the color used as the blocks' suffix: 'a', 'b', ..., 'z', 'ba', ...
+    fn color_key(index: usize) -> String {
+        let letter = |i| char::from(b'a' + (i % 26) as u8);
+
+        let mut key = vec![letter(index)];
+        let mut rest = index / 26;
+        while rest > 0 {
+            key.push(letter(rest));
+            rest /= 26;
+        }
+        key.into_iter().rev().collect()
+    }
+}
+
+impl BoardWriter for OlsakParser {
+    fn write<B>(board: &Board<B>) -> Result<String, WriteError>
+    where
+        B: Block,
+    {
+        let palette = colored_palette(board)?;
+
+        let mut content = "#d\n".to_string();
+        let mut keys = vec![];
+        if let Some(palette) = palette {
+            if let Some(white) = palette.desc_by_id(ColorPalette::WHITE_ID) {
+                content += &format!(
+                    "   0:{}   {}   white\n",
+                    white.symbol(),
+                    white.value().to_hex()
+                );
+            }
+
+            for (i, color_desc) in non_white_colors(palette).into_iter().enumerate() {
+                let key = Self::color_key(i);
+                content += &format!(
+                    "   {}:{}   {}   {}\n",
+                    key,
+                    color_desc.symbol(),
+                    color_desc.value().to_hex(),
+                    color_desc.defined_name(),
+                );
+                keys.push((color_desc.id(), key));
+            }
+        } else {
+            content += "   0:   #FFFFFF   white\n   1:X  #000000   black\n";
+        }
+
+        let write_block = |block: Clue<B>| {
+            let size = block.to_string();
+            if let Some(palette) = palette {
+                let color_id = block_color(block, palette)?.id();
+                let key = keys
+                    .iter()
+                    .find_map(|(id, key)| if *id == color_id { Some(key) } else { None })
+                    .expect("Every color should have a key");
+                Ok(size + key)
+            } else {
+                Ok(size)
+            }
+        };
+
+        for (header, direction) in &[
+            (": rows", LineDirection::Row),
+            (": columns", LineDirection::Column),
+        ] {
+            content += header;
+            content += "\n";
+            for line in write_clues(board, *direction, write_block)? {
+                content += &line.join(" ");
+                content += "\n";
+            }
+        }
+
+        Ok(content)
+    }
+}
+
+impl BoardWriter for SimpleParser {
+    fn write<B>(board: &Board<B>) -> Result<String, WriteError>
+    where
+        B: Block,
+    {
+        if colored_palette(board)?.is_some() {
+            return Err(WriteError(
+                "The simple format supports only black-and-white puzzles".to_string(),
+            ));
+        }
+
+        let write_block = |block: Clue<B>| Ok(block.to_string());
+
+        let mut content = String::new();
+        for (header, direction) in &[
+            ("rows", LineDirection::Row),
+            ("columns", LineDirection::Column),
+        ] {
+            content += header;
+            content += "\n";
+            for line in write_clues(board, *direction, write_block)? {
+                // the empty lines are ignored by the parser
+                if line.is_empty() {
+                    content += "0";
+                } else {
+                    content += &line.join(" ");
+                }
+                content += "\n";
+            }
+        }
+
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block::{binary::BinaryBlock, multicolor::ColoredBlock, Block},
+        board::{Board, LineDirection},
+        parser::{BoardParser, DetectedParser},
+    };
+
+    use super::{BoardWriter, OlsakParser, SimpleParser};
+
+    /// parse -> write -> parse yields the same clues
+    fn round_trip<P, B>(content: &str) -> String
+    where
+        P: BoardParser + BoardWriter,
+        B: Block,
+    {
+        let board: Board<B> = P::with_content(content).unwrap().parse().unwrap();
+        let written = P::write(&board).unwrap();
+
+        let parser = DetectedParser::with_content(&written).unwrap();
+        let parsed: Board<B> = parser.parse().unwrap();
+        for &direction in &[LineDirection::Row, LineDirection::Column] {
+            assert_eq!(
+                board.descriptions(direction),
+                parsed.descriptions(direction),
+                "{}",
+                written
+            );
+        }
+        written
+    }
+
+    const OLSAK_COLORED: &str = "
+#d
+   0:   #FFFFFF   white
+   a:%  #FF0000   red
+   b:*  #0000FF   blue
+: rows
+1a 1b
+2a
+: columns
+2a
+
+1b
+";
+
+    #[test]
+    fn color_keys() {
+        let keys: Vec<_> = [0, 1, 25, 26, 27, 52]
+            .iter()
+            .map(|&i| OlsakParser::color_key(i))
+            .collect();
+        assert_eq!(keys, ["a", "b", "z", "ba", "bb", "ca"]);
+    }
+
+    #[test]
+    fn olsak_black_and_white() {
+        let content = "#d\n   0:   #FFFFFF   white\n   1:X  #000000   black\n\
+                       : rows\n1 1\n3\n\n: columns\n2\n1\n1 1\n";
+        let written = round_trip::<OlsakParser, BinaryBlock>(content);
+        assert_eq!(written, content);
+    }
+
+    #[test]
+    fn olsak_colored() {
+        let _ = round_trip::<OlsakParser, ColoredBlock>(OLSAK_COLORED);
+    }
+
+    #[test]
+    fn simple() {
+        let content = "rows\n1 1\n3\n0\ncolumns\n2\n1\n1 1\n";
+        let written = round_trip::<SimpleParser, BinaryBlock>(content);
+        assert_eq!(written, content);
+    }
+
+    #[test]
+    fn simple_blotted() {
+        let content = "rows\n1 ?\n?\n0\ncolumns\n?\n1\n? 1\n";
+        let written = round_trip::<SimpleParser, BinaryBlock>(content);
+        assert_eq!(written, content);
+    }
+
+    #[test]
+    fn olsak_colored_blotted() {
+        let content = OLSAK_COLORED.replace("2a\n:", "?a\n:");
+        let board = OlsakParser::with_content(&content)
+            .unwrap()
+            .parse::<ColoredBlock>()
+            .unwrap();
+        assert!(board.is_blotted());
+        let written = round_trip::<OlsakParser, ColoredBlock>(&content);
+        // the keys get reassigned on writing
+        assert!(written.contains("\n?c\n"), "{}", written);
+    }
+
+    #[test]
+    fn simple_does_not_support_colors() {
+        let board: Board<ColoredBlock> = OlsakParser::with_content(OLSAK_COLORED)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(SimpleParser::write(&board).is_err());
+    }
+
+    #[cfg(feature = "ini")]
+    #[test]
+    fn toml_black_and_white() {
+        let content =
+            "[clues]\nrows = \"\"\"\n1 1\n3\n0\n\"\"\"\n\ncolumns = \"\"\"\n2\n1\n1 1\n\"\"\"\n";
+        let written = round_trip::<super::MyFormat, BinaryBlock>(content);
+        assert_eq!(written, content);
+    }
+
+    #[cfg(feature = "ini")]
+    #[test]
+    fn toml_colored() {
+        let content = r#"
+        [clues]
+        rows = '''
+        1r 1b
+        2r
+        '''
+        columns = '2r, 0, 1b'
+
+        [colors]
+        defs = ['r=(255, 0, 0) %', 'b=(blue) *', 'g=(#0F0) +']
+        "#;
+        let written = round_trip::<super::MyFormat, ColoredBlock>(content);
+        assert!(written.contains("\"b=(blue) *\""));
+        assert!(written.contains("\"r=(255, 0, 0) %\""));
+        assert!(written.contains("\"g=(#0F0) +\""));
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn webpbn_black_and_white() {
+        let content = r#"<?xml version="1.0"?>
+        <puzzleset><puzzle type="grid" defaultcolor="black">
+        <clues type="columns"><line><count>2</count></line><line><count>1</count></line></clues>
+        <clues type="rows"><line><count>1</count></line><line><count>2</count></line></clues>
+        </puzzle></puzzleset>"#;
+        let _ = round_trip::<super::WebPbn, BinaryBlock>(content);
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn webpbn_blotted() {
+        let content = r#"<?xml version="1.0"?>
+        <puzzleset><puzzle type="grid" defaultcolor="black">
+        <clues type="columns"><line><count>?</count></line><line><count>1</count></line></clues>
+        <clues type="rows"><line><count>1</count></line><line><count>?</count></line></clues>
+        </puzzle></puzzleset>"#;
+        let written = round_trip::<super::WebPbn, BinaryBlock>(content);
+        assert!(written.contains("<count>?</count>"));
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn webpbn_colored() {
+        let board: Board<ColoredBlock> = OlsakParser::with_content(OLSAK_COLORED)
+            .unwrap()
+            .parse()
+            .unwrap();
+        let xml = super::WebPbn::write(&board).unwrap();
+        let _ = round_trip::<super::WebPbn, ColoredBlock>(&xml);
+    }
+}
@@ -5,8 +5,8 @@ use colored::{self, ColoredString, Colorize};
 use hashbrown::HashMap;
 
 use crate::{
-    block::{base::color::ColorDesc, binary::BinaryColor, Block, Color, Description},
-    board::{Board, LineDirection},
+    block::{base::color::ColorDesc, binary::BinaryColor, Block, Clue, Color, Description},
+    board::{Board, LineDirection, LinePosition},
     utils::{
         pad, pad_with,
         rc::{MutRc, ReadRc, ReadRef},
@@ -14,6 +14,14 @@ use crate::{
     },
 };
 
+pub use self::raster::{RasterFormat, RasterRenderer};
+
+mod raster;
+
+/// The terminal escape sequences to swap the foreground and the background colors.
+const REVERSE_VIDEO: &str = "\x1b[7m";
+const RESET_VIDEO: &str = "\x1b[0m";
+
 pub trait Renderer<B>
 where
     B: Block,
@@ -46,12 +54,38 @@ where
     }
 
     fn render(&self) -> String {
-        let full_width = self.side_width() + self.board().width();
+        self.render_with_highlight(None)
+    }
+
+    fn render_simple(&self) -> String {
+        Self::concat(
+            self.grid_lines()
+                .into_iter()
+                .map(|row| row.iter().map(ToString::to_string).collect()),
+        )
+    }
+}
+
+impl<B> ShellRenderer<B>
+where
+    B: Block + Display,
+    B::Color: Display,
+{
+    /// Render the whole board as the `render` does
+    /// with the clues of the given line highlighted in reverse video.
+    pub fn render_highlighted(&self, position: LinePosition) -> String {
+        self.render_with_highlight(Some(position))
+    }
+
+    fn render_with_highlight(&self, highlight: Option<LinePosition>) -> String {
+        let side_width = self.side_width();
+        let full_width = side_width + self.board().width();
 
         let mut header = self.header_lines();
         for row in &mut header {
             pad_with(row, "#".to_string(), full_width, false);
         }
+        let header_height = header.len();
 
         let header = header.into_iter().map(|row| {
             row.into_iter()
@@ -67,17 +101,27 @@ where
         let grid = self.grid_lines();
         let grid = side.zip(grid).map(|(s, g)| s.chain(g).collect());
 
-        Self::concat(header.chain(grid).map(|line: Vec<ColoredString>| {
-            line.iter().map(|symbol| pad(symbol, 2, true)).collect()
-        }))
-    }
+        let is_highlighted = |x: usize, y: usize| match highlight {
+            Some(LinePosition::Row(index)) => x < side_width && y == header_height + index,
+            Some(LinePosition::Column(index)) => y < header_height && x == side_width + index,
+            None => false,
+        };
 
-    fn render_simple(&self) -> String {
-        Self::concat(
-            self.grid_lines()
-                .into_iter()
-                .map(|row| row.iter().map(ToString::to_string).collect()),
-        )
+        Self::concat(header.chain(grid).enumerate().map(
+            |(y, line): (usize, Vec<ColoredString>)| {
+                line.iter()
+                    .enumerate()
+                    .map(|(x, symbol)| {
+                        let symbol = pad(symbol, 2, true);
+                        if is_highlighted(x, y) {
+                            format!("{}{}{}", REVERSE_VIDEO, symbol, RESET_VIDEO)
+                        } else {
+                            symbol
+                        }
+                    })
+                    .collect()
+            },
+        ))
     }
 }
 
@@ -90,16 +134,16 @@ where
     }
 
     fn side_width(&self) -> usize {
-        Self::descriptions_width(self.board().descriptions(LineDirection::Row))
+        descriptions_width(self.board().descriptions(LineDirection::Row))
     }
+}
 
-    fn descriptions_width(descriptions: &[ReadRc<Description<B>>]) -> usize {
-        descriptions
-            .iter()
-            .map(|desc| desc.vec.len())
-            .max()
-            .unwrap_or(0)
-    }
+fn descriptions_width<B: Block>(descriptions: &[ReadRc<Description<B>>]) -> usize {
+    descriptions
+        .iter()
+        .map(|desc| desc.clues().len())
+        .max()
+        .unwrap_or(0)
 }
 
 impl<B> ShellRenderer<B>
@@ -107,13 +151,13 @@ where
     B: Block + Display,
 {
     fn desc_to_string(desc: &ReadRc<Description<B>>) -> Vec<String> {
-        desc.vec.iter().map(ToString::to_string).collect()
+        desc.clues().iter().map(ToString::to_string).collect()
     }
 
     fn descriptions_to_matrix(descriptions: &[ReadRc<Description<B>>]) -> Vec<Vec<String>> {
         let mut rows: Vec<_> = descriptions.iter().map(Self::desc_to_string).collect();
 
-        let width = Self::descriptions_width(descriptions);
+        let width = descriptions_width(descriptions);
 
         for row in &mut rows {
             pad_with(row, " ".to_string(), width, false);
@@ -196,3 +240,424 @@ impl Display for BinaryColor {
         write!(f, "{}", symbol)
     }
 }
+
+type Rgb = (u8, u8, u8);
+
+const BLACK: Rgb = (0, 0, 0);
+const WHITE: Rgb = (0xFF, 0xFF, 0xFF);
+
+/// The real color of the solved cell: the palette's value for the colored puzzles,
+/// the plain black and white for the black-and-white ones.
+fn solved_cell_rgb<B>(board: &Board<B>, cell: B::Color) -> Rgb
+where
+    B: Block,
+{
+    cell.as_color_id()
+        .and_then(|color_id| board.desc_by_id(color_id))
+        .map_or_else(
+            || {
+                if cell == B::Color::blank() {
+                    WHITE
+                } else {
+                    BLACK
+                }
+            },
+            |desc| desc.rgb_value(),
+        )
+}
+
+/// The perceived brightness of the color in the range [0, 255].
+fn brightness(rgb: Rgb) -> u32 {
+    let (r, g, b) = rgb;
+    (299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b)) / 1000
+}
+
+/// Draws the board as an SVG image: the clues of the columns on the top,
+/// the clues of the rows on the left and the cells painted with their real colors.
+///
+/// The unsolved cells are left transparent, so the partially solved board can be drawn too.
+#[derive(Debug)]
+pub struct SvgRenderer<B>
+where
+    B: Block,
+{
+    board: MutRc<Board<B>>,
+}
+
+impl<B> Renderer<B> for SvgRenderer<B>
+where
+    B: Block,
+{
+    fn with_board(board: MutRc<Board<B>>) -> Self {
+        Self { board }
+    }
+
+    fn render(&self) -> String {
+        self.image(true)
+    }
+
+    /// The grid only, without the clues.
+    fn render_simple(&self) -> String {
+        self.image(false)
+    }
+}
+
+impl<B> SvgRenderer<B>
+where
+    B: Block,
+{
+    const CELL_SIZE: usize = 20;
+    const PADDING: usize = 2;
+    const BOLD_LINE_EVERY: usize = 5;
+
+    const GRID_COLOR: Rgb = (0x99, 0x99, 0x99);
+
+    fn board(&self) -> ReadRef<'_, Board<B>> {
+        self.board.read()
+    }
+
+    fn image(&self, with_clues: bool) -> String {
+        let (side_width, header_height) = if with_clues {
+            let board = self.board();
+            (
+                descriptions_width(board.descriptions(LineDirection::Row)),
+                descriptions_width(board.descriptions(LineDirection::Column)),
+            )
+        } else {
+            (0, 0)
+        };
+
+        let mut elements = vec![];
+        if with_clues {
+            elements.extend(self.header_clues(side_width, header_height));
+            elements.extend(self.side_clues(side_width, header_height));
+        }
+        elements.extend(self.cells(side_width, header_height));
+        elements.extend(self.grid_lines(side_width, header_height));
+
+        let (width, height) = {
+            let board = self.board();
+            (
+                Self::coordinate(side_width + board.width()) + Self::PADDING,
+                Self::coordinate(header_height + board.height()) + Self::PADDING,
+            )
+        };
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" \
+             width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n{}\n</svg>",
+            elements.join("\n"),
+            w = width,
+            h = height,
+        )
+    }
+
+    /// The pixel offset of the cell with given index.
+    fn coordinate(index: usize) -> usize {
+        Self::PADDING + index * Self::CELL_SIZE
+    }
+
+    fn color_desc(&self, color: B::Color) -> Option<ColorDesc> {
+        color
+            .as_color_id()
+            .and_then(|color_id| self.board().desc_by_id(color_id))
+    }
+
+    fn cell_rgb(&self, cell: B::Color) -> Option<Rgb> {
+        if cell.is_solved() {
+            Some(solved_cell_rgb(&self.board(), cell))
+        } else {
+            None
+        }
+    }
+
+    fn header_clues(&self, side_width: usize, header_height: usize) -> Vec<String> {
+        let board = self.board();
+        board
+            .descriptions(LineDirection::Column)
+            .iter()
+            .enumerate()
+            .flat_map(|(x, desc)| {
+                let shift = header_height - desc.clues().len();
+                desc.clues()
+                    .into_iter()
+                    .enumerate()
+                    .flat_map(|(i, clue)| self.clue(clue, side_width + x, shift + i))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn side_clues(&self, side_width: usize, header_height: usize) -> Vec<String> {
+        let board = self.board();
+        board
+            .descriptions(LineDirection::Row)
+            .iter()
+            .enumerate()
+            .flat_map(|(y, desc)| {
+                let shift = side_width - desc.clues().len();
+                desc.clues()
+                    .into_iter()
+                    .enumerate()
+                    .flat_map(|(i, clue)| self.clue(clue, shift + i, header_height + y))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// The colored blocks are drawn as the colored squares with the contrast text,
+    /// the black-and-white ones as the plain text.
+    fn clue(&self, clue: Clue<B>, x: usize, y: usize) -> Vec<String> {
+        let (x, y) = (Self::coordinate(x), Self::coordinate(y));
+        if let Some(desc) = self.color_desc(clue.color()) {
+            let rgb = desc.rgb_value();
+            vec![
+                Self::rect(x, y, rgb),
+                Self::text(x, y, clue, Self::contrast_color(rgb)),
+            ]
+        } else {
+            vec![Self::text(x, y, clue, BLACK)]
+        }
+    }
+
+    fn cells(&self, side_width: usize, header_height: usize) -> Vec<String> {
+        let board = self.board();
+        let mut color_cache = HashMap::new();
+
+        let mut elements = vec![];
+        for (y, row) in board.iter_rows().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                let rgb = *color_cache
+                    .entry(cell)
+                    .or_insert_with(|| self.cell_rgb(cell));
+
+                if let Some(rgb) = rgb {
+                    let x = Self::coordinate(side_width + x);
+                    let y = Self::coordinate(header_height + y);
+                    elements.push(Self::rect(x, y, rgb));
+
+                    if cell == B::Color::blank() {
+                        elements.push(Self::dot(x, y));
+                    }
+                }
+            }
+        }
+
+        elements
+    }
+
+    /// Thin lines between every cell and the bold ones every few cells.
+    /// The lines continue over the clues to simplify finding the line's clues.
+    fn grid_lines(&self, side_width: usize, header_height: usize) -> Vec<String> {
+        let (width, height) = {
+            let board = self.board();
+            (board.width(), board.height())
+        };
+
+        let is_bold =
+            |index: usize, size: usize| index % Self::BOLD_LINE_EVERY == 0 || index == size;
+
+        let (left, right) = (Self::coordinate(0), Self::coordinate(side_width + width));
+        let (top, bottom) = (
+            Self::coordinate(0),
+            Self::coordinate(header_height + height),
+        );
+
+        let vertical = (0..=width).map(|i| {
+            let x = Self::coordinate(side_width + i);
+            ((x, top), (x, bottom), is_bold(i, width))
+        });
+        let horizontal = (0..=height).map(|i| {
+            let y = Self::coordinate(header_height + i);
+            ((left, y), (right, y), is_bold(i, height))
+        });
+
+        let (bold, thin): (Vec<_>, Vec<_>) =
+            vertical.chain(horizontal).partition(|&(_, _, bold)| bold);
+        // the bold lines should be drawn over the thin ones
+        thin.into_iter()
+            .chain(bold)
+            .map(|(from, to, bold)| Self::line(from, to, bold))
+            .collect()
+    }
+
+    fn rect(x: usize, y: usize, rgb: Rgb) -> String {
+        format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{size}\" height=\"{size}\" fill=\"{}\"/>",
+            x,
+            y,
+            Self::hex(rgb),
+            size = Self::CELL_SIZE,
+        )
+    }
+
+    /// Marks the cell as solved to the blank color.
+    fn dot(x: usize, y: usize) -> String {
+        let half = Self::CELL_SIZE / 2;
+        format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>",
+            x + half,
+            y + half,
+            Self::CELL_SIZE / 10,
+            Self::hex(Self::GRID_COLOR),
+        )
+    }
+
+    fn text(x: usize, y: usize, value: Clue<B>, rgb: Rgb) -> String {
+        let half = Self::CELL_SIZE / 2;
+        format!(
+            "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"{}\" \
+             text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"{}\">{}</text>",
+            x + half,
+            y + half,
+            Self::CELL_SIZE * 3 / 5,
+            Self::hex(rgb),
+            value,
+        )
+    }
+
+    fn line(from: (usize, usize), to: (usize, usize), bold: bool) -> String {
+        let (rgb, width) = if bold {
+            (BLACK, 2)
+        } else {
+            (Self::GRID_COLOR, 1)
+        };
+
+        format!(
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"{}\"/>",
+            from.0,
+            from.1,
+            to.0,
+            to.1,
+            Self::hex(rgb),
+            width,
+        )
+    }
+
+    fn hex(rgb: Rgb) -> String {
+        let (r, g, b) = rgb;
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+
+    /// Black text for the light background and white text for the dark one.
+    fn contrast_color(rgb: Rgb) -> Rgb {
+        if brightness(rgb) < 128 {
+            WHITE
+        } else {
+            BLACK
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block::{
+            base::color::{ColorPalette, ColorValue},
+            binary::{BinaryBlock, BinaryColor},
+            multicolor::{ColoredBlock, MultiColor},
+            Color, Description,
+        },
+        board::{Board, LinePosition},
+        utils::rc::MutRc,
+    };
+
+    use super::{Renderer, ShellRenderer, SvgRenderer};
+
+    fn u_letter() -> MutRc<Board<BinaryBlock>> {
+        let rows = vec![
+            Description::new(vec![BinaryBlock(1), BinaryBlock(1)]),
+            Description::new(vec![BinaryBlock(1), BinaryBlock(1)]),
+            Description::new(vec![BinaryBlock(3)]),
+        ];
+        let columns = vec![
+            Description::new(vec![BinaryBlock(3)]),
+            Description::new(vec![BinaryBlock(1)]),
+            Description::new(vec![BinaryBlock(3)]),
+        ];
+
+        MutRc::new(Board::with_descriptions_and_palette(rows, columns, None))
+    }
+
+    #[test]
+    fn shell_highlighted() {
+        let r = ShellRenderer::with_board(u_letter());
+        let plain = r.render();
+        assert!(!plain.contains(super::REVERSE_VIDEO));
+
+        let row = r.render_highlighted(LinePosition::Row(2));
+        assert_eq!(row.lines().count(), plain.lines().count());
+        assert_eq!(row.matches(super::REVERSE_VIDEO).count(), 2);
+        assert!(row.ends_with("\x1b[7m  \x1b[0m\x1b[7m3 \x1b[0m? ? ? "));
+
+        let column = r.render_highlighted(LinePosition::Column(1));
+        assert_eq!(column.matches(super::REVERSE_VIDEO).count(), 1);
+        assert!(column.starts_with("# # 3 \x1b[7m1 \x1b[0m3 \n"));
+    }
+
+    #[test]
+    fn svg_unsolved() {
+        let r = SvgRenderer::with_board(u_letter());
+        let svg = r.render();
+
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>"));
+        // side clues are 2 cells wide, header clues are 1 cell high
+        assert!(svg.contains("width=\"104\" height=\"84\""));
+        assert_eq!(svg.matches("<rect ").count(), 0);
+        assert_eq!(svg.matches("<text ").count(), 8);
+        assert_eq!(svg.matches("<line ").count(), 8);
+    }
+
+    #[test]
+    fn svg_solved() {
+        let board = u_letter();
+        let (b, w) = (BinaryColor::Black, BinaryColor::White);
+        Board::restore_with_callback(&board, vec![b, w, b, b, w, b, b, b, b]);
+
+        let svg = SvgRenderer::with_board(board).render_simple();
+        assert!(svg.contains("width=\"64\" height=\"64\""));
+        assert_eq!(svg.matches("<text ").count(), 0);
+        assert_eq!(svg.matches("fill=\"#000000\"").count(), 7);
+        assert_eq!(svg.matches("fill=\"#ffffff\"").count(), 2);
+        assert_eq!(svg.matches("<circle ").count(), 2);
+    }
+
+    #[test]
+    fn svg_colored() {
+        let mut palette = ColorPalette::with_white("W");
+        palette
+            .color_with_name_and_value("red", ColorValue::parse("f00"))
+            .unwrap();
+        palette
+            .color_with_name_and_value("navy", ColorValue::parse("navy"))
+            .unwrap();
+        let color = |name| MultiColor::from_color_ids(&[palette.id_by_name(name).unwrap()]);
+        let (red, navy) = (color("red"), color("navy"));
+
+        // R N
+        let rows = vec![Description::new(vec![
+            ColoredBlock::from_size_and_color(1, red.0),
+            ColoredBlock::from_size_and_color(1, navy.0),
+        ])];
+        let columns = vec![
+            Description::new(vec![ColoredBlock::from_size_and_color(1, red.0)]),
+            Description::new(vec![ColoredBlock::from_size_and_color(1, navy.0)]),
+        ];
+
+        let board = MutRc::new(Board::with_descriptions_and_palette(
+            rows,
+            columns,
+            Some(palette),
+        ));
+        Board::restore_with_callback(&board, vec![red, navy]);
+
+        let svg = SvgRenderer::with_board(board).render();
+        // the clues and the cells
+        assert_eq!(svg.matches("<rect ").count(), 6);
+        assert_eq!(svg.matches("fill=\"#ff0000\"").count(), 3);
+        assert_eq!(svg.matches("fill=\"#000080\"").count(), 3);
+        // white text on the dark clues
+        assert_eq!(svg.matches("fill=\"#ffffff\">1</text>").count(), 4);
+    }
+}
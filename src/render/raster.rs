@@ -0,0 +1,401 @@
+use std::{
+    convert::TryFrom,
+    fmt,
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use hashbrown::HashMap;
+
+use crate::{
+    block::{Block, Color},
+    board::Board,
+    utils::rc::{MutRc, ReadRef},
+};
+
+use super::{brightness, solved_cell_rgb, Rgb, WHITE};
+
+/// The bitmap formats supported by the `RasterRenderer`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RasterFormat {
+    Png,
+    Ppm,
+    Pbm,
+}
+
+impl RasterFormat {
+    const NAMES: [(Self, &'static str); 3] =
+        [(Self::Png, "png"), (Self::Ppm, "ppm"), (Self::Pbm, "pbm")];
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find_map(|&(format, name)| if format == self { Some(name) } else { None })
+            .expect("Every format should have a name")
+    }
+
+    /// Whether the format was compiled in.
+    pub fn is_available(self) -> bool {
+        match self {
+            Self::Png => cfg!(feature = "raster"),
+            Self::Ppm | Self::Pbm => true,
+        }
+    }
+
+    /// The names of all the available formats.
+    pub fn variants() -> impl Iterator<Item = &'static str> {
+        Self::NAMES.iter().filter_map(|&(format, name)| {
+            if format.is_available() {
+                Some(name)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Detect the format by the extension of the file.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .ok_or_else(|| format!("No extension in {:?}", path.as_ref()))?;
+
+        extension.parse()
+    }
+}
+
+impl fmt::Display for RasterFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for RasterFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let format = Self::NAMES
+            .iter()
+            .find_map(|&(format, name)| if name == s { Some(format) } else { None })
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::variants().collect();
+                format!("Unknown image format {:?}: expected one of {:?}", s, names)
+            })?;
+
+        if !format.is_available() {
+            return Err(format!(
+                "The {} format is not available (hint: add --features=raster)",
+                format
+            ));
+        }
+        Ok(format)
+    }
+}
+
+/// Draws the cells of the board (without the clues) as a bitmap
+/// where every cell is a square of `cell_size` pixels.
+///
+/// The unsolved cells are painted with the blend of all their possible colors.
+#[derive(Debug)]
+pub struct RasterRenderer<B>
+where
+    B: Block,
+{
+    board: MutRc<Board<B>>,
+    cell_size: usize,
+}
+
+impl<B> RasterRenderer<B>
+where
+    B: Block,
+{
+    pub const DEFAULT_CELL_SIZE: usize = 10;
+
+    pub fn with_board(board: MutRc<Board<B>>) -> Self {
+        Self::with_board_and_cell_size(board, Self::DEFAULT_CELL_SIZE)
+    }
+
+    pub fn with_board_and_cell_size(board: MutRc<Board<B>>, cell_size: usize) -> Self {
+        assert!(cell_size > 0, "The cell should be at least one pixel");
+        Self { board, cell_size }
+    }
+
+    fn board(&self) -> ReadRef<'_, Board<B>> {
+        self.board.read()
+    }
+
+    /// The width and the height of the image in pixels.
+    pub fn image_size(&self) -> (usize, usize) {
+        let board = self.board();
+        (
+            board.width() * self.cell_size,
+            board.height() * self.cell_size,
+        )
+    }
+
+    pub fn render(&self, format: RasterFormat) -> io::Result<Vec<u8>> {
+        let mut image = vec![];
+        self.write(format, &mut image)?;
+        Ok(image)
+    }
+
+    pub fn write<W: Write>(&self, format: RasterFormat, writer: W) -> io::Result<()> {
+        match format {
+            RasterFormat::Png => self.write_png(writer),
+            RasterFormat::Ppm => self.write_ppm(writer),
+            RasterFormat::Pbm => self.write_pbm(writer),
+        }
+    }
+
+    fn cell_rgb(&self, cell: B::Color) -> Rgb {
+        let board = self.board();
+        if cell.is_solved() {
+            return solved_cell_rgb(&board, cell);
+        }
+
+        let variants: Vec<_> = cell
+            .variants()
+            .into_iter()
+            .map(|color| solved_cell_rgb(&board, color))
+            .collect();
+        blend(&variants)
+    }
+
+    /// The rows of pixels from top to bottom.
+    fn pixel_rows(&self) -> Vec<Vec<Rgb>> {
+        let mut color_cache = HashMap::new();
+
+        let rows: Vec<Vec<Rgb>> = self
+            .board()
+            .iter_rows()
+            .map(|row| {
+                row.iter()
+                    .flat_map(|&cell| {
+                        let rgb = *color_cache
+                            .entry(cell)
+                            .or_insert_with(|| self.cell_rgb(cell));
+                        vec![rgb; self.cell_size]
+                    })
+                    .collect()
+            })
+            .collect();
+
+        rows.into_iter()
+            .flat_map(|row| vec![row; self.cell_size])
+            .collect()
+    }
+
+    /// Binary Portable PixMap.
+    fn write_ppm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let (width, height) = self.image_size();
+        write!(writer, "P6\n{} {}\n255\n", width, height)?;
+
+        for row in self.pixel_rows() {
+            let bytes: Vec<_> = row
+                .into_iter()
+                .flat_map(|(r, g, b)| vec![r, g, b])
+                .collect();
+            writer.write_all(&bytes)?;
+        }
+        writer.flush()
+    }
+
+    /// Binary Portable BitMap: every pixel darker than the middle gray is black.
+    fn write_pbm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let (width, height) = self.image_size();
+        write!(writer, "P4\n{} {}\n", width, height)?;
+
+        for row in self.pixel_rows() {
+            // every row is padded to the whole number of bytes
+            let bytes: Vec<u8> = row
+                .chunks(8)
+                .map(|chunk| {
+                    chunk.iter().enumerate().fold(0, |byte, (i, &rgb)| {
+                        if brightness(rgb) < 128 {
+                            byte | (0x80 >> i)
+                        } else {
+                            byte
+                        }
+                    })
+                })
+                .collect();
+            writer.write_all(&bytes)?;
+        }
+        writer.flush()
+    }
+
+    #[cfg(feature = "raster")]
+    fn write_png<W: Write>(&self, writer: W) -> io::Result<()> {
+        let (width, height) = self.image_size();
+        let too_large = |_| io::Error::new(io::ErrorKind::InvalidInput, "The image is too large");
+        let (width, height) = (
+            u32::try_from(width).map_err(too_large)?,
+            u32::try_from(height).map_err(too_large)?,
+        );
+
+        let mut encoder = png::Encoder::new(writer, width, height);
+        encoder.set_color(png::ColorType::RGB);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let data: Vec<_> = self
+            .pixel_rows()
+            .into_iter()
+            .flatten()
+            .flat_map(|(r, g, b)| vec![r, g, b])
+            .collect();
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "raster"))]
+    #[allow(clippy::unused_self)]
+    fn write_png<W: Write>(&self, _writer: W) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "Cannot write PNG: no support for PNG (hint: add --features=raster)",
+        ))
+    }
+}
+
+/// The average of the colors.
+fn blend(colors: &[Rgb]) -> Rgb {
+    if colors.is_empty() {
+        return WHITE;
+    }
+
+    let n = colors.len();
+    let average = |component: fn(&Rgb) -> u8| {
+        let sum: usize = colors.iter().map(|rgb| usize::from(component(rgb))).sum();
+        // round to the nearest
+        u8::try_from((sum + n / 2) / n).expect("The average of bytes should fit into a byte")
+    };
+
+    (
+        average(|rgb| rgb.0),
+        average(|rgb| rgb.1),
+        average(|rgb| rgb.2),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block::{
+            base::color::{ColorPalette, ColorValue},
+            binary::{BinaryBlock, BinaryColor},
+            multicolor::{ColoredBlock, MultiColor},
+            Color, Description,
+        },
+        board::Board,
+        utils::rc::MutRc,
+    };
+
+    use super::{blend, RasterFormat, RasterRenderer};
+
+    fn single_row_renderer(
+        cells: Vec<BinaryColor>,
+        cell_size: usize,
+    ) -> RasterRenderer<BinaryBlock> {
+        let rows = vec![Description::new(vec![])];
+        let columns = cells.iter().map(|_| Description::new(vec![])).collect();
+        let board = MutRc::new(Board::with_descriptions_and_palette(rows, columns, None));
+        Board::restore_with_callback(&board, cells);
+        RasterRenderer::with_board_and_cell_size(board, cell_size)
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(RasterFormat::from_path("a/b.ppm"), Ok(RasterFormat::Ppm));
+        assert_eq!(RasterFormat::from_path("b.PBM"), Ok(RasterFormat::Pbm));
+        #[cfg(feature = "raster")]
+        assert_eq!(RasterFormat::from_path("b.png"), Ok(RasterFormat::Png));
+        #[cfg(not(feature = "raster"))]
+        assert!(RasterFormat::from_path("b.png").is_err());
+        assert!(RasterFormat::from_path("b.jpg").is_err());
+        assert!(RasterFormat::from_path("ppm").is_err());
+    }
+
+    #[test]
+    fn blend_colors() {
+        assert_eq!(blend(&[(0, 0, 0), (255, 255, 255)]), (128, 128, 128));
+        assert_eq!(blend(&[(255, 0, 0), (0, 0, 255), (0, 0, 0)]), (85, 0, 85));
+        assert_eq!(blend(&[(1, 2, 3)]), (1, 2, 3));
+    }
+
+    #[test]
+    fn ppm() {
+        let (b, w, u) = (
+            BinaryColor::Black,
+            BinaryColor::White,
+            BinaryColor::Undefined,
+        );
+        let r = single_row_renderer(vec![b, w, u], 2);
+        assert_eq!(r.image_size(), (6, 2));
+
+        let image = r.render(RasterFormat::Ppm).unwrap();
+        let header = b"P6\n6 2\n255\n";
+        assert!(image.starts_with(header));
+
+        let row = [
+            0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 255, 255, 128, 128, 128, 128, 128, 128,
+        ];
+        assert_eq!(&image[header.len()..], [row, row].concat().as_slice());
+    }
+
+    #[test]
+    fn pbm() {
+        let (b, w, u) = (
+            BinaryColor::Black,
+            BinaryColor::White,
+            BinaryColor::Undefined,
+        );
+        let r = single_row_renderer(vec![b, w, u, b], 3);
+
+        let image = r.render(RasterFormat::Pbm).unwrap();
+        let header = b"P4\n12 3\n";
+        assert!(image.starts_with(header));
+        // 111000000111 padded to 2 bytes
+        assert_eq!(&image[header.len()..], [0xE0, 0x70].repeat(3).as_slice());
+    }
+
+    #[test]
+    fn unsolved_colored_cell_is_blended() {
+        let mut palette = ColorPalette::with_white("W");
+        palette
+            .color_with_name_and_value("red", ColorValue::parse("f00"))
+            .unwrap();
+        let red = MultiColor::from_color_ids(&[palette.id_by_name("red").unwrap()]);
+
+        let rows = vec![Description::new(vec![ColoredBlock::from_size_and_color(
+            1, red.0,
+        )])];
+        let columns = vec![Description::new(vec![ColoredBlock::from_size_and_color(
+            1, red.0,
+        )])];
+        let board = MutRc::new(Board::with_descriptions_and_palette(
+            rows,
+            columns,
+            Some(palette),
+        ));
+        let r = RasterRenderer::with_board_and_cell_size(MutRc::clone(&board), 1);
+        let image = r.render(RasterFormat::Ppm).unwrap();
+        assert!(image.ends_with(&[255, 128, 128]));
+
+        Board::restore_with_callback(&board, vec![red]);
+        let image = r.render(RasterFormat::Ppm).unwrap();
+        assert!(image.ends_with(&[255, 0, 0]));
+    }
+
+    #[cfg(feature = "raster")]
+    #[test]
+    fn png_signature() {
+        let r = single_row_renderer(vec![BinaryColor::White], 4);
+        let image = r.render(RasterFormat::Png).unwrap();
+        assert!(image.starts_with(b"\x89PNG\r\n\x1a\n"));
+    }
+}
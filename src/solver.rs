@@ -1,97 +1,982 @@
+use std::{fmt, iter, str::FromStr};
+
 use log::warn;
 
-use crate::{block::Block, board::Board, solver::probing::ProbeSolver, utils::rc::MutRc};
+use crate::{
+    block::{binary::BinaryBlock, Block},
+    board::{BinaryDowngrade, Board, Point, Reduction},
+    solver::{backtracking::SearchOptions, probing::ProbeSolver},
+    utils::rc::MutRc,
+};
+
+pub use self::error::SolveError;
 
-#[cfg(not(feature = "sat"))]
 pub mod backtracking;
+pub mod difficulty;
+mod error;
+pub mod hint;
 pub mod line;
 pub mod probing;
+pub mod progress;
 pub mod propagation;
 #[cfg(feature = "sat")]
 pub mod sat;
 
-#[cfg(not(feature = "sat"))]
+/// The algorithm to use for the puzzles that cannot be solved
+/// by the line propagation and probing alone.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Backend {
+    Backtracking,
+    Sat,
+}
+
+impl Backend {
+    const NAMES: [(Self, &'static str); 2] =
+        [(Self::Backtracking, "backtracking"), (Self::Sat, "sat")];
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find_map(|&(backend, name)| if backend == self { Some(name) } else { None })
+            .expect("Every backend should have a name")
+    }
+
+    /// Whether the backend was compiled in.
+    pub fn is_available(self) -> bool {
+        match self {
+            Self::Backtracking => true,
+            Self::Sat => cfg!(feature = "sat"),
+        }
+    }
+
+    /// The names of all the available backends.
+    pub fn variants() -> impl Iterator<Item = &'static str> {
+        Self::NAMES.iter().filter_map(|&(backend, name)| {
+            if backend.is_available() {
+                Some(name)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Self::Backtracking
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let backend = Self::NAMES
+            .iter()
+            .find_map(|&(backend, name)| if name == s { Some(backend) } else { None })
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::variants().collect();
+                format!("Unknown backend {:?}: expected one of {:?}", s, names)
+            })?;
+
+        if !backend.is_available() {
+            return Err(SolveError::BackendUnavailable(backend).to_string());
+        }
+        Ok(backend)
+    }
+}
+
+pub type Solution<B> = Vec<<B as Block>::Color>;
+/// The solutions found by the search backend.
+/// If the search gets stopped by the limits, the last item is the corresponding [`SolveError`].
+pub type Solutions<B> = Box<dyn Iterator<Item = Result<Solution<B>, SolveError>>>;
+
+/// The outcome of the [`check_unique`].
+// the check runs once per puzzle, so the size of the variants does not matter
+#[allow(variant_size_differences)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Uniqueness<B>
+where
+    B: Block,
+{
+    /// The puzzle has exactly one solution.
+    Unique(Solution<B>),
+    /// The puzzle has (at least) two solutions differing in the `diff` cells.
+    Multiple {
+        first: Solution<B>,
+        second: Solution<B>,
+        diff: Vec<Point>,
+    },
+    /// The puzzle has no solutions at all.
+    NoSolutions,
+}
+
+impl<B> Uniqueness<B>
+where
+    B: Block,
+{
+    /// Convert the solutions and the points to the other board.
+    fn map<B2, F, G>(self, solution: F, point: G) -> Uniqueness<B2>
+    where
+        B2: Block,
+        F: Fn(Solution<B>) -> Solution<B2>,
+        G: Fn(Point) -> Point,
+    {
+        match self {
+            Self::Unique(unique) => Uniqueness::Unique(solution(unique)),
+            Self::Multiple {
+                first,
+                second,
+                diff,
+            } => Uniqueness::Multiple {
+                first: solution(first),
+                second: solution(second),
+                diff: diff.into_iter().map(point).collect(),
+            },
+            Self::NoSolutions => Uniqueness::NoSolutions,
+        }
+    }
+}
+
+/// Solve the board with the line propagation, then probing,
+/// then (if the puzzle is still not solved) with the specified search `backend`.
+///
+/// Return `None` if the board gets solved without searching,
+/// otherwise the solutions found by the backend.
+///
+/// The board without callbacks can be reduced (the solved lines removed and the single color
+/// downgraded, see the [`Board::downgrade`]) to search on the smaller one,
+/// but the solutions are always of the original size and colors.
 pub fn run<B, S, P>(
     board: MutRc<Board<B>>,
+    backend: Backend,
     max_solutions: Option<usize>,
     timeout: Option<u32>,
     max_depth: Option<usize>,
-) -> Result<Option<backtracking::Solver<B, P, S>>, String>
+    options: SearchOptions,
+) -> Result<Option<Solutions<B>>, SolveError>
 where
-    B: Block,
+    B: 'static + Block,
+    S: 'static + line::LineSolver<BlockType = B>,
+    P: 'static + ProbeSolver<BlockType = B>,
+{
+    if propagate::<_, S>(&board)? {
+        return Ok(None);
+    }
+
+    match shrink(&board) {
+        Some(Shrunk::Binary(binary, downgrade)) => {
+            let solutions = run::<_, S::Binary, P::Binary>(
+                MutRc::clone(&binary),
+                backend,
+                max_solutions,
+                timeout,
+                max_depth,
+                options,
+            );
+            Board::restore_with_callback(&board, downgrade.upgrade(&binary.read().make_snapshot()));
+
+            return Ok(solutions?.map(|solutions| {
+                let upgraded: Solutions<B> =
+                    Box::new(solutions.map(move |solution| {
+                        solution.map(|solution| downgrade.upgrade(&solution))
+                    }));
+                upgraded
+            }));
+        }
+        Some(Shrunk::Reduced(reduced, reduction)) => {
+            let solutions = run::<_, S, P>(
+                MutRc::clone(&reduced),
+                backend,
+                max_solutions,
+                timeout,
+                max_depth,
+                options,
+            );
+            let original = board.read().make_snapshot();
+            Board::restore_with_callback(
+                &board,
+                reduction.expand(&original, &reduced.read().make_snapshot()),
+            );
+
+            return Ok(solutions?.map(|solutions| {
+                let expanded: Solutions<B> = Box::new(solutions.map(move |solution| {
+                    solution.map(|solution| reduction.expand(&original, &solution))
+                }));
+                expanded
+            }));
+        }
+        None => {}
+    }
+
+    match backend {
+        Backend::Backtracking => {
+            run_backtracking::<_, S, P>(board, max_solutions, timeout, max_depth, options)
+        }
+        Backend::Sat => {
+            if timeout.is_some() || max_depth.is_some() {
+                warn!("The timeout and the maximum depth are ignored by the SAT backend");
+            }
+            run_sat::<_, S, P>(board, max_solutions, options.threads)
+        }
+    }
+}
+
+/// Check whether the puzzle has exactly one solution.
+///
+/// The search stops as soon as the second solution found,
+/// so the check is usually much faster than finding all the solutions.
+/// If the time or depth limit is exceeded before the answer is clear,
+/// the corresponding [`SolveError`] returned.
+pub fn check_unique<B, S, P>(
+    board: MutRc<Board<B>>,
+    backend: Backend,
+    timeout: Option<u32>,
+    max_depth: Option<usize>,
+    options: SearchOptions,
+) -> Result<Uniqueness<B>, SolveError>
+where
+    B: 'static + Block,
     S: line::LineSolver<BlockType = B>,
     P: ProbeSolver<BlockType = B>,
 {
-    warn!("Solving with simple line propagation");
-    let mut solver = propagation::Solver::new(MutRc::clone(&board));
-    let solved_points = solver
-        .run::<S>(None)
-        .map_err(|err| format!("Bad puzzle: simple propagation failed: {:?}", err))?;
+    let max_solutions = Some(2);
+    let solutions = match propagate::<_, S>(&board) {
+        Ok(true) => vec![],
+        Ok(false) => {
+            match shrink(&board) {
+                Some(Shrunk::Binary(binary, downgrade)) => {
+                    let uniqueness = check_unique::<_, S::Binary, P::Binary>(
+                        MutRc::clone(&binary),
+                        backend,
+                        timeout,
+                        max_depth,
+                        options,
+                    );
+                    Board::restore_with_callback(
+                        &board,
+                        downgrade.upgrade(&binary.read().make_snapshot()),
+                    );
 
-    warn!("Solved {} points", solved_points.len());
+                    return Ok(
+                        uniqueness?.map(|solution| downgrade.upgrade(&solution), |point| point)
+                    );
+                }
+                Some(Shrunk::Reduced(reduced, reduction)) => {
+                    let uniqueness = check_unique::<_, S, P>(
+                        MutRc::clone(&reduced),
+                        backend,
+                        timeout,
+                        max_depth,
+                        options,
+                    );
+                    let original = board.read().make_snapshot();
+                    Board::restore_with_callback(
+                        &board,
+                        reduction.expand(&original, &reduced.read().make_snapshot()),
+                    );
 
-    if !board.read().is_solved_full() {
-        warn!(
-            "Trying to solve with backtracking (solved on {:.3}%)",
-            board.read().solution_rate() * 100.0
-        );
-        let mut solver =
-            backtracking::Solver::<_, P, S>::with_options(board, max_solutions, timeout, max_depth);
-        solver.run()?;
-        return Ok(Some(solver));
-    }
+                    return Ok(uniqueness?.map(
+                        |solution| reduction.expand(&original, &solution),
+                        |point| reduction.original_point(&point),
+                    ));
+                }
+                None => {}
+            }
+
+            match backend {
+                Backend::Backtracking => {
+                    let mut solver = backtracking::Solver::<_, P, S>::with_options(
+                        MutRc::clone(&board),
+                        max_solutions,
+                        timeout,
+                        max_depth,
+                    );
+                    solver.set_search_options(options);
+                    solver.run().and_then(|()| {
+                        if solver.solutions.len() < 2 {
+                            // the search was interrupted before finding the second solution
+                            if let Some(err) = solver.exceeded_limit() {
+                                return Err(err);
+                            }
+                        }
+                        Ok(solver.solutions)
+                    })
+                }
+                Backend::Sat => {
+                    run_sat::<_, S, P>(MutRc::clone(&board), max_solutions, options.threads)
+                        .and_then(|solutions| solutions.into_iter().flatten().collect())
+                }
+            }
+            .or_else(no_solutions_on_contradiction)?
+        }
+        Err(err) => no_solutions_on_contradiction(err)?,
+    };
 
-    Ok(None)
+    let mut solutions = solutions.into_iter();
+    match (solutions.next(), solutions.next()) {
+        (Some(first), Some(second)) => {
+            Board::restore_with_callback(&board, first.clone());
+            let diff = board.read().diff(&second);
+            Ok(Uniqueness::Multiple {
+                first,
+                second,
+                diff,
+            })
+        }
+        (Some(solution), None) => Ok(Uniqueness::Unique(solution)),
+        (None, _) => {
+            if board.read().is_solved_full() {
+                Ok(Uniqueness::Unique(board.read().make_snapshot()))
+            } else {
+                Ok(Uniqueness::NoSolutions)
+            }
+        }
+    }
 }
 
-#[cfg(feature = "sat")]
-#[allow(clippy::needless_pass_by_value)]
-pub fn run<B, S, P>(
+/// Count the solutions of the puzzle (up to the `max_solutions`) without storing them.
+///
+/// If the time or depth limit is exceeded before all the solutions (or `max_solutions` of them)
+/// are counted, the corresponding [`SolveError`] returned.
+pub fn count_solutions<B, S, P>(
     board: MutRc<Board<B>>,
+    backend: Backend,
     max_solutions: Option<usize>,
-) -> Result<Option<impl Iterator<Item = Vec<B::Color>>>, String>
+    timeout: Option<u32>,
+    max_depth: Option<usize>,
+    options: SearchOptions,
+) -> Result<usize, SolveError>
 where
-    B: Block,
+    B: 'static + Block,
     S: line::LineSolver<BlockType = B>,
     P: ProbeSolver<BlockType = B>,
 {
-    use crate::board::LineDirection;
+    match propagate::<_, S>(&board) {
+        Ok(true) => return Ok(1),
+        Ok(false) => {}
+        Err(err) if err.is_contradiction() => return Ok(0),
+        Err(err) => return Err(err),
+    }
+
+    // the smaller boards have the same number of solutions
+    match shrink(&board) {
+        Some(Shrunk::Binary(binary, downgrade)) => {
+            let count = count_solutions::<_, S::Binary, P::Binary>(
+                MutRc::clone(&binary),
+                backend,
+                max_solutions,
+                timeout,
+                max_depth,
+                options,
+            );
+            Board::restore_with_callback(&board, downgrade.upgrade(&binary.read().make_snapshot()));
+            return count;
+        }
+        Some(Shrunk::Reduced(reduced, reduction)) => {
+            let count = count_solutions::<_, S, P>(
+                MutRc::clone(&reduced),
+                backend,
+                max_solutions,
+                timeout,
+                max_depth,
+                options,
+            );
+            let cells = reduction.expand(
+                &board.read().make_snapshot(),
+                &reduced.read().make_snapshot(),
+            );
+            Board::restore_with_callback(&board, cells);
+            return count;
+        }
+        None => {}
+    }
+
+    match backend {
+        Backend::Backtracking => {
+            let mut solver = backtracking::Solver::<_, P, S>::with_options(
+                board,
+                max_solutions,
+                timeout,
+                max_depth,
+            );
+            solver.set_search_options(options);
+            let count = solver.count_solutions()?;
+            // the count never exceeds the maximum
+            if max_solutions != Some(count) {
+                if let Some(err) = solver.exceeded_limit() {
+                    return Err(err);
+                }
+            }
+            Ok(count)
+        }
+        Backend::Sat => match run_sat::<_, S, P>(board, max_solutions, options.threads) {
+            Ok(Some(solutions)) => Ok(solutions.count()),
+            // solved by probing
+            Ok(None) => Ok(1),
+            Err(err) if err.is_contradiction() => Ok(0),
+            Err(err) => Err(err),
+        },
+    }
+}
+
+/// The smaller board with the same solutions.
+enum Shrunk<B>
+where
+    B: Block,
+{
+    /// The single color of the board replaced with the black one.
+    Binary(MutRc<Board<BinaryBlock>>, BinaryDowngrade),
+    /// The solved lines removed (see the [`Board::reduce`]).
+    Reduced(MutRc<Board<B>>, Reduction),
+}
+
+/// Make the smaller board to solve instead of the given one.
+///
+/// The black-and-white board gets solved with the `Binary` equivalents of the requested solvers.
+/// The board with the callbacks is never shrunk to report every change of it.
+fn shrink<B>(board: &MutRc<Board<B>>) -> Option<Shrunk<B>>
+where
+    B: Block,
+{
+    let board = board.read();
+    if board.has_callbacks() {
+        return None;
+    }
+
+    if let Some((binary, downgrade)) = board.downgrade() {
+        warn!("Solving the single colored board as the black-and-white one");
+        return Some(Shrunk::Binary(MutRc::new(binary), downgrade));
+    }
+
+    let (reduced, reduction) = board.reduce()?;
+    warn!(
+        "Solving the reduced board {}x{}",
+        reduced.height(),
+        reduced.width()
+    );
+    Some(Shrunk::Reduced(MutRc::new(reduced), reduction))
+}
+
+/// Treat the contradictions as the absence of solutions, pass any other errors through.
+fn no_solutions_on_contradiction<T>(err: SolveError) -> Result<Vec<T>, SolveError> {
+    if err.is_contradiction() {
+        Ok(vec![])
+    } else {
+        Err(err)
+    }
+}
+
+/// Validate the clues and solve the board with the line propagation.
+///
+/// Return whether the board gets solved completely.
+pub(crate) fn propagate<B, S>(board: &MutRc<Board<B>>) -> Result<bool, SolveError>
+where
+    B: Block,
+    S: line::LineSolver<BlockType = B>,
+{
+    board.read().validate()?;
 
     warn!("Solving with simple line propagation");
-    let mut solver = propagation::Solver::new(MutRc::clone(&board));
-    let solved_points = solver
-        .run::<S>(None)
-        .map_err(|err| format!("Bad puzzle: simple propagation failed: {:?}", err))?;
+    let mut solver = propagation::Solver::new(MutRc::clone(board));
+    let solved_points = solver.run::<S>(None)?;
     warn!("Solved {} points", solved_points.len());
 
-    if board.read().is_solved_full() {
-        return Ok(None);
+    Ok(board.read().is_solved_full())
+}
+
+fn run_backtracking<B, S, P>(
+    board: MutRc<Board<B>>,
+    max_solutions: Option<usize>,
+    timeout: Option<u32>,
+    max_depth: Option<usize>,
+    options: SearchOptions,
+) -> Result<Option<Solutions<B>>, SolveError>
+where
+    B: 'static + Block,
+    S: 'static + line::LineSolver<BlockType = B>,
+    P: 'static + ProbeSolver<BlockType = B>,
+{
+    warn!(
+        "Trying to solve with backtracking (solved on {:.3}%)",
+        board.read().solution_rate() * 100.0
+    );
+    let mut solver =
+        backtracking::Solver::<_, P, S>::with_options(board, max_solutions, timeout, max_depth);
+    solver.set_search_options(options);
+
+    if solver.is_parallel() {
+        // the threads collect all the solutions before returning
+        let error = match solver.run() {
+            Ok(()) => solver.exceeded_limit(),
+            // the same as the serial search without any solutions
+            Err(SolveError::NoSolutions) => None,
+            Err(err) => Some(err),
+        };
+        warn_search_progress(&solver);
+
+        let solutions = solver.solutions.into_iter().map(Ok).chain(error.map(Err));
+        return Ok(Some(Box::new(solutions)));
     }
 
+    let mut solutions = solver.into_solutions()?;
+    let mut finished = false;
+    Ok(Some(Box::new(iter::from_fn(move || {
+        if finished {
+            return None;
+        }
+        if let Some(solution) = solutions.next() {
+            return Some(Ok(solution));
+        }
+
+        finished = true;
+        warn_search_progress(solutions.solver());
+        solutions.error().cloned().map(Err)
+    }))))
+}
+
+fn warn_search_progress<B, P, S>(solver: &backtracking::Solver<B, P, S>)
+where
+    B: Block,
+    P: ProbeSolver<BlockType = B>,
+    S: line::LineSolver<BlockType = B>,
+{
+    let search_tree = solver.search_tree.read();
+    if !search_tree.is_empty() {
+        warn!("Searching progress: {:?}", search_tree);
+    }
+}
+
+#[cfg(feature = "sat")]
+#[allow(clippy::needless_pass_by_value)]
+fn run_sat<B, S, P>(
+    board: MutRc<Board<B>>,
+    max_solutions: Option<usize>,
+    probe_threads: usize,
+) -> Result<Option<Solutions<B>>, SolveError>
+where
+    B: 'static + Block,
+    S: line::LineSolver<BlockType = B>,
+    P: ProbeSolver<BlockType = B>,
+{
+    use crate::board::LineDirection;
+
     let impact = {
         warn!(
             "Solving with probing (solved on {:.3}%)",
             board.read().solution_rate() * 100.0
         );
         let mut probe_solver = P::with_board(MutRc::clone(&board));
+        probe_solver.set_threads(probe_threads);
         probe_solver.run_unsolved::<S>()?
     };
 
-    if !board.read().is_solved_full() {
-        warn!(
-            "Trying to solve with SAT (solved on {:.3}%)",
-            board.read().solution_rate() * 100.0
+    if board.read().is_solved_full() {
+        return Ok(None);
+    }
+
+    warn!(
+        "Trying to solve with SAT (solved on {:.3}%)",
+        board.read().solution_rate() * 100.0
+    );
+    let solver = sat::ClauseGenerator::with_clues(
+        board.read().descriptions(LineDirection::Column),
+        board.read().descriptions(LineDirection::Row),
+        board.read().make_snapshot(),
+    );
+
+    let solutions_iter = solver.run(impact, max_solutions);
+    Ok(Some(Box::new(solutions_iter.map(Ok))))
+}
+
+#[cfg(not(feature = "sat"))]
+#[allow(clippy::needless_pass_by_value, clippy::extra_unused_type_parameters)]
+fn run_sat<B, S, P>(
+    _board: MutRc<Board<B>>,
+    _max_solutions: Option<usize>,
+    _probe_threads: usize,
+) -> Result<Option<Solutions<B>>, SolveError>
+where
+    B: 'static + Block,
+    S: line::LineSolver<BlockType = B>,
+    P: ProbeSolver<BlockType = B>,
+{
+    Err(SolveError::BackendUnavailable(Backend::Sat))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+
+    use crate::{
+        block::{
+            binary::{
+                BinaryBlock,
+                BinaryColor::{Black, White},
+            },
+            multicolor::{ColoredBlock, MultiColor},
+            Clue, Description,
+        },
+        board::{Board, Point},
+        test_utils::{board, permutations},
+        utils::rc::MutRc,
+    };
+
+    use super::{
+        backtracking::SearchOptions,
+        check_unique, count_solutions,
+        line::{DynamicSolver, LineSolver},
+        probing::{FullProbe1, FullProbe2, ProbeSolver},
+        run, Backend, SolveError, Uniqueness,
+    };
+
+    fn check(board: MutRc<Board<BinaryBlock>>, backend: Backend) -> Uniqueness<BinaryBlock> {
+        check_unique::<_, DynamicSolver<_>, FullProbe1<_>>(
+            board,
+            backend,
+            None,
+            None,
+            SearchOptions::default(),
+        )
+        .unwrap()
+    }
+
+    fn backends() -> impl Iterator<Item = Backend> {
+        Backend::variants().map(|name| name.parse().unwrap())
+    }
+
+    #[test]
+    fn unique_puzzle() {
+        for backend in backends() {
+            let board = board(&[&[1, 1], &[1, 1], &[3]], &[&[3], &[1], &[3]]);
+            match check(board, backend) {
+                Uniqueness::Unique(solution) => assert_eq!(solution.len(), 9),
+                other => panic!("Expected unique solution, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn ambiguous_puzzle() {
+        for backend in backends() {
+            let board = permutations(2);
+            match check(board, backend) {
+                Uniqueness::Multiple {
+                    first,
+                    second,
+                    diff,
+                } => {
+                    assert_ne!(first, second);
+                    let mut diff = diff;
+                    diff.sort();
+                    let mut expected = vec![
+                        Point::new(0, 0),
+                        Point::new(1, 0),
+                        Point::new(0, 1),
+                        Point::new(1, 1),
+                    ];
+                    expected.sort();
+                    assert_eq!(diff, expected);
+                }
+                other => panic!("Expected multiple solutions, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn contradictory_puzzle() {
+        for backend in backends() {
+            let board = board(&[&[2], &[]], &[&[2], &[]]);
+            assert_eq!(check(board, backend), Uniqueness::NoSolutions);
+        }
+    }
+
+    fn count(
+        board: MutRc<Board<BinaryBlock>>,
+        backend: Backend,
+        max_solutions: Option<usize>,
+    ) -> usize {
+        count_solutions::<_, DynamicSolver<_>, FullProbe1<_>>(
+            board,
+            backend,
+            max_solutions,
+            None,
+            None,
+            SearchOptions::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn count_permutations() {
+        for backend in backends() {
+            let board = permutations(4);
+            assert_eq!(count(board, backend, None), 24);
+        }
+    }
+
+    #[test]
+    fn count_up_to_max() {
+        for backend in backends() {
+            let board = permutations(3);
+            assert_eq!(count(board, backend, Some(4)), 4);
+        }
+    }
+
+    #[test]
+    fn count_unique_and_contradictory() {
+        for backend in backends() {
+            let unique = board(&[&[1, 1], &[1, 1], &[3]], &[&[3], &[1], &[3]]);
+            assert_eq!(count(unique, backend, None), 1);
+
+            let contradictory = board(&[&[2], &[]], &[&[2], &[]]);
+            assert_eq!(count(contradictory, backend, None), 0);
+        }
+    }
+
+    #[cfg(feature = "threaded")]
+    #[test]
+    fn search_in_threads() {
+        let options = SearchOptions {
+            threads: 3,
+            ..SearchOptions::default()
+        };
+        let solutions = run::<_, DynamicSolver<_>, FullProbe1<_>>(
+            permutations(3),
+            Backend::Backtracking,
+            None,
+            None,
+            None,
+            options,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(solutions.count(), 6);
+        let unique = check_unique::<_, DynamicSolver<_>, FullProbe1<_>>(
+            permutations(3),
+            Backend::Backtracking,
+            None,
+            None,
+            options,
+        )
+        .unwrap();
+        match unique {
+            Uniqueness::Multiple { .. } => {}
+            other => panic!("Expected multiple solutions, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn backend_from_str() {
+        #[cfg(feature = "sat")]
+        assert_eq!("sat".parse(), Ok(Backend::Sat));
+        #[cfg(not(feature = "sat"))]
+        assert!("sat".parse::<Backend>().is_err());
+        assert_eq!(" Backtracking".parse(), Ok(Backend::Backtracking));
+        assert!("dfs".parse::<Backend>().is_err());
+    }
+
+    #[cfg(not(feature = "sat"))]
+    #[test]
+    fn unavailable_backend() {
+        // the 2x2 permutations cannot be solved without searching
+        let expected = SolveError::BackendUnavailable(Backend::Sat);
+
+        let unique = check_unique::<_, DynamicSolver<_>, FullProbe1<_>>(
+            permutations(2),
+            Backend::Sat,
+            None,
+            None,
+            SearchOptions::default(),
         );
-        let solver = sat::ClauseGenerator::with_clues(
-            board.read().descriptions(LineDirection::Column),
-            board.read().descriptions(LineDirection::Row),
-            board.read().make_snapshot(),
+        assert_eq!(unique, Err(expected.clone()));
+        let count = count_solutions::<_, DynamicSolver<_>, FullProbe1<_>>(
+            permutations(2),
+            Backend::Sat,
+            None,
+            None,
+            None,
+            SearchOptions::default(),
         );
+        assert_eq!(count, Err(expected));
+    }
+
+    #[test]
+    fn backend_names_round_trip() {
+        for name in Backend::variants() {
+            let backend: Backend = name.parse().unwrap();
+            assert_eq!(backend.to_string(), name);
+        }
+    }
+
+    #[test]
+    fn limit_after_solutions() {
+        let threads: &[usize] = if cfg!(feature = "threaded") {
+            &[1, 3]
+        } else {
+            &[1]
+        };
+
+        for &threads in threads {
+            let options = SearchOptions {
+                threads,
+                ..SearchOptions::default()
+            };
+            let solutions: Vec<_> = run::<_, DynamicSolver<_>, FullProbe1<_>>(
+                permutations(3),
+                Backend::Backtracking,
+                None,
+                None,
+                Some(1),
+                options,
+            )
+            .unwrap()
+            .unwrap()
+            .collect();
+
+            // the depth is counted differently, but the limit is reported the same way
+            let (limit, found) = solutions.split_last().unwrap();
+            assert_eq!(limit, &Err(SolveError::DepthLimit(1)));
+            assert!(found.iter().all(Result::is_ok));
+        }
+    }
+
+    #[test]
+    fn search_on_reduced_board() {
+        // the last row and the column are solved, the rest are the permutations of 3x3
+        let rows: &[&[usize]] = &[&[1], &[1], &[1], &[], &[4]];
+        let columns: &[&[usize]] = &[&[1, 1], &[1, 1], &[1, 1], &[1]];
+
+        for backend in backends() {
+            let solutions: Vec<_> = run::<_, DynamicSolver<_>, FullProbe1<_>>(
+                board(rows, columns),
+                backend,
+                None,
+                None,
+                None,
+                SearchOptions::default(),
+            )
+            .unwrap()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+            assert_eq!(solutions.len(), 6);
+            for solution in &solutions {
+                let board = board(rows, columns);
+                // the full solution is validated on the restore
+                Board::restore_with_callback(&board, solution.clone());
+                assert!(board.read().is_solved_full());
+            }
+
+            assert_eq!(count(board(rows, columns), backend, None), 6);
+        }
+    }
+
+    #[test]
+    fn single_colored_board() {
+        // the colored permutations of 3x3
+        let desc = || vec![Description::new(vec![ColoredBlock::from_size_and_color(1, 4)]); 3];
+
+        for backend in backends() {
+            let board = MutRc::new(Board::with_descriptions_and_palette(desc(), desc(), None));
+            let solutions: Vec<_> = run::<_, DynamicSolver<_>, FullProbe1<_>>(
+                MutRc::clone(&board),
+                backend,
+                None,
+                None,
+                None,
+                SearchOptions::default(),
+            )
+            .unwrap()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
 
-        let solutions_iter = solver.run(impact, max_solutions);
+            assert_eq!(solutions.len(), 6);
+            for solution in solutions {
+                let colored = solution
+                    .iter()
+                    .filter(|&&cell| cell == MultiColor(4))
+                    .count();
+                let blank = solution
+                    .iter()
+                    .filter(|&&cell| cell == MultiColor(1))
+                    .count();
+                assert_eq!((colored, blank), (3, 6));
+            }
+        }
+    }
+
+    #[test]
+    fn single_colored_board_keeps_solvers() {
+        type Line = <DynamicSolver<ColoredBlock> as LineSolver>::Binary;
+        type Probe = <FullProbe2<ColoredBlock> as ProbeSolver>::Binary;
+        assert_eq!(
+            TypeId::of::<Line>(),
+            TypeId::of::<DynamicSolver<BinaryBlock>>()
+        );
+        assert_eq!(
+            TypeId::of::<Probe>(),
+            TypeId::of::<FullProbe2<BinaryBlock>>()
+        );
 
-        return Ok(Some(solutions_iter));
+        let desc = || vec![Description::new(vec![ColoredBlock::from_size_and_color(1, 4)]); 3];
+        let board = MutRc::new(Board::with_descriptions_and_palette(desc(), desc(), None));
+        let count = count_solutions::<_, DynamicSolver<_>, FullProbe2<_>>(
+            board,
+            Backend::Backtracking,
+            None,
+            None,
+            None,
+            SearchOptions::default(),
+        );
+        assert_eq!(count, Ok(6));
     }
 
-    Ok(None)
+    #[test]
+    fn blotted_puzzle() {
+        let block = |size| Clue::Block(BinaryBlock(size));
+        let blot = || Clue::Blot(Black);
+        let desc = |lines: Vec<Vec<Clue<BinaryBlock>>>| {
+            lines.into_iter().map(Description::with_clues).collect()
+        };
+
+        for backend in backends() {
+            let rows = desc(vec![vec![block(1), block(1)], vec![blot()], vec![block(3)]]);
+            let columns = desc(vec![vec![blot()], vec![block(2)], vec![block(1), block(1)]]);
+            let board = MutRc::new(Board::with_descriptions_and_palette(rows, columns, None));
+
+            let mut solutions: Vec<_> = run::<_, DynamicSolver<_>, FullProbe1<_>>(
+                board,
+                backend,
+                None,
+                None,
+                None,
+                SearchOptions::default(),
+            )
+            .unwrap()
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+            solutions.sort_by_key(|solution| solution[3] == Black);
+
+            // only the first cell of the blotted row and column is unknown
+            let (b, w) = (Black, White);
+            assert_eq!(
+                solutions,
+                vec![
+                    vec![b, w, b, w, b, w, b, b, b],
+                    vec![b, w, b, b, b, w, b, b, b],
+                ]
+            );
+        }
+    }
 }
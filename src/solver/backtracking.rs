@@ -1,4 +1,6 @@
-use std::{cmp::Reverse, fmt, marker::PhantomData, time::Instant};
+use std::{cmp::Reverse, fmt, marker::PhantomData, str::FromStr, time::Instant};
+#[cfg(feature = "threaded")]
+use std::{mem, thread};
 
 use hashbrown::{HashMap, HashSet};
 use log::{debug, info, log_enabled, warn, Level};
@@ -9,8 +11,11 @@ use crate::{
     solver::{
         line::LineSolver,
         probing::{Impact, Priority, ProbeImpact, ProbeSolver},
+        progress::{CancelToken, Progress, ProgressCallback, ProgressReporter},
+        SolveError,
     },
     utils::{
+        random::XorShift,
         rc::{MutRc, ReadRef},
         time,
     },
@@ -35,23 +40,255 @@ where
 
     // dynamic variables
     pub solutions: Vec<Solution<B>>,
+    /// The solutions counted (not stored) by the `count_solutions`.
+    solutions_counted: usize,
     depth_reached: usize,
+    nodes_explored: usize,
     start_time: Option<Instant>,
     //explored_paths: HashSet<Vec<(Point, B::Color)>>,
     pub search_tree: SearchTreeRef<(Point, B::Color), f64>,
 
+    choose_pixel: ChoosePixel,
+    cancel: CancelToken,
+    progress: ProgressReporter,
+    #[cfg(feature = "threaded")]
+    threads: usize,
+    #[cfg(feature = "threaded")]
+    shared: Option<MutRc<SharedSearch<B>>>,
+
     _phantom: PhantomData<S>,
 }
 
-#[allow(dead_code)]
-enum ChoosePixel {
+/// The recursive search needs more stack than the spawned threads have by default.
+#[cfg(feature = "threaded")]
+const SEARCH_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// The state of the search split between the threads by the top level directions.
+#[cfg(feature = "threaded")]
+#[derive(Debug)]
+struct SharedSearch<B>
+where
+    B: Block,
+{
+    /// The top level directions not taken yet (the most prioritized are at the end).
+    directions: Vec<(Point, B::Color)>,
+    solutions: Vec<Solution<B>>,
+    /// The top level directions that lead to the dead end.
+    excluded: Vec<(Point, B::Color)>,
+    stopped: bool,
+    error: Option<SolveError>,
+}
+
+#[cfg(feature = "threaded")]
+impl<B> SharedSearch<B>
+where
+    B: Block,
+{
+    fn add_solution(&mut self, board: &Board<B>, max_solutions: Option<usize>) {
+        if self
+            .solutions
+            .iter()
+            .any(|solution| !board.differs(solution))
+        {
+            info!("The solution was already found by some thread");
+            return;
+        }
+
+        if let Some(max_solutions) = max_solutions {
+            if self.solutions.len() >= max_solutions {
+                return;
+            }
+            self.stopped = self.solutions.len() + 1 >= max_solutions;
+        }
+        self.solutions.push(board.make_snapshot());
+    }
+
+    fn fail(&mut self, err: SolveError) {
+        if self.error.is_none() {
+            self.error = Some(err);
+        }
+        self.stopped = true;
+    }
+}
+
+/// Everything the thread needs to explore the top level directions on its own board.
+#[cfg(feature = "threaded")]
+#[derive(Debug)]
+struct SearchJob<B>
+where
+    B: Block,
+{
+    shared: MutRc<SharedSearch<B>>,
+    board: Board<B>,
+    max_solutions: Option<usize>,
+    timeout: Option<u32>,
+    max_depth: Option<usize>,
+    start_time: Option<Instant>,
+    choose_pixel: ChoosePixel,
+    cancel: CancelToken,
+    progress: ProgressReporter,
+}
+
+/// How to choose the cell to guess first.
+///
+/// Most of the strategies rate the cells by the number of cells solved
+/// when probing every color of the cell (see the `ProbeImpact`).
+/// For details, see https://ieeexplore.ieee.org/document/6476646
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChoosePixel {
+    /// The sum of the impacts of all the colors.
     Sum,
+    /// The minimal impact of the colors.
     Min,
+    /// The maximal impact of the colors (the most trivial, but also most ineffective strategy).
     Max,
+    /// The product of the impacts of all the colors.
     Mul,
+    /// The square root of the ratio of the maximal and minimal impacts plus the minimal impact.
     Sqrt,
+    /// The product of the logarithms of the impacts plus the minimal impact.
     MinLogm,
+    /// The difference of the logarithms of the two impacts plus the minimal impact.
     MinLogd,
+    /// The cell with the least number of the colors left.
+    MinColors,
+    /// The cell in the most solved row and column.
+    ConstrainedLine,
+    /// The random order of the cells, reproducible with the given seed.
+    Random(u64),
+}
+
+impl ChoosePixel {
+    const NAMES: [(Self, &'static str); 10] = [
+        (Self::Sum, "sum"),
+        (Self::Min, "min"),
+        (Self::Max, "max"),
+        (Self::Mul, "mul"),
+        (Self::Sqrt, "sqrt"),
+        (Self::MinLogm, "min-logm"),
+        (Self::MinLogd, "min-logd"),
+        (Self::MinColors, "min-colors"),
+        (Self::ConstrainedLine, "constrained-line"),
+        (Self::Random(0), "random"),
+    ];
+
+    pub fn name(self) -> &'static str {
+        let this = if let Self::Random(_) = self {
+            Self::Random(0)
+        } else {
+            self
+        };
+        Self::NAMES
+            .iter()
+            .find_map(|&(strategy, name)| if strategy == this { Some(name) } else { None })
+            .expect("Every strategy should have a name")
+    }
+
+    /// The names of all the strategies.
+    pub fn variants() -> impl Iterator<Item = &'static str> {
+        Self::NAMES.iter().map(|&(_strategy, name)| name)
+    }
+
+    /// Rate the cell by the number of cells solved with every color of it.
+    fn rate_impact(self, sizes: &[usize]) -> f64 {
+        let min = sizes.iter().min().unwrap_or(&0);
+        let max = sizes.iter().max().unwrap_or(&0);
+        let sum: usize = sizes.iter().sum();
+
+        let log = |f: f64| f.ln_1p() + 1.0;
+
+        #[allow(clippy::cast_precision_loss)]
+        match self {
+            Self::Sum => sum as f64,
+            Self::Min => *min as f64,
+            Self::Max => *max as f64,
+            Self::Mul => sizes.iter().map(|x| (x + 1) as f64).product(),
+            Self::MinLogm => {
+                let logm: f64 = sizes.iter().map(|&x| log(x as f64)).product();
+                logm + (*min as f64)
+            }
+            Self::MinLogd => match sizes {
+                [first, second] => {
+                    let diff = log(*first as f64) - log(*second as f64);
+                    *min as f64 + diff.abs()
+                }
+                _other => *min as f64,
+            },
+            Self::Sqrt | Self::MinColors | Self::ConstrainedLine | Self::Random(_) => {
+                (*max as f64 / (min + 1) as f64).sqrt() + (*min as f64)
+            }
+        }
+    }
+}
+
+impl Default for ChoosePixel {
+    fn default() -> Self {
+        Self::Sqrt
+    }
+}
+
+impl fmt::Display for ChoosePixel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Random(seed) if *seed != 0 => write!(f, "{}:{}", self.name(), seed),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+impl FromStr for ChoosePixel {
+    type Err = String;
+
+    /// The name of the strategy, the random one can have the seed: `random:42`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let mut parts = s.splitn(2, ':');
+        let name = parts.next().unwrap_or_default();
+
+        let strategy = Self::NAMES
+            .iter()
+            .find_map(|&(strategy, strategy_name)| {
+                if strategy_name == name {
+                    Some(strategy)
+                } else {
+                    None
+                }
+            })
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::variants().collect();
+                format!("Unknown strategy {:?}: expected one of {:?}", name, names)
+            })?;
+
+        match (strategy, parts.next()) {
+            (_, None) => Ok(strategy),
+            (Self::Random(_), Some(seed)) => seed
+                .parse()
+                .map(Self::Random)
+                .map_err(|err| format!("Invalid seed {:?}: {}", seed, err)),
+            (_, Some(_)) => Err(format!(
+                "Only the random strategy can have the seed: {:?}",
+                s
+            )),
+        }
+    }
+}
+
+/// How the search goes, regardless of the limits it has.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// How to choose the cells to guess.
+    pub choose_pixel: ChoosePixel,
+    /// The number of threads to split the probing and the search between (with the `threaded` feature).
+    pub threads: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            choose_pixel: ChoosePixel::default(),
+            threads: 1,
+        }
+    }
 }
 
 type SearchTreeRef<K, V> = MutRc<SearchTree<K, V>>;
@@ -208,6 +445,92 @@ where
     }
 }
 
+/// The cell to try every color of on the saved state of the board.
+#[derive(Debug)]
+struct SearchFrame<B>
+where
+    B: Block,
+{
+    point: Point,
+    /// The colors not tried yet (the next one is at the end).
+    colors: Vec<B::Color>,
+    /// The color being tried now.
+    tried: Option<B::Color>,
+    save: Solution<B>,
+}
+
+/// The solutions of the backtracking search found lazily, one at a time.
+#[derive(Debug)]
+pub struct SolutionsIter<B, P, S>
+where
+    B: Block,
+    P: ProbeSolver<BlockType = B>,
+    S: LineSolver<BlockType = B>,
+{
+    solver: Solver<B, P, S>,
+    stack: Vec<SearchFrame<B>>,
+    /// The board solved without the search.
+    solved: Option<Solution<B>>,
+    error: Option<SolveError>,
+}
+
+impl<B, P, S> SolutionsIter<B, P, S>
+where
+    B: Block,
+    P: ProbeSolver<BlockType = B>,
+    S: LineSolver<BlockType = B>,
+{
+    /// The solver to get the search statistics or the limit exceeded.
+    pub fn solver(&self) -> &Solver<B, P, S> {
+        &self.solver
+    }
+
+    /// The error or the exceeded limit that stopped the iteration
+    /// (e.g. the `SolveError::Cancelled` or the `SolveError::Timeout`).
+    pub fn error(&self) -> Option<&SolveError> {
+        self.error.as_ref()
+    }
+}
+
+impl<B, P, S> Iterator for SolutionsIter<B, P, S>
+where
+    B: Block,
+    P: ProbeSolver<BlockType = B>,
+    S: LineSolver<BlockType = B>,
+{
+    type Item = Solution<B>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(solution) = self.solved.take() {
+            return Some(solution);
+        }
+
+        let outside = self.solver.board().make_snapshot();
+        let solution = match self.solver.next_solution(&mut self.stack) {
+            Ok(true) => {
+                self.solver.solutions_counted += 1;
+                Some(self.solver.board().make_snapshot())
+            }
+            Ok(false) => {
+                // tell the truncated enumeration apart from the complete one
+                self.error = self.solver.exceeded_limit();
+                if let Some(err) = &self.error {
+                    warn!("The search stopped: {}", err);
+                }
+                None
+            }
+            Err(err) => {
+                warn!("The search stopped: {}", err);
+                self.stack.clear();
+                self.error = Some(err);
+                None
+            }
+        };
+        Board::restore_with_callback(&self.solver.board, outside);
+        solution
+    }
+}
+
 #[allow(clippy::unused_self)]
 impl<B, P, S> Solver<B, P, S>
 where
@@ -236,15 +559,71 @@ where
             timeout,
             max_depth,
             solutions: vec![],
+            solutions_counted: 0,
             depth_reached: 0,
+            nodes_explored: 0,
             start_time: None,
             //explored_paths: HashSet::new(),
             search_tree: MutRc::new(SearchTree::new()),
+            choose_pixel: ChoosePixel::default(),
+            cancel: CancelToken::default(),
+            progress: ProgressReporter::default(),
+            #[cfg(feature = "threaded")]
+            threads: 1,
+            #[cfg(feature = "threaded")]
+            shared: None,
             _phantom: PhantomData,
         }
     }
 
-    pub fn run(&mut self) -> Result<(), String> {
+    /// Whether the `run` splits the search between the threads.
+    pub fn is_parallel(&self) -> bool {
+        #[cfg(feature = "threaded")]
+        {
+            self.threads > 1
+        }
+        #[cfg(not(feature = "threaded"))]
+        {
+            false
+        }
+    }
+
+    /// How to choose the cells to guess (the `ChoosePixel::Sqrt` by default).
+    pub fn set_choose_pixel(&mut self, strategy: ChoosePixel) {
+        self.choose_pixel = strategy;
+    }
+
+    /// Split the `run` between the given number of threads (only with the `threaded` feature).
+    /// The probing before the search is split between them too.
+    #[allow(unused_variables)]
+    pub fn set_threads(&mut self, threads: usize) {
+        #[cfg(feature = "threaded")]
+        {
+            self.threads = threads.max(1);
+            self.probe_solver.set_threads(self.threads);
+        }
+    }
+
+    /// Apply all the `SearchOptions` at once.
+    pub fn set_search_options(&mut self, options: SearchOptions) {
+        self.set_choose_pixel(options.choose_pixel);
+        self.set_threads(options.threads);
+    }
+
+    /// Stop the search (and the probing) with the `SolveError::Cancelled` once the token is cancelled.
+    /// The solutions found before the cancellation are kept.
+    pub fn set_cancel_token(&mut self, token: CancelToken) {
+        self.probe_solver.set_cancel_token(token.clone());
+        self.cancel = token;
+    }
+
+    /// Call the function before every guess of the search.
+    /// In the parallel search every thread reports its own depth and number of guesses.
+    pub fn set_progress_callback<CB: ProgressCallback + 'static>(&mut self, f: CB) {
+        self.progress = ProgressReporter::new(f);
+    }
+
+    pub fn run(&mut self) -> Result<(), SolveError> {
         if self.is_solved() {
             return Ok(());
         }
@@ -261,9 +640,15 @@ where
             "Starting depth-first search (initial rate is {:.4})",
             self.board().solution_rate()
         );
-        let success = self.search(directions, &[])?;
+        let success = match self.search_root(directions) {
+            Err(SolveError::Cancelled) if !self.solutions.is_empty() => {
+                warn!("The search was cancelled");
+                true
+            }
+            result => result?,
+        };
         if !success {
-            return Err("Backtracking failed".to_string());
+            return Err(SolveError::NoSolutions);
         }
 
         warn!(
@@ -283,9 +668,215 @@ where
             );
         }
 
+        if self.solutions.is_empty() && !self.is_solved() {
+            if let Some(err) = self.exceeded_limit() {
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Count the solutions (up to the `max_solutions`) without storing them.
+    ///
+    /// Unlike the `run`, every cell is branched on all of its colors, so every solution
+    /// is reached exactly once and the memory used does not depend on the number of solutions.
+    /// If the search gets stopped by the time or depth limit, the count is not complete:
+    /// check it with the `exceeded_limit`.
+    pub fn count_solutions(&mut self) -> Result<usize, SolveError> {
+        let mut stack = match self.start_enumeration() {
+            Ok(stack) => stack,
+            Err(err) if err.is_contradiction() => return Ok(0),
+            Err(err) => return Err(err),
+        };
+        if stack.is_empty() {
+            self.solutions_counted = 1;
+            return Ok(self.solutions_counted);
+        }
+
+        let save = stack[0].save.clone();
+        let result = self.count_rest(&mut stack);
+        Board::restore_with_callback(&self.board, save);
+        result.map(|()| self.solutions_counted)
+    }
+
+    fn count_rest(&mut self, stack: &mut Vec<SearchFrame<B>>) -> Result<(), SolveError> {
+        while self.next_solution(stack)? {
+            self.solutions_counted += 1;
+        }
         Ok(())
     }
 
+    /// Probe the board and return the iterator over the solutions of the search.
+    ///
+    /// The solutions are found one by one (the same way as in the `count_solutions`),
+    /// so the search stops as soon as the caller stops iterating.
+    /// The board is left unchanged between the iterations.
+    pub fn into_solutions(mut self) -> Result<SolutionsIter<B, P, S>, SolveError> {
+        let stack = self.start_enumeration()?;
+        let solved = if stack.is_empty() {
+            Some(self.board().make_snapshot())
+        } else {
+            None
+        };
+
+        Ok(SolutionsIter {
+            solver: self,
+            stack,
+            solved,
+            error: None,
+        })
+    }
+
+    /// Solve the board with probing and choose the first cell to branch on.
+    /// Return nothing to branch on if the board gets solved.
+    fn start_enumeration(&mut self) -> Result<Vec<SearchFrame<B>>, SolveError> {
+        if self.is_solved() {
+            return Ok(vec![]);
+        }
+
+        let impact = self.probe_solver.run_unsolved::<S>()?;
+        if self.is_solved() {
+            return Ok(vec![]);
+        }
+
+        self.start_time = time::now();
+        warn!(
+            "Starting to enumerate the solutions (initial rate is {:.4})",
+            self.board().solution_rate()
+        );
+        Ok(vec![self.branch_on(impact)])
+    }
+
+    /// The most promising cell of the consistent unsolved board to try all of its colors.
+    fn branch_on(&self, impact: Impact<B>) -> SearchFrame<B> {
+        let point = self
+            .choose_directions(impact)
+            .first()
+            .map(|&(point, _color)| point)
+            .or_else(|| self.board().unsolved_cells().next())
+            .expect("The unsolved board should have some unsolved cells");
+
+        let mut colors = self.board().cell(&point).variants();
+        // pop from the end, so the first variant goes first
+        colors.reverse();
+
+        SearchFrame {
+            point,
+            colors,
+            tried: None,
+            save: self.board().make_snapshot(),
+        }
+    }
+
+    /// Continue the depth-first search until the next solution.
+    /// Return False if there are no more solutions (or the search was stopped by the limits).
+    fn next_solution(&mut self, stack: &mut Vec<SearchFrame<B>>) -> Result<bool, SolveError> {
+        loop {
+            let depth = stack.len();
+            let (point, color, save) = match stack.last_mut() {
+                None => return Ok(false),
+                Some(frame) => match frame.colors.pop() {
+                    Some(color) => {
+                        frame.tried = Some(color);
+                        (frame.point, color, frame.save.clone())
+                    }
+                    None => {
+                        let _ = stack.pop();
+                        continue;
+                    }
+                },
+            };
+
+            if self.limits_reached(depth - 1) {
+                stack.clear();
+                return Ok(false);
+            }
+            self.depth_reached = self.depth_reached.max(depth);
+
+            Board::restore_with_callback(&self.board, save);
+            match self.try_color(point, color, depth) {
+                Ok(None) => {
+                    info!("Found one of solutions on depth {}", depth);
+                    self.add_enumeration_path(stack, Some(1.0));
+                    return Ok(true);
+                }
+                Ok(Some(impact)) => {
+                    let rate = self.board().solution_rate();
+                    self.add_enumeration_path(stack, Some(rate));
+                    let frame = self.branch_on(impact);
+                    stack.push(frame);
+                }
+                Err(err) if err.is_contradiction() => {
+                    self.add_enumeration_path(stack, None);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Set the color and solve the board as much as possible.
+    /// Return the probing impact if the board is not solved yet.
+    fn try_color(
+        &mut self,
+        point: Point,
+        color: B::Color,
+        depth: usize,
+    ) -> Result<Option<Impact<B>>, SolveError> {
+        self.nodes_explored += 1;
+        self.progress.report(Progress {
+            depth,
+            nodes_explored: self.nodes_explored,
+            solution_rate: self.board().solution_rate(),
+        });
+
+        let mut probe_jobs = self.probe_solver.unsolved_cells();
+        Board::set_color_with_callback(&self.board, &point, &color);
+        probe_jobs.extend(self.probe_solver.propagate_point::<S>(&point)?);
+        if self.is_solved() {
+            return Ok(None);
+        }
+
+        let impact = self.probe_solver.run::<S>(&mut probe_jobs)?;
+        if self.is_solved() {
+            return Ok(None);
+        }
+        Ok(Some(impact))
+    }
+
+    /// The maximum depth of the search tree.
+    pub fn depth_reached(&self) -> usize {
+        self.depth_reached
+    }
+
+    /// The number of the guesses made (the nodes of the search tree).
+    ///
+    /// The `search_tree` itself only gets filled with enabled logging.
+    pub fn nodes_explored(&self) -> usize {
+        self.nodes_explored
+    }
+
+    /// The time or depth limit that stopped the search, if any.
+    pub fn exceeded_limit(&self) -> Option<SolveError> {
+        if self.cancel.is_cancelled() {
+            return Some(SolveError::Cancelled);
+        }
+
+        if let (Some(timeout), Some(start_time)) = (self.timeout, self.start_time) {
+            if start_time.elapsed().as_secs() >= timeout.into() {
+                return Some(SolveError::Timeout(timeout));
+            }
+        }
+
+        if let Some(max_depth) = self.max_depth {
+            if self.depth_reached > max_depth {
+                return Some(SolveError::DepthLimit(max_depth));
+            }
+        }
+
+        None
+    }
+
     fn board(&self) -> ReadRef<'_, Board<B>> {
         self.board.read()
     }
@@ -323,6 +914,16 @@ where
     fn add_solution(&mut self) {
         // TODO: force to check the board
         info!("Found one of solutions");
+        #[cfg(feature = "threaded")]
+        {
+            if let Some(shared) = &self.shared {
+                shared
+                    .write()
+                    .add_solution(&self.board(), self.max_solutions);
+                return;
+            }
+        }
+
         if !self.already_found() {
             let cells = self.board().make_snapshot();
             self.solutions.push(cells);
@@ -345,11 +946,20 @@ where
         let mut points_rate: Vec<_> = point_wise
             .iter()
             .map(|(point, color_to_impact)| {
-                let values = color_to_impact.values();
-                let priority: Priority = Self::rate_by_impact(values).into();
+                let priority: Priority = self.rate_point(point, color_to_impact.values()).into();
                 (point, priority)
             })
             .collect();
+
+        if let ChoosePixel::Random(seed) = self.choose_pixel {
+            // the order of the hash map is not reproducible
+            points_rate.sort_by_key(|&(point, _rate)| point);
+            // every node of the search gets its own order
+            let mut rng = XorShift::with_seed(seed.wrapping_add(self.nodes_explored as u64));
+            for (_point, rate) in &mut points_rate {
+                *rate = rng.next_f64().into();
+            }
+        }
         points_rate.sort_by_key(|&(point, rate)| (Reverse(rate), point));
         //dbg!(&points_rate[..10]);
 
@@ -367,39 +977,223 @@ where
             .collect()
     }
 
-    const CHOOSE_STRATEGY: ChoosePixel = ChoosePixel::Sqrt;
-
+    /// The higher the rate, the earlier the cell is guessed.
     #[allow(single_use_lifetimes)]
-    fn rate_by_impact<'a>(impact: impl Iterator<Item = &'a (usize, Priority)>) -> f64 {
+    fn rate_point<'a>(
+        &self,
+        point: &Point,
+        impact: impl Iterator<Item = &'a (usize, Priority)>,
+    ) -> f64 {
         let sizes_only: Vec<_> = impact.map(|(new_points, _priority)| *new_points).collect();
+        let impact_rate = self.choose_pixel.rate_impact(&sizes_only);
+
+        match self.choose_pixel {
+            ChoosePixel::MinColors => {
+                let colors = self.board().cell(point).variants().len();
+                // the impact only matters for the cells with the same number of colors
+                #[allow(clippy::cast_precision_loss)]
+                let colors_rate = -(colors as f64);
+                colors_rate + impact_rate / (impact_rate + 1.0)
+            }
+            ChoosePixel::ConstrainedLine => {
+                let board = self.board();
+                board.row_solution_rate(point.y) + board.column_solution_rate(point.x)
+            }
+            _ => impact_rate,
+        }
+    }
 
-        let min = sizes_only.iter().min().unwrap_or(&0);
-        let max = sizes_only.iter().max().unwrap_or(&0);
-        let sum: usize = sizes_only.iter().sum();
+    /// Search from the root of the tree: either on the current thread
+    /// or splitting the directions between the threads.
+    fn search_root(&mut self, directions: Vec<(Point, B::Color)>) -> Result<bool, SolveError> {
+        #[cfg(feature = "threaded")]
+        {
+            if self.threads > 1 && directions.len() > 1 {
+                return self.search_parallel(directions);
+            }
+        }
 
-        let log = |f: f64| f.ln_1p() + 1.0;
+        self.search(directions, &[])
+    }
 
-        #[allow(clippy::cast_precision_loss)]
-        // Max is the most trivial, but also most ineffective strategy.
-        // For details, see https://ieeexplore.ieee.org/document/6476646
-        match Self::CHOOSE_STRATEGY {
-            ChoosePixel::Sum => sum as f64,
-            ChoosePixel::Min => *min as f64,
-            ChoosePixel::Max => *max as f64,
-            ChoosePixel::Mul => sizes_only.iter().map(|x| (x + 1) as f64).product(),
-            ChoosePixel::Sqrt => (*max as f64 / (min + 1) as f64).sqrt() + (*min as f64),
-            ChoosePixel::MinLogm => {
-                let logm: f64 = sizes_only.iter().map(|&x| log(x as f64)).product();
-                logm + (*min as f64)
+    /// Every thread takes the next top level direction and searches it on its own board.
+    /// The dead end directions get excluded from the boards of all the threads.
+    ///
+    /// The callbacks of the board are not called for the guesses made by the threads.
+    #[cfg(feature = "threaded")]
+    fn search_parallel(
+        &mut self,
+        mut directions: Vec<(Point, B::Color)>,
+    ) -> Result<bool, SolveError> {
+        self.depth_reached = self.depth_reached.max(1);
+
+        // pop from the end, so the most prioritized items are on the left
+        directions.reverse();
+        let shared = MutRc::new(SharedSearch {
+            directions,
+            solutions: vec![],
+            excluded: vec![],
+            stopped: false,
+            error: None,
+        });
+
+        let worker: fn(SearchJob<B>) -> (usize, usize) = Self::search_worker;
+        let handles: Vec<_> = (0..self.threads)
+            .map(|i| {
+                let job = SearchJob {
+                    shared: MutRc::clone(&shared),
+                    board: self.board().clone(),
+                    max_solutions: self.max_solutions,
+                    timeout: self.timeout,
+                    max_depth: self.max_depth,
+                    start_time: self.start_time,
+                    choose_pixel: self.choose_pixel,
+                    cancel: self.cancel.clone(),
+                    progress: self.progress.clone(),
+                };
+                thread::Builder::new()
+                    .name(format!("search-{}", i))
+                    .stack_size(SEARCH_STACK_SIZE)
+                    .spawn(move || worker(job))
+                    .expect("Cannot spawn the search thread")
+            })
+            .collect();
+
+        for handle in handles {
+            let (depth_reached, nodes_explored) =
+                handle.join().expect("The search thread panicked");
+            self.depth_reached = self.depth_reached.max(depth_reached);
+            self.nodes_explored += nodes_explored;
+        }
+
+        let (solutions, excluded, error) = {
+            let mut shared = shared.write();
+            (
+                mem::replace(&mut shared.solutions, vec![]),
+                mem::replace(&mut shared.excluded, vec![]),
+                shared.error.take(),
+            )
+        };
+        self.solutions.extend(solutions);
+        if let Some(err) = error {
+            return Err(err);
+        }
+
+        // the root board is not restored in the serial search either
+        self.exclude(excluded)
+    }
+
+    /// The body of the search thread. Return the depth reached and the number of the guesses.
+    #[cfg(feature = "threaded")]
+    fn search_worker(job: SearchJob<B>) -> (usize, usize) {
+        let SearchJob {
+            shared,
+            board,
+            max_solutions,
+            timeout,
+            max_depth,
+            start_time,
+            choose_pixel,
+            cancel,
+            progress,
+        } = job;
+
+        let mut solver = Self::with_options(MutRc::new(board), max_solutions, timeout, max_depth);
+        solver.start_time = start_time;
+        solver.choose_pixel = choose_pixel;
+        solver.set_cancel_token(cancel);
+        solver.progress = progress;
+        solver.shared = Some(MutRc::clone(&shared));
+
+        if let Err(err) = solver.search_shared(&shared) {
+            shared.write().fail(err);
+        }
+        (solver.depth_reached, solver.nodes_explored)
+    }
+
+    #[cfg(feature = "threaded")]
+    fn search_shared(&mut self, shared: &MutRc<SharedSearch<B>>) -> Result<(), SolveError> {
+        let mut excluded_seen = 0;
+
+        loop {
+            if self.limits_reached(0) {
+                return Ok(());
             }
-            ChoosePixel::MinLogd => match sizes_only.as_slice() {
-                [first, second] => {
-                    let diff = log(*first as f64) - log(*second as f64);
-                    *min as f64 + diff.abs()
+
+            let (direction, excluded) = {
+                let mut shared = shared.write();
+                match shared.directions.pop() {
+                    Some(direction) => (direction, shared.excluded[excluded_seen..].to_vec()),
+                    None => return Ok(()),
                 }
-                _other => *min as f64,
-            },
+            };
+            excluded_seen += excluded.len();
+
+            if !self.exclude(excluded)? {
+                info!("All the directions lead to the dead end");
+                shared.write().stopped = true;
+                return Ok(());
+            }
+
+            let (point, color) = direction;
+            let cell_colors = self.board().cell(&point).variants();
+            if !cell_colors.contains(&color) || cell_colors.len() == 1 {
+                info!("The direction {:?} is already solved", direction);
+                continue;
+            }
+
+            info!(
+                "Trying direction {:?} (rate={:.4})",
+                direction,
+                self.board().solution_rate()
+            );
+            let guess_save = self.board().make_snapshot();
+            let state_result = self.try_direction(&[direction]);
+            Board::restore_with_callback(&self.board, guess_save);
+
+            if !state_result? {
+                info!("The direction {:?} is a dead end", direction);
+                shared.write().excluded.push(direction);
+            }
+        }
+    }
+
+    /// Unset the colors that lead to the dead end.
+    /// Return False if the board cannot be solved without them.
+    #[cfg(feature = "threaded")]
+    fn exclude(&mut self, excluded: Vec<(Point, B::Color)>) -> Result<bool, SolveError> {
+        if excluded.is_empty() {
+            return Ok(true);
+        }
+
+        for (point, color) in excluded {
+            if Board::unset_color_with_callback(&self.board, &point, &color).is_err() {
+                return Ok(false);
+            }
+        }
+
+        match self.probe_solver.run_unsolved::<S>() {
+            Err(SolveError::Cancelled) => return Err(SolveError::Cancelled),
+            Err(_) => return Ok(false),
+            Ok(_) => {}
+        }
+
+        if self.is_solved() {
+            self.add_solution();
+        }
+        Ok(true)
+    }
+
+    /// Whether some other thread has stopped the search.
+    fn is_stopped(&self) -> bool {
+        #[cfg(feature = "threaded")]
+        {
+            if let Some(shared) = &self.shared {
+                return shared.read().stopped;
+            }
         }
+
+        false
     }
 
     /// Recursively search for solutions.
@@ -408,7 +1202,7 @@ where
         &mut self,
         directions: Vec<(Point, B::Color)>,
         path: &[(Point, B::Color)],
-    ) -> Result<bool, String> {
+    ) -> Result<bool, SolveError> {
         if self.is_explored(path) {
             return Ok(true);
         }
@@ -435,7 +1229,7 @@ where
         &mut self,
         mut directions: Vec<(Point, B::Color)>,
         path: &[(Point, B::Color)],
-    ) -> Result<bool, String> {
+    ) -> Result<bool, SolveError> {
         let depth = path.len();
         // going to dive deeper, so increment it (full_path's length)
         self.depth_reached = self.depth_reached.max(depth + 1);
@@ -487,6 +1281,9 @@ where
                 let impact = self.probe_solver.run_unsolved::<S>();
                 board_changed = false;
 
+                if let Err(SolveError::Cancelled) = impact {
+                    return Err(SolveError::Cancelled);
+                }
                 if impact.is_err() {
                     // the whole `path` branch of a search tree is a dead end
                     warn!("The last possible color {:?} for the {:?} lead to the contradiction. The path {:?} is invalid", color, point, path);
@@ -556,6 +1353,9 @@ where
 
                 let run_with_new_info = self.probe_solver.run_unsolved::<S>();
                 board_changed = false;
+                if let Err(SolveError::Cancelled) = run_with_new_info {
+                    return Err(SolveError::Cancelled);
+                }
                 if run_with_new_info.is_err() {
                     // the whole `path` branch of a search tree is a dead end
                     info!(
@@ -618,13 +1418,30 @@ where
         }
     }
 
+    /// Add the guesses of the enumeration to the `search_tree` with the score (or as the dead end).
+    fn add_enumeration_path(&mut self, stack: &[SearchFrame<B>], score: Option<f64>) {
+        if log_enabled!(Level::Info) {
+            let path: Vec<_> = stack
+                .iter()
+                .filter_map(|frame| frame.tried.map(|color| (frame.point, color)))
+                .collect();
+            SearchTree::add(MutRc::clone(&self.search_tree), &path, score);
+        }
+    }
+
     /// Trying to search for solutions in the given direction.
     /// At first it set the given state and get a list of the
     /// further jobs for finding the contradictions.
     /// Later that jobs will be used as candidates for a deeper search.
-    fn try_direction(&mut self, path: &[(Point, B::Color)]) -> Result<bool, String> {
+    fn try_direction(&mut self, path: &[(Point, B::Color)]) -> Result<bool, SolveError> {
         let depth = path.len();
         let direction = *path.last().expect("Path should be non-empty");
+        self.nodes_explored += 1;
+        self.progress.report(Progress {
+            depth,
+            nodes_explored: self.nodes_explored,
+            solution_rate: self.board().solution_rate(),
+        });
 
         // add every cell to the jobs queue
         let mut probe_jobs = self.probe_solver.unsolved_cells();
@@ -667,6 +1484,7 @@ where
                     self.search(directions, path)
                 }
             }
+            Err(SolveError::Cancelled) => Err(SolveError::Cancelled),
             Err(err) => {
                 info!("Guess {:?} failed on probing stage: {}", direction, err);
                 self.add_search_deadend(path);
@@ -675,7 +1493,10 @@ where
         }
     }
 
-    fn set_guess(&mut self, guess: (Point, B::Color)) -> Result<Vec<(Point, Priority)>, String> {
+    fn set_guess(
+        &mut self,
+        guess: (Point, B::Color),
+    ) -> Result<Vec<(Point, Priority)>, SolveError> {
         let (point, color) = guess;
 
         if !self.board().cell(&point).variants().contains(&color) {
@@ -690,10 +1511,11 @@ where
             .probe_solver
             .propagate_point::<S>(&point)
             .map_err(|err| {
-                format!(
-                    "Error while propagating value {:?} in {:?}: {:?}",
+                info!(
+                    "Error while propagating value {:?} in {:?}: {}",
                     &color, &point, err
-                )
+                );
+                err
             })?;
 
         if self.board().is_solved_full() {
@@ -709,8 +1531,12 @@ where
     /// 2) the maximum allowed run time
     /// 3) the maximum depth
     fn limits_reached(&self, depth: usize) -> bool {
+        if self.is_stopped() || self.cancel.is_cancelled() {
+            return true;
+        }
+
         if let Some(max_solutions) = self.max_solutions {
-            let solutions_number = self.solutions.len();
+            let solutions_number = self.solutions.len() + self.solutions_counted;
             if solutions_number >= max_solutions {
                 if depth == 0 {
                     // only show log on the most top level
@@ -748,3 +1574,197 @@ where
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block::binary::BinaryBlock,
+        solver::{
+            line::DynamicSolver,
+            probing::FullProbe1,
+            progress::{CancelToken, Progress},
+            SolveError,
+        },
+        test_utils::{permutations, propagated},
+        utils::rc::MutRc,
+    };
+
+    use super::{ChoosePixel, Solver};
+
+    type TestSolver = Solver<BinaryBlock, FullProbe1<BinaryBlock>, DynamicSolver<BinaryBlock>>;
+
+    #[test]
+    fn all_solutions() {
+        let mut solver = TestSolver::with_options(permutations(3), None, None, None);
+        solver.run().unwrap();
+        assert_eq!(solver.solutions.len(), 6);
+        assert!(solver.nodes_explored() > 0);
+    }
+
+    #[test]
+    fn max_solutions() {
+        let mut solver = TestSolver::with_options(permutations(3), Some(2), None, None);
+        solver.run().unwrap();
+        assert_eq!(solver.solutions.len(), 2);
+    }
+
+    #[test]
+    fn choose_pixel_names() {
+        for name in ChoosePixel::variants() {
+            let strategy: ChoosePixel = name.parse().unwrap();
+            assert_eq!(strategy.to_string(), name);
+        }
+
+        assert_eq!("random:42".parse(), Ok(ChoosePixel::Random(42)));
+        assert_eq!(ChoosePixel::Random(42).to_string(), "random:42");
+        assert!("random:x".parse::<ChoosePixel>().is_err());
+        assert!("sqrt:42".parse::<ChoosePixel>().is_err());
+        assert!("unknown".parse::<ChoosePixel>().is_err());
+    }
+
+    #[test]
+    fn every_strategy_finds_all_solutions() {
+        let strategies = ChoosePixel::variants()
+            .map(|name| name.parse().unwrap())
+            .chain(Some(ChoosePixel::Random(7)));
+        for strategy in strategies {
+            let mut solver = TestSolver::with_options(permutations(3), None, None, None);
+            solver.set_choose_pixel(strategy);
+            solver.run().unwrap();
+            assert_eq!(solver.solutions.len(), 6, "{}", strategy);
+        }
+    }
+
+    #[test]
+    fn lazy_solutions() {
+        let board = permutations(3);
+        let before = board.read().make_snapshot();
+        let mut solutions = TestSolver::with_options(MutRc::clone(&board), None, None, None)
+            .into_solutions()
+            .unwrap();
+
+        let mut found = vec![];
+        for solution in &mut solutions {
+            assert!(!found.contains(&solution));
+            found.push(solution);
+            assert_eq!(board.read().make_snapshot(), before);
+        }
+        assert_eq!(found.len(), 6);
+        assert!(solutions.error().is_none());
+    }
+
+    #[test]
+    fn lazy_solutions_stop_early() {
+        let mut all = TestSolver::with_options(permutations(4), None, None, None)
+            .into_solutions()
+            .unwrap();
+        assert_eq!(all.by_ref().count(), 24);
+
+        let mut first = TestSolver::with_options(permutations(4), None, None, None)
+            .into_solutions()
+            .unwrap();
+        assert_eq!(first.by_ref().take(2).count(), 2);
+        assert!(first.solver().nodes_explored() < all.solver().nodes_explored());
+    }
+
+    #[test]
+    fn lazy_solutions_cancelled() {
+        let mut solver = TestSolver::with_options(permutations(5), None, None, None);
+        let token = CancelToken::new();
+        solver.set_cancel_token(token.clone());
+        solver.set_progress_callback(move |report: Progress| {
+            if report.nodes_explored >= 3 {
+                token.cancel();
+            }
+        });
+
+        let mut solutions = solver.into_solutions().unwrap();
+        assert!(solutions.by_ref().count() < 120);
+        assert_eq!(solutions.error(), Some(&SolveError::Cancelled));
+    }
+
+    #[test]
+    fn lazy_solutions_without_search() {
+        let board = propagated(
+            &[&[1, 1], &[2], &[2], &[1, 2], &[1]],
+            &[&[1], &[1, 1], &[1], &[3], &[3]],
+        );
+        let mut solutions = TestSolver::with_options(board, None, None, None)
+            .into_solutions()
+            .unwrap();
+        assert!(solutions.next().is_some());
+        assert!(solutions.next().is_none());
+        assert_eq!(solutions.solver().nodes_explored(), 0);
+    }
+
+    #[test]
+    fn cancelled_before_start() {
+        let mut solver = TestSolver::with_options(permutations(3), None, None, None);
+        let token = CancelToken::new();
+        solver.set_cancel_token(token.clone());
+        token.cancel();
+
+        assert_eq!(solver.run(), Err(SolveError::Cancelled));
+        assert_eq!(solver.nodes_explored(), 0);
+    }
+
+    #[test]
+    fn cancel_from_progress() {
+        let mut solver = TestSolver::with_options(permutations(5), None, None, None);
+        let token = CancelToken::new();
+        solver.set_cancel_token(token.clone());
+
+        let reports = MutRc::new(vec![]);
+        let progress = MutRc::clone(&reports);
+        solver.set_progress_callback(move |report: Progress| {
+            progress.write().push(report);
+            if report.nodes_explored >= 3 {
+                token.cancel();
+            }
+        });
+
+        match solver.run() {
+            // the solutions found before the cancellation are kept
+            Ok(()) => assert!(!solver.solutions.is_empty()),
+            Err(err) => assert_eq!(err, SolveError::Cancelled),
+        }
+        assert_eq!(solver.exceeded_limit(), Some(SolveError::Cancelled));
+        assert_eq!(solver.nodes_explored(), 3);
+
+        let reports = reports.read();
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[0].nodes_explored, 1);
+        assert_eq!(reports[0].depth, 1);
+        assert!(reports
+            .iter()
+            .all(|report| (0.0..1.0).contains(&report.solution_rate)));
+    }
+
+    #[cfg(feature = "threaded")]
+    #[test]
+    fn parallel_same_solutions() {
+        let mut serial = TestSolver::with_options(permutations(3), None, None, None);
+        serial.threads = 1;
+        serial.run().unwrap();
+
+        let mut parallel = TestSolver::with_options(permutations(3), None, None, None);
+        parallel.threads = 3;
+        parallel.run().unwrap();
+
+        assert_eq!(parallel.solutions.len(), serial.solutions.len());
+        assert!(serial
+            .solutions
+            .iter()
+            .all(|solution| parallel.solutions.contains(solution)));
+        assert!(parallel.nodes_explored() > 0);
+    }
+
+    #[cfg(feature = "threaded")]
+    #[test]
+    fn parallel_stops_on_max_solutions() {
+        let mut solver = TestSolver::with_options(permutations(4), Some(3), None, None);
+        solver.threads = 4;
+        solver.run().unwrap();
+        assert_eq!(solver.solutions.len(), 3);
+    }
+}
@@ -0,0 +1,189 @@
+use std::fmt;
+
+use log::warn;
+
+use crate::{
+    block::Block,
+    board::Board,
+    solver::{
+        backtracking::{self, SearchOptions},
+        line::LineSolver,
+        probing::{ProbeSolver, ProbeStats},
+        propagate, SolveError,
+    },
+    utils::rc::MutRc,
+};
+
+/// The simplest stage of the solver that is enough to solve the puzzle
+/// (the categories of the [webpbn survey](https://webpbn.com/survey/)).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DifficultyLevel {
+    /// The line propagation alone solves the puzzle.
+    LineSolvable,
+    /// The probing (trying every color of the cell) solves the puzzle.
+    ProbeSolvable,
+    /// The puzzle requires guessing.
+    NeedsSearch,
+}
+
+impl fmt::Display for DifficultyLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::LineSolvable => "line solvable",
+            Self::ProbeSolvable => "probe solvable",
+            Self::NeedsSearch => "needs search",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How much effort every stage of the solver spent on the puzzle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Difficulty {
+    pub level: DifficultyLevel,
+    /// The probing stage (before any guessing).
+    pub probing: ProbeStats,
+    /// The maximum depth of the search tree.
+    pub search_depth: usize,
+    /// The number of guesses made while searching.
+    pub search_nodes: usize,
+}
+
+impl Difficulty {
+    fn with_level(level: DifficultyLevel) -> Self {
+        Self {
+            level,
+            probing: ProbeStats::default(),
+            search_depth: 0,
+            search_nodes: 0,
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (probes: {}, contradictions: {}, search depth: {}, search nodes: {})",
+            self.level,
+            self.probing.probes,
+            self.probing.contradictions,
+            self.search_depth,
+            self.search_nodes
+        )
+    }
+}
+
+/// Solve the board by stages, stopping at the first one that solves it,
+/// and report the work done by every stage.
+///
+/// The search stops after the first solution found.
+pub fn rate_difficulty<B, S, P>(
+    board: MutRc<Board<B>>,
+    timeout: Option<u32>,
+    max_depth: Option<usize>,
+    options: SearchOptions,
+) -> Result<Difficulty, SolveError>
+where
+    B: Block,
+    S: LineSolver<BlockType = B>,
+    P: ProbeSolver<BlockType = B>,
+{
+    if propagate::<_, S>(&board)? {
+        return Ok(Difficulty::with_level(DifficultyLevel::LineSolvable));
+    }
+
+    warn!(
+        "Solving with probing (solved on {:.3}%)",
+        board.read().solution_rate() * 100.0
+    );
+    let mut probe_solver = P::with_board(MutRc::clone(&board));
+    let _impact = probe_solver.run_unsolved::<S>()?;
+    let probing = probe_solver.stats();
+
+    if board.read().is_solved_full() {
+        return Ok(Difficulty {
+            probing,
+            ..Difficulty::with_level(DifficultyLevel::ProbeSolvable)
+        });
+    }
+
+    let mut solver =
+        backtracking::Solver::<_, P, S>::with_options(board, Some(1), timeout, max_depth);
+    solver.set_search_options(options);
+    solver.run()?;
+    if solver.solutions.is_empty() {
+        return Err(solver.exceeded_limit().unwrap_or(SolveError::NoSolutions));
+    }
+
+    Ok(Difficulty {
+        level: DifficultyLevel::NeedsSearch,
+        probing,
+        search_depth: solver.depth_reached(),
+        search_nodes: solver.nodes_explored(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block::{binary::BinaryBlock, Description},
+        board::Board,
+        solver::{backtracking::SearchOptions, line::DynamicSolver, probing::FullProbe1},
+        utils::rc::MutRc,
+    };
+
+    use super::{rate_difficulty, Difficulty, DifficultyLevel};
+
+    fn rate(rows: &[&[usize]], columns: &[&[usize]]) -> Difficulty {
+        let desc = |lines: &[&[usize]]| {
+            lines
+                .iter()
+                .map(|line| Description::new(line.iter().map(|&n| BinaryBlock(n)).collect()))
+                .collect()
+        };
+        let board = Board::with_descriptions_and_palette(desc(rows), desc(columns), None);
+        rate_difficulty::<_, DynamicSolver<_>, FullProbe1<_>>(
+            MutRc::new(board),
+            None,
+            None,
+            SearchOptions::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn line_solvable() {
+        let difficulty = rate(&[&[1, 1], &[1, 1], &[3]], &[&[3], &[1], &[3]]);
+        assert_eq!(difficulty.level, DifficultyLevel::LineSolvable);
+        assert_eq!(difficulty.probing.probes, 0);
+        assert_eq!(difficulty.search_nodes, 0);
+    }
+
+    #[test]
+    fn probe_solvable() {
+        let difficulty = rate(
+            &[&[1, 1], &[2], &[2], &[1, 2], &[1]],
+            &[&[1], &[1, 1], &[1], &[3], &[3]],
+        );
+        assert_eq!(difficulty.level, DifficultyLevel::ProbeSolvable);
+        assert!(difficulty.probing.contradictions > 0);
+        assert_eq!(difficulty.search_nodes, 0);
+    }
+
+    #[test]
+    fn needs_search() {
+        let difficulty = rate(&[&[1], &[1]], &[&[1], &[1]]);
+        assert_eq!(difficulty.level, DifficultyLevel::NeedsSearch);
+        assert!(difficulty.probing.probes > 0);
+        assert_eq!(difficulty.probing.contradictions, 0);
+        assert!(difficulty.search_nodes > 0);
+        assert!(difficulty.search_depth > 0);
+    }
+
+    #[test]
+    fn levels_ordered_by_hardness() {
+        assert!(DifficultyLevel::LineSolvable < DifficultyLevel::ProbeSolvable);
+        assert!(DifficultyLevel::ProbeSolvable < DifficultyLevel::NeedsSearch);
+    }
+}
@@ -0,0 +1,63 @@
+use std::{error::Error, fmt};
+
+use crate::{
+    board::{LinePosition, Point},
+    solver::Backend,
+};
+
+/// The reasons why the puzzle cannot be (fully) solved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveError {
+    /// The clues of the line cannot fit the current state of its cells.
+    LineContradiction(LinePosition),
+    /// No more colors left for the cell.
+    CellContradiction(Point),
+    /// The search explored every possibility without finding any solution.
+    NoSolutions,
+    /// The search stopped after the specified number of seconds without finding any solution.
+    Timeout(u32),
+    /// The search stopped on the specified depth without finding any solution.
+    DepthLimit(usize),
+    /// The solver was stopped with the [`CancelToken`](crate::solver::progress::CancelToken).
+    Cancelled,
+    /// The clues cannot describe any board of the given size.
+    InvalidClues(String),
+    /// The clues do not correspond to the colors of the puzzle.
+    InconsistentPalette(String),
+    /// The search backend was not compiled in.
+    BackendUnavailable(Backend),
+}
+
+impl SolveError {
+    /// Whether the error proves that the puzzle has no solutions.
+    pub fn is_contradiction(&self) -> bool {
+        match self {
+            Self::LineContradiction(_) | Self::CellContradiction(_) | Self::NoSolutions => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LineContradiction(position) => write!(f, "Contradiction in {:?}", position),
+            Self::CellContradiction(point) => {
+                write!(f, "Contradiction in cell ({}, {})", point.x, point.y)
+            }
+            Self::NoSolutions => write!(f, "No solutions found"),
+            Self::Timeout(seconds) => write!(f, "No solutions found in {} seconds", seconds),
+            Self::DepthLimit(depth) => write!(f, "No solutions found up to depth {}", depth),
+            Self::Cancelled => write!(f, "The solving was cancelled"),
+            Self::InvalidClues(msg) => write!(f, "Invalid clues: {}", msg),
+            Self::InconsistentPalette(msg) => write!(f, "Inconsistent palette: {}", msg),
+            Self::BackendUnavailable(backend) => write!(
+                f,
+                "The {} backend is not available (hint: add --features={})",
+                backend, backend
+            ),
+        }
+    }
+}
+
+impl Error for SolveError {}
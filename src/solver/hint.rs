@@ -0,0 +1,262 @@
+use log::debug;
+
+use crate::{
+    block::{Block, Color},
+    board::{Board, LinePosition, Point},
+    solver::{
+        line::{self, LineSolver},
+        probing::ProbeSolver,
+        propagation, SolveError,
+    },
+    utils::rc::{MutRc, ReadRc},
+};
+
+/// The single logical step that brings new information about the board.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Hint<C>
+where
+    C: Color,
+{
+    /// The clues of the line alone force the colors of the `cells`.
+    Line {
+        position: LinePosition,
+        cells: Vec<(Point, C)>,
+    },
+    /// Putting the `color` into the `point` leads to the `contradiction`,
+    /// so the cell cannot have that color, which in turn forces the colors of the `cells`.
+    Probe {
+        point: Point,
+        color: C,
+        contradiction: SolveError,
+        cells: Vec<(Point, C)>,
+    },
+}
+
+impl<C> Hint<C>
+where
+    C: Color,
+{
+    /// The cells that get (partially) solved by the hint with their new colors.
+    pub fn cells(&self) -> &[(Point, C)] {
+        match self {
+            Self::Line { cells, .. } | Self::Probe { cells, .. } => cells,
+        }
+    }
+
+    /// Whether the hint requires the probing (more complex reasoning than a single line).
+    pub fn is_probe(&self) -> bool {
+        if let Self::Probe { .. } = self {
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Find the next deduction a human can make on the (partially solved) board.
+///
+/// The line that gives the most new cells is preferred.
+/// Only if no single line gives any progress, the probing used to find the contradiction.
+/// The board stays the same after the call.
+///
+/// Return `None` if the board is solved or cannot be solved without guessing.
+/// Return the error if the current state of the board is already contradictory.
+pub fn next_hint<B, S, P>(board: &MutRc<Board<B>>) -> Result<Option<Hint<B::Color>>, SolveError>
+where
+    B: Block,
+    S: LineSolver<BlockType = B>,
+    P: ProbeSolver<BlockType = B>,
+{
+    if let Some(hint) = line_hint::<_, S>(&board.read())? {
+        return Ok(Some(hint));
+    }
+
+    if board.read().is_solved_full() {
+        return Ok(None);
+    }
+
+    probe_hint::<_, S, P>(board)
+}
+
+fn line_hint<B, S>(board: &Board<B>) -> Result<Option<Hint<B::Color>>, SolveError>
+where
+    B: Block,
+    S: LineSolver<BlockType = B>,
+{
+    let positions = (0..board.height())
+        .map(LinePosition::Row)
+        .chain((0..board.width()).map(LinePosition::Column));
+
+    let mut best_hint: Option<Hint<B::Color>> = None;
+    for position in positions {
+        let line = board.get_line(position);
+        let solved = line::solve::<S, _>(board.description(position), ReadRc::clone(&line))
+            .map_err(|_| SolveError::LineContradiction(position))?;
+
+        let cells: Vec<_> = line
+            .iter()
+            .zip(solved.iter())
+            .enumerate()
+            .filter_map(|(offset, (old, &new))| {
+                if *old == new {
+                    None
+                } else {
+                    Some((Point::with_line_and_offset(position, offset), new))
+                }
+            })
+            .collect();
+
+        let best_size = best_hint.as_ref().map_or(0, |hint| hint.cells().len());
+        if cells.len() > best_size {
+            best_hint = Some(Hint::Line { position, cells });
+        }
+    }
+
+    Ok(best_hint)
+}
+
+fn probe_hint<B, S, P>(board: &MutRc<Board<B>>) -> Result<Option<Hint<B::Color>>, SolveError>
+where
+    B: Block,
+    S: LineSolver<BlockType = B>,
+    P: ProbeSolver<BlockType = B>,
+{
+    let mut probes = P::with_board(MutRc::clone(board)).unsolved_cells();
+    let mut propagation = propagation::Solver::new(MutRc::clone(board));
+    let save = board.read().make_snapshot();
+
+    while let Some((point, _priority)) = probes.pop() {
+        let colors = board.read().cell(&point).variants();
+        for color in colors {
+            Board::set_color_with_callback(board, &point, &color);
+            let assumption = propagation.run::<S>(Some(point));
+            Board::restore_with_callback(board, save.clone());
+
+            if let Err(contradiction) = assumption {
+                debug!(
+                    "The color {:?} for {:?} leads to contradiction: {}",
+                    color, point, contradiction
+                );
+
+                let forced = Board::unset_color_with_callback(board, &point, &color)
+                    .and_then(|()| propagation.run::<S>(Some(point)));
+                let cells = {
+                    let board = board.read();
+                    board
+                        .diff(&save)
+                        .into_iter()
+                        .map(|point| (point, board.cell(&point)))
+                        .collect()
+                };
+                Board::restore_with_callback(board, save);
+                let _ = forced?;
+
+                return Ok(Some(Hint::Probe {
+                    point,
+                    color,
+                    contradiction,
+                    cells,
+                }));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block::{binary::BinaryBlock, binary::BinaryColor},
+        board::{Board, LinePosition, Point},
+        solver::{line::DynamicSolver, probing::FullProbe1, SolveError},
+        test_utils::board,
+        utils::rc::MutRc,
+    };
+
+    use super::{next_hint, Hint};
+
+    fn hint(board: &MutRc<Board<BinaryBlock>>) -> Option<Hint<BinaryColor>> {
+        next_hint::<_, DynamicSolver<_>, FullProbe1<_>>(board).unwrap()
+    }
+
+    /// Apply the hints one by one until the board gets solved.
+    fn follow_hints(board: &MutRc<Board<BinaryBlock>>) -> Vec<Hint<BinaryColor>> {
+        let mut hints = vec![];
+        while let Some(hint) = hint(board) {
+            for (point, color) in hint.cells() {
+                Board::set_color_with_callback(board, point, color);
+            }
+            hints.push(hint);
+        }
+        hints
+    }
+
+    #[test]
+    fn line_with_the_most_cells() {
+        let board = board(&[&[1, 1], &[1, 1], &[3]], &[&[3], &[1], &[3]]);
+        let snapshot = board.read().make_snapshot();
+
+        match hint(&board).unwrap() {
+            Hint::Line { position, cells } => {
+                // the first of the completely solvable lines
+                assert_eq!(position, LinePosition::Row(0));
+                assert_eq!(
+                    cells,
+                    vec![
+                        (Point::new(0, 0), BinaryColor::Black),
+                        (Point::new(1, 0), BinaryColor::White),
+                        (Point::new(2, 0), BinaryColor::Black),
+                    ]
+                );
+            }
+            other => panic!("Expected line hint, got {:?}", other),
+        }
+        assert!(!board.read().differs(&snapshot));
+    }
+
+    #[test]
+    fn line_solvable_needs_no_probes() {
+        let board = board(&[&[1, 1], &[1, 1], &[3]], &[&[3], &[1], &[3]]);
+        let hints = follow_hints(&board);
+        assert!(board.read().is_solved_full());
+        assert!(hints.iter().all(|hint| !hint.is_probe()));
+    }
+
+    #[test]
+    fn probe_when_lines_are_stuck() {
+        let board = board(
+            &[&[1, 1], &[2], &[2], &[1, 2], &[1]],
+            &[&[1], &[1, 1], &[1], &[3], &[3]],
+        );
+        let hints = follow_hints(&board);
+        assert!(board.read().is_solved_full());
+
+        let probe = hints
+            .iter()
+            .find(|hint| hint.is_probe())
+            .expect("The puzzle is not line solvable");
+        if let Hint::Probe { point, cells, .. } = probe {
+            assert!(cells.iter().any(|(cell, _color)| cell == point));
+        }
+    }
+
+    #[test]
+    fn no_hint_without_guessing() {
+        let board = board(&[&[1], &[1]], &[&[1], &[1]]);
+        assert!(hint(&board).is_none());
+        assert!(!board.read().is_solved_full());
+    }
+
+    #[test]
+    fn mistake_on_the_board() {
+        let board = board(&[&[1, 1], &[1, 1], &[3]], &[&[3], &[1], &[3]]);
+        Board::set_color_with_callback(&board, &Point::new(1, 0), &BinaryColor::Black);
+
+        let err = next_hint::<_, DynamicSolver<_>, FullProbe1<_>>(&board).unwrap_err();
+        assert!(err.is_contradiction());
+        if let SolveError::LineContradiction(position) = err {
+            assert!(position == LinePosition::Row(0) || position == LinePosition::Column(1));
+        }
+    }
+}
@@ -2,16 +2,23 @@ use std::iter::once;
 
 use crate::{
     block::{
-        base::color::ColorPalette, binary::BinaryColor, multicolor::MultiColor, Block, Color,
-        Description, Line,
+        binary::{BinaryBlock, BinaryColor},
+        multicolor::{MultiColor, WideColor},
+        Block, Color, Description, Line,
     },
     utils::{self, rc::ReadRc},
 };
 
+pub(crate) use self::blotted::Automaton;
+
+mod blotted;
+
 type LineColor<T> = <<T as LineSolver>::BlockType as Block>::Color;
 
 pub trait LineSolver {
     type BlockType: Block;
+    /// The same solver for the black-and-white board the single colored one gets downgraded to.
+    type Binary: 'static + LineSolver<BlockType = BinaryBlock>;
 
     fn new(desc: ReadRc<Description<Self::BlockType>>, line: Line<LineColor<Self>>) -> Self;
     fn solve(&mut self) -> Result<(), UnsolvableLine>;
@@ -62,6 +69,7 @@ where
     B::Color: DynamicColor,
 {
     type BlockType = B;
+    type Binary = DynamicSolver<BinaryBlock>;
 
     fn new(desc: ReadRc<Description<B>>, line: Line<B::Color>) -> Self {
         let block_sums = Self::calc_block_sum(&desc);
@@ -119,6 +127,11 @@ where
             return true;
         }
 
+        if self.desc.is_blotted() {
+            let automaton = Automaton::with_description(&self.desc);
+            return automaton.solve(&self.line, &mut self.solved_line);
+        }
+
         let (position, block) = (self.line.len() - 1, self.desc.vec.len());
         self.solve_block(position as isize, block)
     }
@@ -323,7 +336,29 @@ impl DynamicColor for MultiColor {
     }
 
     fn can_be_blank(&self) -> bool {
-        (self.0 & ColorPalette::WHITE_ID) == ColorPalette::WHITE_ID
+        self.can_be(Self::blank())
+    }
+
+    fn can_be(self, color: Self) -> bool {
+        (self.0 & color.0) != 0
+    }
+
+    fn add_color(self, color: Self) -> Self {
+        Self(self.0 | color.0)
+    }
+
+    fn solved_copy(&self) -> Self {
+        Self(0)
+    }
+}
+
+impl DynamicColor for WideColor {
+    fn both_colors() -> Option<Self> {
+        None
+    }
+
+    fn can_be_blank(&self) -> bool {
+        self.can_be(Self::blank())
     }
 
     fn can_be(self, color: Self) -> bool {
@@ -458,7 +493,10 @@ mod tests {
 
 #[cfg(test)]
 mod tests_solve_color {
-    use crate::block::{base::color::ColorId, multicolor::ColoredBlock};
+    use crate::block::{
+        base::color::{ColorId, ColorPalette},
+        multicolor::ColoredBlock,
+    };
 
     use super::*;
 
@@ -471,7 +509,9 @@ mod tests_solve_color {
     }
 
     fn id_to_color_line(line: &[ColorId]) -> ReadRc<[MultiColor]> {
-        line.iter().cloned().map(MultiColor).collect()
+        line.iter()
+            .map(|&id| MultiColor::from_color_ids(&[id]))
+            .collect()
     }
 
     fn desc_from_slice(desc: &[ColoredBlock]) -> ReadRc<Description<ColoredBlock>> {
@@ -0,0 +1,312 @@
+//! The lines with the blotted clues have unknown number of blocks,
+//! so the clue is turned into the automaton reading the cells one by one.
+
+use crate::block::{Block, Clue, Color, Description};
+
+use super::DynamicColor;
+
+/// The nondeterministic finite automaton accepting every line that fits the clue.
+#[derive(Debug, Clone)]
+pub(crate) struct Automaton<C> {
+    /// For every state: the color of the next cell and the state to move into.
+    transitions: Vec<Vec<(C, usize)>>,
+    initial: usize,
+    accepting: Vec<bool>,
+}
+
+impl<C> Automaton<C>
+where
+    C: Color,
+{
+    pub fn with_description<B>(desc: &Description<B>) -> Self
+    where
+        B: Block<Color = C>,
+    {
+        let blank = C::blank();
+        let mut this = Self {
+            transitions: vec![],
+            initial: 0,
+            accepting: vec![],
+        };
+        // the moves without reading a cell
+        let mut skips = vec![];
+
+        // the previous cell is blank (or the line just started)
+        let mut gap = this.add_state(&mut skips);
+        this.add_transition(gap, blank, gap);
+        // the previous cell has the given color
+        let mut ended: Vec<(C, usize)> = vec![];
+
+        for clue in desc.clues() {
+            let color = clue.color();
+            // the first cell of the block
+            let start = this.add_state(&mut skips);
+            this.add_transition(gap, color, start);
+            for &(previous, state) in &ended {
+                // the blocks of the same color are separated with the space
+                if previous != color {
+                    this.add_transition(state, color, start);
+                }
+            }
+
+            let next_gap = this.add_state(&mut skips);
+            this.add_transition(next_gap, blank, next_gap);
+
+            match clue {
+                Clue::Block(block) => {
+                    let mut last = start;
+                    for _ in 1..block.size() {
+                        let next = this.add_state(&mut skips);
+                        this.add_transition(last, color, next);
+                        last = next;
+                    }
+
+                    this.add_transition(last, blank, next_gap);
+                    ended = vec![(color, last)];
+                }
+                Clue::Blot(_) => {
+                    // any number of blocks of any size
+                    skips[gap].push(next_gap);
+                    this.add_transition(start, color, start);
+                    this.add_transition(start, blank, next_gap);
+                    this.add_transition(next_gap, color, start);
+                    ended.push((color, start));
+                }
+            }
+            gap = next_gap;
+        }
+
+        this.accepting[gap] = true;
+        for (_, state) in ended {
+            this.accepting[state] = true;
+        }
+        this.apply_skips(&skips);
+        this
+    }
+
+    fn add_state(&mut self, skips: &mut Vec<Vec<usize>>) -> usize {
+        self.transitions.push(vec![]);
+        self.accepting.push(false);
+        skips.push(vec![]);
+        self.transitions.len() - 1
+    }
+
+    fn add_transition(&mut self, from: usize, color: C, to: usize) {
+        self.transitions[from].push((color, to));
+    }
+
+    /// Let every state do whatever the states it can skip to do.
+    fn apply_skips(&mut self, skips: &[Vec<usize>]) {
+        // the skips only lead to the later states
+        for state in (0..self.states()).rev() {
+            for &skip in &skips[state] {
+                let transitions = self.transitions[skip].clone();
+                self.transitions[state].extend(transitions);
+                if self.accepting[skip] {
+                    self.accepting[state] = true;
+                }
+            }
+        }
+    }
+
+    pub fn states(&self) -> usize {
+        self.transitions.len()
+    }
+
+    pub fn transitions(&self, state: usize) -> &[(C, usize)] {
+        &self.transitions[state]
+    }
+
+    /// The states the automaton can get into after reading every prefix of the line.
+    pub fn reachable<F>(&self, length: usize, can_be: F) -> Vec<Vec<bool>>
+    where
+        F: Fn(usize, C) -> bool,
+    {
+        let mut reachable = vec![vec![false; self.states()]; length + 1];
+        reachable[0][self.initial] = true;
+
+        for position in 0..length {
+            let mut next = vec![false; self.states()];
+            for (state, _) in reachable[position].iter().enumerate().filter(|(_, &r)| r) {
+                for &(color, to) in self.transitions(state) {
+                    if can_be(position, color) {
+                        next[to] = true;
+                    }
+                }
+            }
+            reachable[position + 1] = next;
+        }
+        reachable
+    }
+
+    /// The states the automaton can read the rest of the line from
+    /// and finish in the accepting state.
+    pub fn finishing<F>(&self, length: usize, can_be: F) -> Vec<Vec<bool>>
+    where
+        F: Fn(usize, C) -> bool,
+    {
+        let mut finishing = vec![vec![false; self.states()]; length + 1];
+        finishing[length] = self.accepting.clone();
+
+        for position in (0..length).rev() {
+            let current = (0..self.states())
+                .map(|state| {
+                    self.transitions(state)
+                        .iter()
+                        .any(|&(color, to)| can_be(position, color) && finishing[position + 1][to])
+                })
+                .collect();
+            finishing[position] = current;
+        }
+        finishing
+    }
+}
+
+impl<C> Automaton<C>
+where
+    C: DynamicColor,
+{
+    /// Add every color the cell can have in any fitting line to the `solved` line.
+    ///
+    /// Return `false` if no line fits.
+    pub fn solve(&self, line: &[C], solved: &mut [C]) -> bool {
+        let can_be = |position: usize, color: C| {
+            let cell = &line[position];
+            if color == C::blank() {
+                cell.can_be_blank()
+            } else {
+                cell.can_be(color)
+            }
+        };
+
+        let finishing = self.finishing(line.len(), can_be);
+        if !finishing[0][self.initial] {
+            return false;
+        }
+
+        let reachable = self.reachable(line.len(), can_be);
+        for (position, cell) in solved.iter_mut().enumerate() {
+            for (state, _) in reachable[position].iter().enumerate().filter(|(_, &r)| r) {
+                for &(color, to) in self.transitions(state) {
+                    if can_be(position, color) && finishing[position + 1][to] {
+                        *cell = cell.add_color(color);
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block::{
+            binary::{
+                BinaryBlock,
+                BinaryColor::{self, Black, Undefined, White},
+            },
+            multicolor::{ColoredBlock, MultiColor},
+            Clue, Description,
+        },
+        solver::line::{solve, DynamicSolver},
+        utils::rc::ReadRc,
+    };
+
+    fn solve_binary(
+        clues: Vec<Clue<BinaryBlock>>,
+        line: Vec<BinaryColor>,
+    ) -> Option<Vec<BinaryColor>> {
+        let desc = ReadRc::new(Description::with_clues(clues));
+        solve::<DynamicSolver<_>, _>(desc, line.into())
+            .ok()
+            .map(|solved| solved.to_vec())
+    }
+
+    fn block(size: usize) -> Clue<BinaryBlock> {
+        Clue::Block(BinaryBlock(size))
+    }
+
+    const BLOT: Clue<BinaryBlock> = Clue::Blot(Black);
+
+    #[test]
+    fn single_blot() {
+        assert_eq!(
+            solve_binary(vec![BLOT], vec![Undefined; 3]),
+            Some(vec![Undefined; 3])
+        );
+        assert_eq!(
+            solve_binary(vec![BLOT], vec![Black, Undefined, Black]),
+            Some(vec![Black, Undefined, Black])
+        );
+    }
+
+    #[test]
+    fn no_space_for_blot() {
+        assert_eq!(
+            solve_binary(vec![block(1), BLOT, block(1)], vec![Undefined; 3]),
+            Some(vec![Black, White, Black])
+        );
+        assert_eq!(
+            solve_binary(vec![block(3), BLOT], vec![Undefined; 4]),
+            Some(vec![Undefined, Black, Black, Undefined])
+        );
+    }
+
+    #[test]
+    fn blot_with_blocks() {
+        assert_eq!(
+            solve_binary(vec![block(1), BLOT], vec![Black, Black, Undefined]),
+            None
+        );
+        assert_eq!(
+            solve_binary(
+                vec![BLOT, block(2)],
+                vec![Black, Undefined, Undefined, Undefined, Black]
+            ),
+            Some(vec![Black, Undefined, White, Black, Black])
+        );
+    }
+
+    #[test]
+    fn colored_blot() {
+        let (white, red, blue) = (1, 4, 8);
+        let desc = ReadRc::new(Description::with_clues(vec![
+            Clue::Block(ColoredBlock::from_size_and_color(1, blue)),
+            Clue::Blot(MultiColor(red)),
+            Clue::Block(ColoredBlock::from_size_and_color(1, blue)),
+        ]));
+
+        let line = vec![MultiColor(white | red | blue); 3];
+        let solved = solve::<DynamicSolver<_>, _>(desc, line.into()).unwrap();
+        assert_eq!(
+            solved.to_vec(),
+            vec![MultiColor(blue), MultiColor(white | red), MultiColor(blue)]
+        );
+    }
+
+    #[test]
+    fn blots_keep_order() {
+        let (white, red, blue) = (1, 2, 4);
+        let desc = ReadRc::new(Description::<ColoredBlock>::with_clues(vec![
+            Clue::Blot(MultiColor(red)),
+            Clue::Blot(MultiColor(blue)),
+        ]));
+
+        let line: Vec<_> = [red | blue, white | blue, white | red, red, red | blue]
+            .iter()
+            .map(|&colors| MultiColor(colors))
+            .collect();
+        let solved = solve::<DynamicSolver<_>, _>(desc, line.into()).unwrap();
+        assert_eq!(
+            solved.to_vec(),
+            vec![
+                MultiColor(red),
+                MultiColor(white),
+                MultiColor(white | red),
+                MultiColor(red),
+                MultiColor(red | blue)
+            ]
+        );
+    }
+}
@@ -1,4 +1,6 @@
 use std::env;
+#[cfg(feature = "threaded")]
+use std::{sync::mpsc, thread};
 
 use hashbrown::hash_map::DefaultHashBuilder;
 use log::{debug, info};
@@ -6,12 +8,9 @@ use ordered_float::OrderedFloat;
 use priority_queue::PriorityQueue as PQ;
 
 use crate::{
-    block::{Block, Color},
+    block::{binary::BinaryBlock, Block, Color},
     board::{Board, Point},
-    solver::{
-        line::{LineSolver, UnsolvableLine},
-        propagation,
-    },
+    solver::{line::LineSolver, progress::CancelToken, propagation, SolveError},
     utils::{
         iter::PartialEntry,
         rc::{MutRc, ReadRef},
@@ -56,33 +55,54 @@ impl From<f64> for Priority {
     }
 }
 
+/// How much work the probing has done.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProbeStats {
+    /// The number of the cells tried with every possible color.
+    pub probes: usize,
+    /// The number of the probes that lead to a contradiction.
+    pub contradictions: usize,
+    /// The number of the pairs of cells tried with every combination of colors
+    /// (only by the `FullProbe2`).
+    pub pair_probes: usize,
+}
+
 type PointImpact<C> = Vec<(C, ProbeResult<Vec<(Point, C)>>)>;
 pub type Impact<B> = Vec<ProbeImpact<<B as Block>::Color>>;
 type OrderedPoints = PQ<Point, Priority, DefaultHashBuilder>;
 
 pub trait ProbeSolver {
     type BlockType: Block;
+    /// The same solver for the black-and-white board the single colored one gets downgraded to.
+    type Binary: 'static + ProbeSolver<BlockType = BinaryBlock>;
 
     fn with_board(board: MutRc<Board<Self::BlockType>>) -> Self;
 
     fn unsolved_cells(&self) -> OrderedPoints;
-    fn propagate_point<S>(
-        &mut self,
-        point: &Point,
-    ) -> Result<Vec<(Point, Priority)>, UnsolvableLine>
+    fn propagate_point<S>(&mut self, point: &Point) -> Result<Vec<(Point, Priority)>, SolveError>
     where
         S: LineSolver<BlockType = Self::BlockType>;
 
-    fn run_unsolved<S>(&mut self) -> Result<Impact<Self::BlockType>, String>
+    fn run_unsolved<S>(&mut self) -> Result<Impact<Self::BlockType>, SolveError>
     where
         S: LineSolver<BlockType = Self::BlockType>,
     {
         self.run::<S>(&mut self.unsolved_cells())
     }
 
-    fn run<S>(&mut self, probes: &mut OrderedPoints) -> Result<Impact<Self::BlockType>, String>
+    fn run<S>(&mut self, probes: &mut OrderedPoints) -> Result<Impact<Self::BlockType>, SolveError>
     where
         S: LineSolver<BlockType = Self::BlockType>;
+
+    /// The work done since the solver was created.
+    fn stats(&self) -> ProbeStats;
+
+    /// Stop probing with the `SolveError::Cancelled` once the token is cancelled.
+    fn set_cancel_token(&mut self, token: CancelToken);
+
+    /// Probe the cells with the given number of threads (only with the `threaded` feature).
+    /// Ignored by the solvers probing on the single thread.
+    fn set_threads(&mut self, _threads: usize) {}
 }
 
 #[derive(Debug)]
@@ -93,6 +113,11 @@ where
     board: MutRc<Board<B>>,
     low_threshold: Priority,
     propagation_solver: propagation::Solver<B>,
+    stats: ProbeStats,
+    cancel: CancelToken,
+    threads: usize,
+    #[cfg(feature = "threaded")]
+    workers: Vec<ProbeWorker<B>>,
 }
 
 fn low_priority_threshold() -> Priority {
@@ -103,11 +128,15 @@ fn low_priority_threshold() -> Priority {
         .into()
 }
 
+/// How many cells every thread probes between the checks for contradiction.
+const PROBES_PER_THREAD: usize = 4;
+
 impl<B> ProbeSolver for FullProbe1<B>
 where
     B: Block,
 {
     type BlockType = B;
+    type Binary = FullProbe1<BinaryBlock>;
 
     fn with_board(board: MutRc<Board<B>>) -> Self {
         let propagation_solver = propagation::Solver::with_cache(MutRc::clone(&board));
@@ -115,6 +144,11 @@ where
             board,
             low_threshold: low_priority_threshold(),
             propagation_solver,
+            stats: ProbeStats::default(),
+            cancel: CancelToken::default(),
+            threads: 1,
+            #[cfg(feature = "threaded")]
+            workers: vec![],
         }
     }
 
@@ -141,10 +175,7 @@ where
         queue
     }
 
-    fn propagate_point<S>(
-        &mut self,
-        point: &Point,
-    ) -> Result<Vec<(Point, Priority)>, UnsolvableLine>
+    fn propagate_point<S>(&mut self, point: &Point) -> Result<Vec<(Point, Priority)>, SolveError>
     where
         S: LineSolver<BlockType = B>,
     {
@@ -167,26 +198,46 @@ where
             .collect())
     }
 
-    fn run<S>(&mut self, probes: &mut OrderedPoints) -> Result<Impact<Self::BlockType>, String>
+    fn run<S>(&mut self, probes: &mut OrderedPoints) -> Result<Impact<Self::BlockType>, SolveError>
     where
         S: LineSolver<BlockType = B>,
     {
         let mut contradictions_number = 0;
 
-        let impact =
-            loop {
-                let mut impact = Vec::new();
+        let impact = loop {
+            let mut impact = Vec::new();
 
-                if self.is_solved() {
-                    break impact;
+            if self.is_solved() {
+                break impact;
+            }
+
+            let mut false_probes = None;
+            let mut probe_counter = 0_u32;
+
+            while !probes.is_empty() {
+                if self.cancel.is_cancelled() {
+                    info!("Probing cancelled");
+                    return Err(SolveError::Cancelled);
                 }
 
-                let mut false_probes = None;
-                let mut probe_counter = 0_u32;
+                let mut batch = Vec::with_capacity(self.batch_size());
+                while batch.len() < self.batch_size() {
+                    match probes.pop() {
+                        Some(probe) => batch.push(probe),
+                        None => break,
+                    }
+                }
 
-                while let Some((point, priority)) = probes.pop() {
-                    probe_counter += 1;
+                let points: Vec<_> = batch
+                    .iter()
+                    .filter(|&&(_point, priority)| priority >= self.low_threshold)
+                    .map(|&(point, _priority)| point)
+                    .collect();
+                let mut batch_results = self.probe_all::<S>(&points).into_iter();
+                let mut batch = batch.into_iter();
 
+                for (point, priority) in &mut batch {
+                    probe_counter += 1;
                     debug!(
                         "Trying probe #{} {:?} with priority {:?}",
                         probe_counter, point, priority
@@ -203,15 +254,19 @@ where
                         continue;
                     }
 
-                    let probe_results = self.probe::<S>(point);
+                    self.stats.probes += 1;
+                    let probe_results = batch_results
+                        .next()
+                        .expect("Every cell of the batch should be probed");
                     let (contradictions, non_contradictions): (Vec<_>, Vec<_>) = probe_results
                         .into_iter()
                         .partition(|(_color, res)| res.is_contradiction());
 
                     if !contradictions.is_empty() {
-                        let bad_colors = contradictions
+                        let bad_colors: Vec<_> = contradictions
                             .into_iter()
-                            .map(|(color, _should_be_none)| color);
+                            .map(|(color, _should_be_none)| color)
+                            .collect();
 
                         false_probes = Some((point, bad_colors));
                         break;
@@ -227,29 +282,56 @@ where
                     }));
                 }
 
-                if let Some((contradiction, colors)) = false_probes {
-                    contradictions_number += 1;
+                if false_probes.is_some() {
+                    // the rest of the batch is outdated after the contradiction
+                    probes.extend(batch);
+                    break;
+                }
+            }
 
-                    for color in colors {
-                        Board::unset_color_with_callback(&self.board, &contradiction, &color)?;
-                    }
-                    let new_probes = self.propagate_point::<S>(&contradiction).map_err(|err| {
-                        format!(
-                            "Error while propagating contradicted values in {:?}: {:?}",
-                            contradiction, err
-                        )
-                    })?;
-                    probes.extend(new_probes);
-                } else {
-                    break impact;
+            if let Some((contradiction, colors)) = false_probes {
+                contradictions_number += 1;
+                self.stats.contradictions += 1;
+
+                for color in colors {
+                    Board::unset_color_with_callback(&self.board, &contradiction, &color)?;
                 }
-            };
+                let new_probes = self.propagate_point::<S>(&contradiction).map_err(|err| {
+                    info!(
+                        "Error while propagating contradicted values in {:?}: {}",
+                        contradiction, err
+                    );
+                    err
+                })?;
+                probes.extend(new_probes);
+            } else {
+                break impact;
+            }
+        };
 
         if contradictions_number > 0 {
             info!("Contradictions found: {}", contradictions_number);
         }
         Ok(impact)
     }
+
+    fn stats(&self) -> ProbeStats {
+        self.stats
+    }
+
+    fn set_cancel_token(&mut self, token: CancelToken) {
+        self.cancel = token;
+    }
+
+    #[allow(unused_variables)]
+    fn set_threads(&mut self, threads: usize) {
+        #[cfg(feature = "threaded")]
+        {
+            self.threads = threads.max(1);
+            // the extra threads stop once their workers are dropped
+            self.workers.truncate(self.threads);
+        }
+    }
 }
 
 enum ProbeResult<PropagationResult> {
@@ -287,7 +369,7 @@ where
         self.board.read()
     }
 
-    fn run_propagation<S>(&mut self, point: &Point) -> Result<Vec<Point>, UnsolvableLine>
+    fn run_propagation<S>(&mut self, point: &Point) -> Result<Vec<Point>, SolveError>
     where
         S: LineSolver<BlockType = B>,
     {
@@ -298,51 +380,536 @@ where
         self.board().is_solved_full()
     }
 
-    /// Try every color for given cell
-    /// and return the number of solved cells (Some) or contradiction (None)
-    fn probe<S>(&mut self, point: Point) -> PointImpact<B::Color>
+    /// How many cells to probe before merging the results.
+    fn batch_size(&self) -> usize {
+        if self.threads > 1 {
+            self.threads * PROBES_PER_THREAD
+        } else {
+            1
+        }
+    }
+
+    /// Probe every cell with all the possible colors on the same state of the board.
+    fn probe_all<S>(&mut self, points: &[Point]) -> Vec<PointImpact<B::Color>>
     where
         S: LineSolver<BlockType = B>,
     {
-        if self.board().cell(&point).is_solved() {
-            info!("Probing expired! {:?}", point);
+        #[cfg(feature = "threaded")]
+        {
+            if self.threads > 1 && points.len() > 1 {
+                return self.probe_parallel::<S>(points);
+            }
         }
 
-        let vars = self.board().cell(&point).variants();
-        debug!("Probing {:?} for variants: {:?}", point, vars);
+        points
+            .iter()
+            .map(|&point| {
+                let (board, propagation_solver) = (&self.board, &mut self.propagation_solver);
+                probe_cell(
+                    board,
+                    |point| propagation_solver.run::<S>(Some(point)),
+                    point,
+                )
+            })
+            .collect()
+    }
 
-        vars.into_iter()
-            .map(|assumption| {
-                let save = self.board().make_snapshot();
-                Board::set_color_with_callback(&self.board, &point, &assumption);
+    /// Split the cells between the threads, every one probes on its own copy of the board.
+    /// The callbacks of the board are not called for these probes.
+    #[cfg(feature = "threaded")]
+    fn probe_parallel<S>(&mut self, points: &[Point]) -> Vec<PointImpact<B::Color>>
+    where
+        S: LineSolver<BlockType = B>,
+    {
+        let snapshot = self.board().make_snapshot();
+        while self.workers.len() < self.threads {
+            let worker = ProbeWorker::spawn(self.workers.len(), self.board().clone());
+            self.workers.push(worker);
+        }
 
-                let solved = self.run_propagation::<S>(&point);
+        let chunk_size = (points.len() + self.threads - 1) / self.threads;
+        let busy: Vec<_> = points
+            .chunks(chunk_size)
+            .zip(&self.workers)
+            .map(|(chunk, worker)| {
+                worker.probe(
+                    snapshot.clone(),
+                    chunk.to_vec(),
+                    propagation::Solver::run::<S>,
+                );
+                worker
+            })
+            .collect();
 
-                let impact = solved.ok().map_or_else(
-                    || {
-                        debug!("Contradiction found! {:?}: {:?}", point, assumption);
-                        ProbeResult::Contradiction
-                    },
-                    |new_cells| {
-                        // get the color of every changed cell before restoring
-                        let new_cells: Vec<_> = new_cells
-                            .into_iter()
-                            .map(|point| (point, self.board().cell(&point)))
-                            .collect();
+        let mut results = Vec::with_capacity(points.len());
+        for worker in busy {
+            results.extend(worker.impact());
+        }
+        results
+    }
+}
 
-                        if !new_cells.is_empty() {
-                            debug!(
-                                "Probing {:?}: {:?} brings some new info: {:?}",
-                                point, assumption, new_cells
-                            );
+/// Try every color for given cell
+/// and return the number of solved cells (Some) or contradiction (None)
+fn probe_cell<B, F>(
+    board: &MutRc<Board<B>>,
+    mut propagate: F,
+    point: Point,
+) -> PointImpact<B::Color>
+where
+    B: Block,
+    F: FnMut(Point) -> Result<Vec<Point>, SolveError>,
+{
+    if board.read().cell(&point).is_solved() {
+        info!("Probing expired! {:?}", point);
+    }
+
+    let vars = board.read().cell(&point).variants();
+    debug!("Probing {:?} for variants: {:?}", point, vars);
+
+    vars.into_iter()
+        .map(|assumption| {
+            let save = board.read().make_snapshot();
+            Board::set_color_with_callback(board, &point, &assumption);
+
+            let solved = propagate(point);
+
+            let impact = solved.ok().map_or_else(
+                || {
+                    debug!("Contradiction found! {:?}: {:?}", point, assumption);
+                    ProbeResult::Contradiction
+                },
+                |new_cells| {
+                    // get the color of every changed cell before restoring
+                    let new_cells: Vec<_> = new_cells
+                        .into_iter()
+                        .map(|point| (point, board.read().cell(&point)))
+                        .collect();
+
+                    if !new_cells.is_empty() {
+                        debug!(
+                            "Probing {:?}: {:?} brings some new info: {:?}",
+                            point, assumption, new_cells
+                        );
+                    }
+                    ProbeResult::NewInfo(new_cells)
+                },
+            );
+            Board::restore_with_callback(board, save);
+
+            (assumption, impact)
+        })
+        .collect()
+}
+
+/// How many cells with the highest priority are combined into the pairs by the `FullProbe2`.
+const PAIR_CANDIDATES: usize = 16;
+
+/// Probe the pairs of cells when the single probes give no contradictions.
+///
+/// Every combination of colors of both cells is propagated.
+/// The colors of any cell that do not appear in all the consistent combinations get excluded.
+#[derive(Debug)]
+pub struct FullProbe2<B>
+where
+    B: Block,
+{
+    probe1: FullProbe1<B>,
+    cancel: CancelToken,
+    pair_probes: usize,
+}
+
+impl<B> ProbeSolver for FullProbe2<B>
+where
+    B: Block,
+{
+    type BlockType = B;
+    type Binary = FullProbe2<BinaryBlock>;
+
+    fn with_board(board: MutRc<Board<B>>) -> Self {
+        Self {
+            probe1: FullProbe1::with_board(board),
+            cancel: CancelToken::default(),
+            pair_probes: 0,
+        }
+    }
+
+    fn unsolved_cells(&self) -> OrderedPoints {
+        self.probe1.unsolved_cells()
+    }
+
+    fn propagate_point<S>(&mut self, point: &Point) -> Result<Vec<(Point, Priority)>, SolveError>
+    where
+        S: LineSolver<BlockType = B>,
+    {
+        self.probe1.propagate_point::<S>(point)
+    }
+
+    fn run<S>(&mut self, probes: &mut OrderedPoints) -> Result<Impact<Self::BlockType>, SolveError>
+    where
+        S: LineSolver<BlockType = B>,
+    {
+        let mut impact = self.probe1.run::<S>(probes)?;
+        while !self.probe1.is_solved() {
+            let candidates = Self::candidates(&impact);
+            if !self.probe_pairs::<S>(&candidates)? {
+                break;
+            }
+            impact = self.probe1.run_unsolved::<S>()?;
+        }
+
+        Ok(impact)
+    }
+
+    fn stats(&self) -> ProbeStats {
+        ProbeStats {
+            pair_probes: self.pair_probes,
+            ..self.probe1.stats()
+        }
+    }
+
+    fn set_cancel_token(&mut self, token: CancelToken) {
+        self.probe1.set_cancel_token(token.clone());
+        self.cancel = token;
+    }
+
+    fn set_threads(&mut self, threads: usize) {
+        self.probe1.set_threads(threads);
+    }
+}
+
+impl<B> FullProbe2<B>
+where
+    B: Block,
+{
+    /// The unsolved cells with the highest probing priority.
+    fn candidates(impact: &[ProbeImpact<B::Color>]) -> Vec<Point> {
+        let mut by_priority: Vec<_> = impact
+            .iter()
+            .map(|probe| (probe.probe_priority, probe.point))
+            .collect();
+        by_priority.sort_by(|a, b| b.cmp(a));
+
+        let mut candidates = Vec::with_capacity(PAIR_CANDIDATES);
+        for (_priority, point) in by_priority {
+            if candidates.len() >= PAIR_CANDIDATES {
+                break;
+            }
+            if !candidates.contains(&point) {
+                candidates.push(point);
+            }
+        }
+        candidates
+    }
+
+    /// Try every pair of the cells until some colors get excluded.
+    /// Return whether the board has changed.
+    fn probe_pairs<S>(&mut self, candidates: &[Point]) -> Result<bool, SolveError>
+    where
+        S: LineSolver<BlockType = B>,
+    {
+        for (i, first) in candidates.iter().enumerate() {
+            for second in &candidates[i + 1..] {
+                if self.cancel.is_cancelled() {
+                    info!("Probing cancelled");
+                    return Err(SolveError::Cancelled);
+                }
+
+                self.pair_probes += 1;
+                let possible = self.probe_pair::<S>(*first, *second)?;
+                let changed = self.exclude_impossible::<S>(&possible)?;
+                if changed {
+                    info!(
+                        "Pair probing {:?} and {:?} excluded some colors",
+                        first, second
+                    );
+                    return Ok(true);
+                }
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Propagate every combination of colors of the two cells
+    /// and collect the colors every cell can have in any of them.
+    fn probe_pair<S>(
+        &mut self,
+        first: Point,
+        second: Point,
+    ) -> Result<Vec<Vec<B::Color>>, SolveError>
+    where
+        S: LineSolver<BlockType = B>,
+    {
+        let board = MutRc::clone(&self.probe1.board);
+        let save = board.read().make_snapshot();
+        let mut possible = vec![vec![]; save.len()];
+        let mut any_consistent = false;
+
+        let first_colors = board.read().cell(&first).variants();
+        for first_color in first_colors {
+            Board::set_color_with_callback(&board, &first, &first_color);
+            if self.probe1.run_propagation::<S>(&first).is_ok() {
+                let first_save = board.read().make_snapshot();
+                let second_colors = board.read().cell(&second).variants();
+                for second_color in second_colors {
+                    Board::set_color_with_callback(&board, &second, &second_color);
+                    if self.probe1.run_propagation::<S>(&second).is_ok() {
+                        any_consistent = true;
+                        let cells = board.read().make_snapshot();
+                        for (colors, cell) in possible.iter_mut().zip(cells) {
+                            for color in cell.variants() {
+                                if !colors.contains(&color) {
+                                    colors.push(color);
+                                }
+                            }
                         }
-                        ProbeResult::NewInfo(new_cells)
-                    },
-                );
-                Board::restore_with_callback(&self.board, save);
+                    }
+                    Board::restore_with_callback(&board, first_save.clone());
+                }
+            }
+            Board::restore_with_callback(&board, save.clone());
+        }
+
+        if !any_consistent {
+            debug!("Every color of {:?} and {:?} contradicts", first, second);
+            return Err(SolveError::CellContradiction(first));
+        }
+
+        Ok(possible)
+    }
+
+    /// Remove the colors that were not possible in any combination and propagate the changes.
+    /// Return whether any color was removed.
+    fn exclude_impossible<S>(&mut self, possible: &[Vec<B::Color>]) -> Result<bool, SolveError>
+    where
+        S: LineSolver<BlockType = B>,
+    {
+        let width = self.probe1.board().width();
+        let cells = self.probe1.board().make_snapshot();
+
+        let mut changed = vec![];
+        for (index, (cell, colors)) in cells.into_iter().zip(possible).enumerate() {
+            let point = Point::new(index % width, index / width);
+            let mut excluded = false;
+            for color in cell.variants() {
+                if !colors.contains(&color) {
+                    Board::unset_color_with_callback(&self.probe1.board, &point, &color)?;
+                    excluded = true;
+                }
+            }
+            if excluded {
+                changed.push(point);
+            }
+        }
+
+        if changed.is_empty() {
+            return Ok(false);
+        }
+
+        for point in changed {
+            let _new_probes = self.probe1.propagate_point::<S>(&point)?;
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "threaded")]
+type Propagate<B> =
+    fn(&mut propagation::Solver<B>, Option<Point>) -> Result<Vec<Point>, SolveError>;
+
+#[cfg(feature = "threaded")]
+type ProbeJob<B> = (Vec<<B as Block>::Color>, Vec<Point>, Propagate<B>);
+
+/// The thread probing on its own copy of the board.
+/// It lives as long as the solver does, so the caches are reused between the batches.
+#[cfg(feature = "threaded")]
+#[derive(Debug)]
+struct ProbeWorker<B>
+where
+    B: Block,
+{
+    jobs: mpsc::Sender<ProbeJob<B>>,
+    impact: mpsc::Receiver<Vec<PointImpact<B::Color>>>,
+}
 
-                (assumption, impact)
+#[cfg(feature = "threaded")]
+impl<B> ProbeWorker<B>
+where
+    B: Block,
+{
+    fn spawn(index: usize, board: Board<B>) -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<ProbeJob<B>>();
+        let (impact_sender, impact) = mpsc::channel();
+
+        let _handle = thread::Builder::new()
+            .name(format!("probe-{}", index))
+            .spawn(move || {
+                let board = MutRc::new(board);
+                let mut propagation_solver = propagation::Solver::with_cache(MutRc::clone(&board));
+
+                // the jobs channel gets closed when the worker is dropped
+                for (snapshot, points, propagate) in job_receiver {
+                    Board::restore_with_callback(&board, snapshot);
+                    let impact = points
+                        .into_iter()
+                        .map(|point| {
+                            probe_cell(
+                                &board,
+                                |point| propagate(&mut propagation_solver, Some(point)),
+                                point,
+                            )
+                        })
+                        .collect();
+                    if impact_sender.send(impact).is_err() {
+                        break;
+                    }
+                }
             })
-            .collect()
+            .expect("Cannot spawn the probing thread");
+
+        Self { jobs, impact }
+    }
+
+    /// Start probing the cells on the given state of the board.
+    fn probe(&self, snapshot: Vec<B::Color>, points: Vec<Point>, propagate: Propagate<B>) {
+        self.jobs
+            .send((snapshot, points, propagate))
+            .expect("The probing thread panicked");
+    }
+
+    /// Wait for the results of the last `probe`.
+    fn impact(&self) -> Vec<PointImpact<B::Color>> {
+        self.impact.recv().expect("The probing thread panicked")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block::{binary::BinaryBlock, Color},
+        board::Board,
+        solver::line::DynamicSolver,
+        test_utils::propagated,
+        utils::rc::MutRc,
+    };
+
+    use super::{FullProbe1, FullProbe2, ProbeSolver};
+
+    fn probe(board: &MutRc<Board<BinaryBlock>>, threads: usize) -> (usize, usize) {
+        let mut solver = FullProbe1::with_board(MutRc::clone(board));
+        solver.set_threads(threads);
+        let impact = solver.run_unsolved::<DynamicSolver<_>>().unwrap();
+        (impact.len(), solver.stats().contradictions)
+    }
+
+    #[test]
+    fn solved_with_contradictions() {
+        let board = propagated(
+            &[&[1, 1], &[2], &[2], &[1, 2], &[1]],
+            &[&[1], &[1, 1], &[1], &[3], &[3]],
+        );
+        let (impact, contradictions) = probe(&board, 1);
+        assert!(board.read().is_solved_full());
+        assert_eq!(impact, 0);
+        assert!(contradictions > 0);
+    }
+
+    #[test]
+    fn ambiguous_impact() {
+        let board = propagated(&[&[1], &[1]], &[&[1], &[1]]);
+        let (impact, contradictions) = probe(&board, 1);
+        assert!(!board.read().is_solved_full());
+        // both colors of every cell
+        assert_eq!(impact, 8);
+        assert_eq!(contradictions, 0);
+    }
+
+    #[test]
+    fn pairs_solve_more() {
+        let rows: &[&[usize]] = &[
+            &[3, 1],
+            &[1, 1, 1],
+            &[1, 2],
+            &[3, 2],
+            &[1, 2],
+            &[1, 1],
+            &[3, 1],
+            &[1, 1],
+        ];
+        let columns: &[&[usize]] = &[
+            &[1, 1, 1],
+            &[2, 3],
+            &[1, 1, 1],
+            &[2, 2],
+            &[1, 1],
+            &[3],
+            &[2, 3],
+            &[1],
+        ];
+
+        let single = propagated(rows, columns);
+        let _ = probe(&single, 1);
+        let single_unsolved = single.read().unsolved_cells().count();
+        assert!(single_unsolved > 0);
+
+        let pairs = propagated(rows, columns);
+        let mut solver = FullProbe2::with_board(MutRc::clone(&pairs));
+        let _impact = solver.run_unsolved::<DynamicSolver<_>>().unwrap();
+        assert!(solver.stats().pair_probes > 0);
+        assert!(pairs.read().unsolved_cells().count() < single_unsolved);
+
+        // the pairs only add to what the single probes found
+        let (single, pairs) = (single.read().make_snapshot(), pairs.read().make_snapshot());
+        for (single, pairs) in single.into_iter().zip(pairs) {
+            if single.is_solved() {
+                assert_eq!(single, pairs);
+            }
+        }
+    }
+
+    #[test]
+    fn pairs_on_solvable() {
+        let board = propagated(
+            &[&[1, 1], &[2], &[2], &[1, 2], &[1]],
+            &[&[1], &[1, 1], &[1], &[3], &[3]],
+        );
+        let mut solver = FullProbe2::with_board(MutRc::clone(&board));
+        let impact = solver.run_unsolved::<DynamicSolver<_>>().unwrap();
+        assert!(board.read().is_solved_full());
+        assert!(impact.is_empty());
+        assert_eq!(solver.stats().pair_probes, 0);
+    }
+
+    #[cfg(feature = "threaded")]
+    #[test]
+    fn parallel_same_as_serial() {
+        type Clues = &'static [&'static [usize]];
+
+        let puzzles: &[(Clues, Clues)] = &[
+            (
+                &[&[1, 1], &[2], &[2], &[1, 2], &[1]],
+                &[&[1], &[1, 1], &[1], &[3], &[3]],
+            ),
+            (&[&[1], &[1], &[1], &[1]], &[&[1], &[1], &[1], &[1]]),
+            (
+                &[&[2, 1], &[1, 1], &[1, 2], &[3], &[1, 1], &[2]],
+                &[&[1, 2], &[2, 1], &[1, 1], &[4], &[1], &[1, 1]],
+            ),
+        ];
+
+        for &(rows, columns) in puzzles {
+            let serial = propagated(rows, columns);
+            let serial_result = probe(&serial, 1);
+
+            let parallel = propagated(rows, columns);
+            let parallel_result = probe(&parallel, 3);
+
+            assert_eq!(
+                serial.read().make_snapshot(),
+                parallel.read().make_snapshot()
+            );
+            assert_eq!(serial_result, parallel_result);
+        }
     }
 }
@@ -0,0 +1,91 @@
+//! Observing and stopping the long running solvers.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::utils::rc::ReadRc;
+
+pub use self::callbacks::ProgressCallback;
+
+/// The handle to stop the solver from any thread.
+///
+/// All the clones of the token share the same state,
+/// so keep one of them to cancel the solver that got the other.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the solver to stop as soon as possible.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The state of the search reported before every guess.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// The depth of the guess in the search tree.
+    pub depth: usize,
+    /// The number of the guesses made so far.
+    pub nodes_explored: usize,
+    /// The solution rate of the board before the guess, from 0 to 1.
+    pub solution_rate: f64,
+}
+
+#[cfg(not(feature = "threaded"))]
+mod callbacks {
+    use super::Progress;
+
+    pub trait ProgressCallback: Fn(Progress) {}
+
+    impl<F> ProgressCallback for F where F: Fn(Progress) {}
+}
+
+#[cfg(feature = "threaded")]
+mod callbacks {
+    use super::Progress;
+
+    pub trait ProgressCallback: Fn(Progress) + Send + Sync {}
+
+    impl<F> ProgressCallback for F where F: Fn(Progress) + Send + Sync {}
+}
+
+/// The optional progress callback shared between the solvers.
+#[derive(Clone, Default)]
+pub(crate) struct ProgressReporter(Option<ReadRc<dyn ProgressCallback>>);
+
+impl ProgressReporter {
+    pub fn new<CB: ProgressCallback + 'static>(f: CB) -> Self {
+        let callback: ReadRc<dyn ProgressCallback> = ReadRc::new(f);
+        Self(Some(callback))
+    }
+
+    pub fn report(&self, progress: Progress) {
+        if let Some(callback) = &self.0 {
+            callback(progress);
+        }
+    }
+}
+
+impl fmt::Debug for ProgressReporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ProgressReporter({})",
+            if self.0.is_some() { "set" } else { "unset" }
+        )
+    }
+}
@@ -7,7 +7,10 @@ use crate::{
     block::{Block, Line},
     board::{Board, LineDirection, LinePosition, Point},
     cache::{cache_info, Cached, GrowableCache},
-    solver::line::{self, LineSolver, UnsolvableLine},
+    solver::{
+        line::{self, LineSolver, UnsolvableLine},
+        SolveError,
+    },
     utils::{
         abs_sub,
         rc::{MutRc, ReadRc, ReadRef},
@@ -214,7 +217,7 @@ where
         }
     }
 
-    pub fn run<S>(&mut self, point: Option<Point>) -> Result<Vec<Point>, UnsolvableLine>
+    pub fn run<S>(&mut self, point: Option<Point>) -> Result<Vec<Point>, SolveError>
     where
         S: LineSolver<BlockType = B>,
     {
@@ -231,7 +234,7 @@ where
         }
     }
 
-    fn run_jobs<S, Q>(&mut self, mut queue: Q) -> Result<Vec<Point>, UnsolvableLine>
+    fn run_jobs<S, Q>(&mut self, mut queue: Q) -> Result<Vec<Point>, SolveError>
     where
         S: LineSolver<BlockType = B>,
         Q: JobQueue<LinePosition>,
@@ -264,10 +267,7 @@ where
     /// If the line gets partially solved, put the crossed lines into queue.
     ///
     /// Return the list of indexes which was updated during this solution.
-    fn update_line<S>(
-        &mut self,
-        position: LinePosition,
-    ) -> Result<Option<Vec<usize>>, UnsolvableLine>
+    fn update_line<S>(&mut self, position: LinePosition) -> Result<Option<Vec<usize>>, SolveError>
     where
         S: LineSolver<BlockType = B>,
     {
@@ -296,7 +296,8 @@ where
 
             self.set_cached_solution(position.direction(), cache_key, value.clone());
             value
-        })?;
+        });
+        let solution = solution.map_err(|_| SolveError::LineContradiction(position))?;
 
         let indexes = self.update_solved(position, &line, &solution);
 
@@ -14,7 +14,7 @@ use varisat::{solver::Solver, CnfFormula, ExtendFormula, Lit, Var};
 use crate::{
     block::{base::color::ColorId, Block, Color, Description},
     board::Point,
-    solver::probing::Impact,
+    solver::{line::Automaton, probing::Impact},
     utils::{pair_combinations, product, rc::ReadRc},
 };
 
@@ -79,6 +79,49 @@ impl LinePositions {
     }
 }
 
+/// The blotted line has no known block positions,
+/// so the automaton of its clue gets encoded instead:
+/// the state variable is true when the automaton comes into that state
+/// before reading the cell at the given position.
+#[derive(Debug)]
+struct LineStates<C> {
+    automaton: Automaton<C>,
+    /// For every position (including the one after the last cell) the variables
+    /// of the states that can lead to the end of the line.
+    vars: Vec<HashMap<usize, Var>>,
+}
+
+impl<C> LineStates<C>
+where
+    C: Color,
+{
+    fn new(automaton: Automaton<C>, line_length: usize, formula: &mut CnfFormula) -> Self {
+        let any_color = |_position, _color| true;
+        let reachable = automaton.reachable(line_length, any_color);
+        let finishing = automaton.finishing(line_length, any_color);
+
+        let vars = reachable
+            .iter()
+            .zip(&finishing)
+            .map(|(reachable, finishing)| {
+                reachable
+                    .iter()
+                    .zip(finishing)
+                    .enumerate()
+                    .filter(|(_state, (&reachable, &finishing))| reachable && finishing)
+                    .map(|(state, _)| (state, formula.new_var()))
+                    .collect()
+            })
+            .collect();
+
+        Self { automaton, vars }
+    }
+
+    fn vars_count(&self) -> usize {
+        self.vars.iter().map(HashMap::len).sum()
+    }
+}
+
 #[derive(Debug)]
 pub struct ClauseGenerator<B>
 where
@@ -86,6 +129,8 @@ where
 {
     columns_vars: Vec<LinePositions>,
     rows_vars: Vec<LinePositions>,
+    columns_states: Vec<Option<LineStates<B::Color>>>,
+    rows_states: Vec<Option<LineStates<B::Color>>>,
     cell_vars: Vec<Vec<HashMap<ColorId, Var>>>,
     cells: Vec<B::Color>,
     width: usize,
@@ -115,7 +160,11 @@ where
         rows: &[ReadRc<Description<B>>],
         cells: Vec<B::Color>,
     ) -> Self {
-        let mut block_colors: HashSet<_> = rows.iter().flat_map(|row| row.colors()).collect();
+        let mut block_colors: HashSet<_> = rows
+            .iter()
+            .chain(columns)
+            .flat_map(|line| line.colors())
+            .collect();
         if block_colors.is_empty() {
             let _ = block_colors.insert(Self::BLACK_COLOR);
         }
@@ -126,6 +175,8 @@ where
         let mut formula = CnfFormula::new();
         let columns_vars = Self::clues_vars(columns, height, &mut formula);
         let rows_vars = Self::clues_vars(rows, width, &mut formula);
+        let columns_states = Self::clues_states(columns, height, &mut formula);
+        let rows_states = Self::clues_states(rows, width, &mut formula);
         let clues_vars = formula.var_count();
 
         let cell_vars = cells
@@ -152,6 +203,8 @@ where
         Self {
             columns_vars,
             rows_vars,
+            columns_states,
+            rows_states,
             cell_vars,
             cells,
             width,
@@ -172,7 +225,15 @@ where
             .flat_map(|col| col.iter().map(|block| block.len()))
             .sum();
 
-        col_vars + row_vars
+        let states_vars: usize = self
+            .columns_states
+            .iter()
+            .chain(&self.rows_states)
+            .flatten()
+            .map(LineStates::vars_count)
+            .sum();
+
+        col_vars + row_vars + states_vars
     }
 
     fn clues_vars(
@@ -183,6 +244,11 @@ where
         clues
             .iter()
             .map(|clue| {
+                if clue.is_blotted() {
+                    // encoded with the `LineStates`
+                    return LinePositions(vec![]);
+                }
+
                 let positions = clue.positions_number(line_length);
                 LinePositions(
                     clue.block_starts()
@@ -207,6 +273,24 @@ where
             .collect()
     }
 
+    fn clues_states(
+        clues: &[ReadRc<Description<B>>],
+        line_length: usize,
+        formula: &mut CnfFormula,
+    ) -> Vec<Option<LineStates<B::Color>>> {
+        clues
+            .iter()
+            .map(|clue| {
+                if clue.is_blotted() {
+                    let automaton = Automaton::with_description(clue);
+                    Some(LineStates::new(automaton, line_length, formula))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
     fn block_positions_clause(positions: &BlockPositions) -> Vec<Lit> {
         // 1. Каждый блок, объявленный в строке или столбце обязан появиться хотя-бы в одной позиции.
         // Этому соответствует клоз вида (X1 V X2 V… XN),
@@ -296,7 +380,19 @@ where
                     .chain(once(color_var.negative()))
                     .collect();
 
-                once(column_clause).chain(once(row_clause))
+                // the blotted lines get supported by their states
+                let column_clause = if self.columns_states[cell_point.x].is_some() {
+                    None
+                } else {
+                    Some(column_clause)
+                };
+                let row_clause = if self.rows_states[cell_point.y].is_some() {
+                    None
+                } else {
+                    Some(row_clause)
+                };
+
+                column_clause.into_iter().chain(row_clause)
             })
             .collect()
     }
@@ -322,6 +418,96 @@ where
             .collect()
     }
 
+    /// The automaton is in the single state at every position
+    /// and moves to the next state reading the color of the cell.
+    fn line_states_clauses(
+        states: &LineStates<B::Color>,
+        cells: &[&HashMap<ColorId, Var>],
+    ) -> Vec<Vec<Lit>> {
+        let mut clauses = vec![];
+        for position_vars in &states.vars {
+            let vars: Vec<_> = position_vars.values().copied().collect();
+            clauses.push(at_least_one(vars.iter().copied()));
+            clauses.extend(at_most_one(&vars));
+        }
+
+        for (position, cell_vars) in cells.iter().enumerate() {
+            let current = &states.vars[position];
+            let next = &states.vars[position + 1];
+
+            for (&from, from_var) in current {
+                for (&to, to_var) in next {
+                    let both = vec![from_var.negative(), to_var.negative()];
+                    let colors: Vec<_> = states
+                        .automaton
+                        .transitions(from)
+                        .iter()
+                        .filter(|&&(_, state)| state == to)
+                        .map(|&(color, _)| Self::get_id(color))
+                        .collect();
+
+                    if colors.is_empty() {
+                        clauses.push(both);
+                    } else if colors.contains(&None) {
+                        // the cell can be blank, so it cannot be the other colors
+                        clauses.extend(
+                            cell_vars
+                                .iter()
+                                .filter(|(&color_id, _)| !colors.contains(&Some(color_id)))
+                                .map(|(_, var)| {
+                                    both.iter().copied().chain(once(var.negative())).collect()
+                                }),
+                        );
+                    } else {
+                        clauses.push(
+                            both.iter()
+                                .copied()
+                                .chain(
+                                    colors
+                                        .iter()
+                                        .flatten()
+                                        .filter_map(|color_id| cell_vars.get(color_id))
+                                        .map(|var| var.positive()),
+                                )
+                                .collect(),
+                        );
+                    }
+                }
+            }
+        }
+        clauses
+    }
+
+    fn states_clauses(&self) -> Vec<Vec<Lit>> {
+        let columns = self
+            .columns_states
+            .iter()
+            .enumerate()
+            .filter_map(|(x, states)| {
+                states.as_ref().map(|states| {
+                    let cells: Vec<_> = (0..self.height)
+                        .map(|y| self.get_vars(Point::new(x, y)))
+                        .collect();
+                    Self::line_states_clauses(states, &cells)
+                })
+            });
+
+        let rows = self
+            .rows_states
+            .iter()
+            .enumerate()
+            .filter_map(|(y, states)| {
+                states.as_ref().map(|states| {
+                    let cells: Vec<_> = (0..self.width)
+                        .map(|x| self.get_vars(Point::new(x, y)))
+                        .collect();
+                    Self::line_states_clauses(states, &cells)
+                })
+            });
+
+        columns.chain(rows).flatten().collect()
+    }
+
     fn get_id(color: B::Color) -> Option<ColorId> {
         if color == Color::blank() {
             return None;
@@ -436,6 +622,7 @@ where
             .chain(color_clauses)
             .chain(space_clauses)
             .chain(point_once_clauses)
+            .chain(self.states_clauses())
             .chain(fixed_points)
     }
 
@@ -570,8 +757,9 @@ where
                     })
                     .collect();
 
-                let fixed_clause: Vec<_> =
-                    colored_cells.iter().map(|&var| var.negative()).collect();
+                // the blotted lines allow the solutions with more colored cells,
+                // so the blank cells should differ too
+                let fixed_clause: Vec<_> = cells.iter().map(|&cell| !cell).collect();
                 solver.add_clause(&fixed_clause);
 
                 colored_cells
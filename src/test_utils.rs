@@ -0,0 +1,37 @@
+//! The boards shared by the tests of several modules.
+
+use crate::{
+    block::{binary::BinaryBlock, Description},
+    board::Board,
+    solver::{self, line::DynamicSolver},
+    utils::rc::MutRc,
+};
+
+/// The black-and-white board with the given clues.
+pub fn board(rows: &[&[usize]], columns: &[&[usize]]) -> MutRc<Board<BinaryBlock>> {
+    let desc = |lines: &[&[usize]]| {
+        lines
+            .iter()
+            .map(|line| Description::new(line.iter().map(|&n| BinaryBlock(n)).collect()))
+            .collect()
+    };
+    MutRc::new(Board::with_descriptions_and_palette(
+        desc(rows),
+        desc(columns),
+        None,
+    ))
+}
+
+/// The same board solved by the line solver as much as possible.
+pub fn propagated(rows: &[&[usize]], columns: &[&[usize]]) -> MutRc<Board<BinaryBlock>> {
+    let board = board(rows, columns);
+    let _solved = solver::propagate::<_, DynamicSolver<_>>(&board).unwrap();
+    board
+}
+
+/// Every row and column has a single cell: the solutions are the permutations.
+pub fn permutations(size: usize) -> MutRc<Board<BinaryBlock>> {
+    let clue: &[usize] = &[1];
+    let clues = vec![clue; size];
+    propagated(&clues, &clues)
+}
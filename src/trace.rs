@@ -0,0 +1,373 @@
+//! Recording the changes the solvers make to the board.
+
+use std::io::{self, Write};
+
+use crate::{
+    block::{base::color::ColorId, Block, Color},
+    board::{Board, LinePosition, Point},
+    utils::rc::{MutRc, WeakRc},
+};
+
+/// The code of the blank cell (the same as in `Board::restore`).
+const BLANK: ColorId = 0;
+
+/// Every cell is represented as the list of its possible colors.
+type Cell = Vec<ColorId>;
+
+/// A single change of the board.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// The line solver updated the line.
+    Line {
+        position: LinePosition,
+        cells: Vec<Cell>,
+    },
+    /// The color was put into the cell to see what happens:
+    /// either a probe or (if other assumptions were made before rolling it back)
+    /// a guess of the search.
+    Assume {
+        point: Point,
+        cell: Cell,
+        guess: bool,
+    },
+    /// The color was excluded from the cell after the contradiction.
+    Exclude { point: Point, cell: Cell },
+    /// The board was rolled back to the saved state.
+    Restore,
+}
+
+#[derive(Debug, Default)]
+struct TraceState {
+    events: Vec<TraceEvent>,
+    /// The indexes of the assumptions not rolled back yet.
+    open_assumptions: Vec<usize>,
+}
+
+impl TraceState {
+    fn assume(&mut self, point: Point, cell: Cell) {
+        if let Some(&parent) = self.open_assumptions.last() {
+            if let Some(TraceEvent::Assume { guess, .. }) = self.events.get_mut(parent) {
+                *guess = true;
+            }
+        }
+
+        self.open_assumptions.push(self.events.len());
+        self.events.push(TraceEvent::Assume {
+            point,
+            cell,
+            guess: false,
+        });
+    }
+
+    fn restore(&mut self) {
+        let _ = self.open_assumptions.pop();
+        self.events.push(TraceEvent::Restore);
+    }
+}
+
+fn cell_ids<C>(cell: C) -> Cell
+where
+    C: Color,
+{
+    cell.variants()
+        .into_iter()
+        .map(|color| {
+            if color == C::blank() {
+                BLANK
+            } else {
+                // the black color of the black-and-white puzzles has no ID
+                color.as_color_id().unwrap_or(1)
+            }
+        })
+        .collect()
+}
+
+/// Records the changes of the board via its callbacks.
+///
+/// ```
+/// use nonogrid::{
+///     solve, trace::SolveTrace, Backend, BinaryBlock, BoardParser, DetectedParser, FullProbe,
+///     LineSolver, RcBoard, SearchOptions,
+/// };
+///
+/// let parser = DetectedParser::with_content("rows\n1\n\ncolumns\n1\n").unwrap();
+/// let board = RcBoard::new(parser.parse::<BinaryBlock>().unwrap());
+/// let trace = SolveTrace::attach(&board);
+///
+/// let options = SearchOptions::default();
+/// solve::<_, LineSolver<_>, FullProbe<_>>(board, Backend::default(), None, None, None, options)
+///     .unwrap();
+/// let mut jsonl = vec![];
+/// trace.write_jsonl(&mut jsonl).unwrap();
+/// assert_eq!(
+///     String::from_utf8(jsonl).unwrap(),
+///     "{\"event\":\"line\",\"direction\":\"row\",\"index\":0,\"cells\":[[1]]}\n"
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct SolveTrace {
+    state: MutRc<TraceState>,
+}
+
+impl SolveTrace {
+    /// Start recording the changes of the board.
+    /// Any previously set callbacks of the board get replaced.
+    pub fn attach<B>(board: &MutRc<Board<B>>) -> Self
+    where
+        B: 'static + Block,
+    {
+        let state = MutRc::new(TraceState::default());
+
+        {
+            let mut board_ref = board.write();
+
+            let (weak_board, trace) = (MutRc::downgrade(board), MutRc::clone(&state));
+            board_ref.set_callback_on_set_line(move |is_column, index| {
+                if let Some(board) = weak_board.upgrade() {
+                    let position = if is_column {
+                        LinePosition::Column(index)
+                    } else {
+                        LinePosition::Row(index)
+                    };
+                    let cells = board
+                        .read()
+                        .get_line(position)
+                        .iter()
+                        .map(|&cell| cell_ids(cell))
+                        .collect();
+                    trace
+                        .write()
+                        .events
+                        .push(TraceEvent::Line { position, cells });
+                }
+            });
+
+            let (weak_board, trace) = (MutRc::downgrade(board), MutRc::clone(&state));
+            board_ref.set_callback_on_change_color(move |point| {
+                if let Some(cell) = Self::cell(&weak_board, point) {
+                    trace.write().assume(point, cell);
+                }
+            });
+
+            let (weak_board, trace) = (MutRc::downgrade(board), MutRc::clone(&state));
+            board_ref.set_callback_on_unset_color(move |point| {
+                if let Some(cell) = Self::cell(&weak_board, point) {
+                    trace
+                        .write()
+                        .events
+                        .push(TraceEvent::Exclude { point, cell });
+                }
+            });
+
+            let trace = MutRc::clone(&state);
+            board_ref.set_callback_on_restore(move || trace.write().restore());
+        }
+
+        Self { state }
+    }
+
+    fn cell<B>(board: &WeakRc<Board<B>>, point: Point) -> Option<Cell>
+    where
+        B: Block,
+    {
+        board
+            .upgrade()
+            .map(|board| cell_ids(board.read().cell(&point)))
+    }
+
+    /// The events recorded so far.
+    pub fn events(&self) -> Vec<TraceEvent> {
+        self.state.read().events.clone()
+    }
+
+    /// Write every event as a separate JSON object on its own line.
+    pub fn write_jsonl<W>(&self, mut writer: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        for event in &self.state.read().events {
+            writeln!(writer, "{}", event_json(event))?;
+        }
+        Ok(())
+    }
+}
+
+fn cell_json(cell: &[ColorId]) -> String {
+    let ids: Vec<_> = cell.iter().map(ToString::to_string).collect();
+    format!("[{}]", ids.join(","))
+}
+
+fn event_json(event: &TraceEvent) -> String {
+    match event {
+        TraceEvent::Line { position, cells } => {
+            let direction = match position {
+                LinePosition::Row(_) => "row",
+                LinePosition::Column(_) => "column",
+            };
+            let cells: Vec<_> = cells.iter().map(|cell| cell_json(cell)).collect();
+            format!(
+                r#"{{"event":"line","direction":"{}","index":{},"cells":[{}]}}"#,
+                direction,
+                position.index(),
+                cells.join(",")
+            )
+        }
+        TraceEvent::Assume { point, cell, guess } => format!(
+            r#"{{"event":"{}","x":{},"y":{},"cell":{}}}"#,
+            if *guess { "guess" } else { "probe" },
+            point.x,
+            point.y,
+            cell_json(cell)
+        ),
+        TraceEvent::Exclude { point, cell } => format!(
+            r#"{{"event":"exclude","x":{},"y":{},"cell":{}}}"#,
+            point.x,
+            point.y,
+            cell_json(cell)
+        ),
+        TraceEvent::Restore => r#"{"event":"restore"}"#.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        block::binary::BinaryBlock,
+        board::{Board, LinePosition, Point},
+        solver::{
+            self, backtracking::SearchOptions, line::DynamicSolver, probing::FullProbe1, Backend,
+        },
+        test_utils::board,
+        utils::rc::MutRc,
+    };
+
+    use super::{SolveTrace, TraceEvent};
+
+    fn solve(board: &MutRc<Board<BinaryBlock>>) {
+        // the search is lazy, so go through all the solutions
+        let _found = solver::run::<_, DynamicSolver<_>, FullProbe1<_>>(
+            MutRc::clone(board),
+            Backend::Backtracking,
+            // enough to start the search on the ambiguous puzzles
+            Some(2),
+            None,
+            None,
+            SearchOptions::default(),
+        )
+        .unwrap()
+        .map_or(0, Iterator::count);
+    }
+
+    #[test]
+    fn line_solvable() {
+        let board = board(&[&[1, 1], &[1, 1], &[3]], &[&[3], &[1], &[3]]);
+        let trace = SolveTrace::attach(&board);
+        solve(&board);
+
+        let events = trace.events();
+        assert!(!events.is_empty());
+        assert!(events.iter().all(|event| {
+            if let TraceEvent::Line { .. } = event {
+                true
+            } else {
+                false
+            }
+        }));
+    }
+
+    #[test]
+    fn probes_with_contradiction() {
+        let board = board(
+            &[&[1, 1], &[2], &[2], &[1, 2], &[1]],
+            &[&[1], &[1, 1], &[1], &[3], &[3]],
+        );
+        let trace = SolveTrace::attach(&board);
+        solve(&board);
+        assert!(board.read().is_solved_full());
+
+        let events = trace.events();
+        let excluded: Vec<_> = events
+            .iter()
+            .filter_map(|event| {
+                if let TraceEvent::Exclude { point, cell } = event {
+                    Some((*point, cell.len()))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        assert!(!excluded.is_empty());
+        // the contradiction leaves the single color
+        assert!(excluded.iter().all(|&(_point, colors)| colors == 1));
+
+        // every excluded cell was probed before
+        for (point, _) in excluded {
+            assert!(events.iter().any(|event| {
+                if let TraceEvent::Assume {
+                    point: probed,
+                    guess: false,
+                    ..
+                } = event
+                {
+                    *probed == point
+                } else {
+                    false
+                }
+            }));
+        }
+    }
+
+    #[test]
+    fn search_guesses() {
+        // every guess leaves the smaller puzzle of the same kind to probe
+        let board = board(&[&[1], &[1], &[1], &[1]], &[&[1], &[1], &[1], &[1]]);
+        let trace = SolveTrace::attach(&board);
+        solve(&board);
+
+        let events = trace.events();
+        assert!(events.iter().any(|event| {
+            if let TraceEvent::Assume { guess, .. } = event {
+                *guess
+            } else {
+                false
+            }
+        }));
+        assert!(events.contains(&TraceEvent::Restore));
+    }
+
+    #[test]
+    fn json_lines() {
+        let board = board(&[&[1], &[1]], &[&[1], &[1]]);
+        let trace = SolveTrace::attach(&board);
+        Board::set_color_with_callback(
+            &board,
+            &Point::new(1, 0),
+            &crate::block::binary::BinaryColor::Black,
+        );
+        let first_row = board.read().get_line(LinePosition::Row(0));
+        Board::set_row_with_callback(&board, 1, &first_row);
+        let _ = Board::unset_color_with_callback(
+            &board,
+            &Point::new(0, 0),
+            &crate::block::binary::BinaryColor::Black,
+        );
+        let snapshot = board.read().make_snapshot();
+        Board::restore_with_callback(&board, snapshot);
+
+        let mut jsonl = vec![];
+        trace.write_jsonl(&mut jsonl).unwrap();
+        assert_eq!(
+            String::from_utf8(jsonl).unwrap(),
+            concat!(
+                r#"{"event":"probe","x":1,"y":0,"cell":[1]}"#,
+                "\n",
+                r#"{"event":"line","direction":"row","index":1,"cells":[[0,1],[1]]}"#,
+                "\n",
+                r#"{"event":"exclude","x":0,"y":0,"cell":[0]}"#,
+                "\n",
+                r#"{"event":"restore"}"#,
+                "\n",
+            )
+        );
+    }
+}
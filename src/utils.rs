@@ -2,8 +2,8 @@ use std::{
     cmp::PartialOrd,
     fmt::Display,
     hash::Hash,
-    iter::once,
-    ops::{Range, Sub},
+    iter::{once, successors},
+    ops::{BitAnd, BitOr, Range, Sub},
 };
 
 use hashbrown::{HashMap, HashSet};
@@ -69,16 +69,24 @@ where
     }
 }
 
-pub fn two_powers(num: u32) -> impl Iterator<Item = u32> {
-    (0..num.count_ones()).scan(num, |num, _i| {
-        let prev = *num;
-        *num = prev & (prev - 1);
-        Some(prev - *num)
+/// The bits of the number set to one (the powers of two it consists of).
+pub fn two_powers<T>(num: T) -> impl Iterator<Item = T>
+where
+    T: Copy + PartialEq + From<u8> + BitAnd<Output = T> + Sub<Output = T>,
+{
+    let (zero, one) = (T::from(0), T::from(1));
+    let non_zero = move |&rest: &T| rest != zero;
+    successors(Some(num).filter(non_zero), move |&rest| {
+        Some(rest & (rest - one)).filter(non_zero)
     })
+    .map(move |rest| rest - (rest & (rest - one)))
 }
 
-pub fn from_two_powers(numbers: &[u32]) -> u32 {
-    numbers.iter().fold(0, |acc, &x| acc | x)
+pub fn from_two_powers<T>(numbers: &[T]) -> T
+where
+    T: Copy + From<u8> + BitOr<Output = T>,
+{
+    numbers.iter().fold(T::from(0), |acc, &x| acc | x)
 }
 
 pub fn dedup<T>(vec: impl Iterator<Item = T>) -> Vec<T>
@@ -224,8 +232,7 @@ pub fn split_sections<'a, 'b>(
     include_header: bool,
     first_section: Option<&'b str>,
 ) -> Result<HashMap<&'b str, Vec<&'a str>>, String> {
-    let first_section = first_section.unwrap_or("");
-    let mut section_indexes: HashMap<_, _> = once((first_section, 0)).collect();
+    let mut section_indexes = HashMap::new();
 
     let lines: Vec<_> = text.lines().map(str::trim).collect();
     for &section in section_names {
@@ -239,6 +246,12 @@ pub fn split_sections<'a, 'b>(
         }
     }
 
+    // the text before the first named section (if any)
+    if !section_indexes.values().any(|&start| start == 0) {
+        let first_section = first_section.unwrap_or("");
+        let _ = section_indexes.insert(first_section, 0);
+    }
+
     let eof = lines.len();
     let indexes_with_eof: Vec<_> = section_indexes.values().copied().chain(once(eof)).collect();
 
@@ -262,6 +275,8 @@ pub fn split_sections<'a, 'b>(
     Ok(res)
 }
 
+// the inherent `str::strip_{prefix,suffix}` take precedence on Rust>=1.45
+#[allow(dead_code)]
 pub trait Stripper<P> {
     fn strip_prefix(&self, pattern: P) -> Option<&Self>;
     fn strip_suffix(&self, pattern: P) -> Option<&Self>;
@@ -296,7 +311,6 @@ macro_rules! impl_stripper {
 impl_stripper!(char, len_utf8);
 impl_stripper!(&Self, len);
 
-#[cfg(not(feature = "sat"))]
 pub mod time {
     use std::time::Instant;
 
@@ -312,14 +326,59 @@ pub mod time {
     }
 }
 
+/// Tiny pseudo-random generator to avoid the extra dependencies.
+pub mod random {
+    /// The [xorshift64*](https://en.wikipedia.org/wiki/Xorshift#xorshift*) generator.
+    #[derive(Debug, Clone)]
+    pub struct XorShift {
+        state: u64,
+    }
+
+    impl XorShift {
+        pub fn with_seed(seed: u64) -> Self {
+            // the zero state never changes
+            let state = if seed == 0 {
+                0x9E37_79B9_7F4A_7C15
+            } else {
+                seed
+            };
+            Self { state }
+        }
+
+        pub fn next_u64(&mut self) -> u64 {
+            self.state ^= self.state >> 12;
+            self.state ^= self.state << 25;
+            self.state ^= self.state >> 27;
+            self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+
+        /// The number in the `[0, 1)` range.
+        #[allow(clippy::cast_precision_loss)]
+        pub fn next_f64(&mut self) -> f64 {
+            // 53 bits of the mantissa
+            (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64
+        }
+
+        /// The number in the `[0, upper)` range.
+        #[allow(clippy::cast_possible_truncation)]
+        pub fn below(&mut self, upper: usize) -> usize {
+            assert!(upper > 0, "Cannot choose from the empty range");
+            (self.next_u64() % upper as u64) as usize
+        }
+    }
+}
+
 pub mod rc {
-    pub use inner::{mutate_ref, read_ref, InteriorMutableRef, MutRef, ReadRc, ReadRef};
+    pub use inner::{
+        mutate_ref, read_ref, InteriorMutableRef, MutRef, ReadRc, ReadRef, WeakReadRc,
+    };
 
     #[cfg(feature = "threaded")]
     mod inner {
-        use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+        use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard, Weak};
 
         pub type ReadRc<T> = Arc<T>;
+        pub type WeakReadRc<T> = Weak<T>;
         pub type InteriorMutableRef<T> = RwLock<T>;
         pub type ReadRef<'a, T> = RwLockReadGuard<'a, T>;
         pub type MutRef<'a, T> = RwLockWriteGuard<'a, T>;
@@ -341,10 +400,11 @@ pub mod rc {
     mod inner {
         use std::{
             cell::{Ref, RefCell, RefMut},
-            rc::Rc,
+            rc::{Rc, Weak},
         };
 
         pub type ReadRc<T> = Rc<T>;
+        pub type WeakReadRc<T> = Weak<T>;
         pub type InteriorMutableRef<T> = RefCell<T>;
         pub type ReadRef<'a, T> = Ref<'a, T>;
         pub type MutRef<'a, T> = RefMut<'a, T>;
@@ -373,6 +433,20 @@ pub mod rc {
         pub fn write(&self) -> MutRef<'_, T> {
             mutate_ref(&self.0)
         }
+
+        /// The reference that does not keep the value alive (e.g. to use in its own callbacks).
+        pub fn downgrade(this: &Self) -> WeakRc<T> {
+            WeakRc(ReadRc::downgrade(&this.0))
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct WeakRc<T>(WeakReadRc<InteriorMutableRef<T>>);
+
+    impl<T> WeakRc<T> {
+        pub fn upgrade(&self) -> Option<MutRc<T>> {
+            self.0.upgrade().map(MutRc)
+        }
     }
 
     impl<T> Clone for MutRc<T> {
@@ -384,7 +458,26 @@ pub mod rc {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use super::{random::XorShift, *};
+
+    #[test]
+    fn random_is_reproducible() {
+        let mut first = XorShift::with_seed(42);
+        let mut second = XorShift::with_seed(42);
+        for _ in 0..100 {
+            assert_eq!(first.next_u64(), second.next_u64());
+        }
+    }
+
+    #[test]
+    fn random_in_range() {
+        let mut rng = XorShift::with_seed(0);
+        for _ in 0..1000 {
+            assert!(rng.below(7) < 7);
+            let x = rng.next_f64();
+            assert!((0.0..1.0).contains(&x));
+        }
+    }
 
     #[test]
     fn pad_vector_left() {
@@ -535,6 +628,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn two_powers_wide() {
+        let num = (1_u128 << 127) | (1 << 64) | 1;
+        let powers: Vec<_> = two_powers(num).collect();
+        assert_eq!(powers, vec![1, 1 << 64, 1 << 127]);
+        assert_eq!(from_two_powers(&powers), num);
+    }
+
     #[test]
     fn to_ranges_empty() {
         let vec: Vec<u8> = vec![];
@@ -549,6 +650,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::single_range_in_vec_init)]
     fn to_ranges_unsorted() {
         let vec = vec![9, 5];
         assert_eq!(idx_to_ranges(vec), Some(vec![5..9]));
@@ -596,4 +698,24 @@ mod tests {
             vec![(4, 5), (4, 2), (4, 0), (5, 2), (5, 0), (2, 0)]
         );
     }
+
+    #[test]
+    fn sections_with_first_section() {
+        let text = "intro\nrows\n1\n2\ncolumns\n3";
+        let sections = split_sections(text, &["rows", "columns"], false, None).unwrap();
+
+        assert_eq!(sections.len(), 3);
+        assert_eq!(sections["rows"], ["1", "2"]);
+        assert_eq!(sections["columns"], ["3"]);
+    }
+
+    #[test]
+    fn sections_start_from_the_first_line() {
+        let text = "rows\n1\n2\ncolumns\n3";
+        let sections = split_sections(text, &["rows", "columns"], false, None).unwrap();
+
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections["rows"], ["1", "2"]);
+        assert_eq!(sections["columns"], ["3"]);
+    }
 }